@@ -0,0 +1,525 @@
+//! Draws an [`Engine`] into an egui plot and converts between engine and egui types.
+
+use std::f32::consts::TAU;
+
+use egui::{
+    plot::{
+        self, Line, LineStyle, MarkerShape, PlotBounds, PlotPoint, PlotPoints, PlotUi, Points, Text,
+    },
+    remap, Align2, Color32, Key, KeyboardShortcut, Modifiers, Pos2,
+};
+
+use crate::engine::{
+    color::Color,
+    document::Camera,
+    keys::KeyBinding,
+    measure::Measurement,
+    pos::Pos,
+    shapes::{Construction, Shape},
+    snap::SnapKind,
+    Engine,
+};
+
+impl From<Pos> for Pos2 {
+    fn from(p: Pos) -> Self {
+        Pos2::new(p.x, p.y)
+    }
+}
+
+impl From<Pos2> for Pos {
+    fn from(p: Pos2) -> Self {
+        Pos::new(p.x, p.y)
+    }
+}
+
+impl From<PlotPoint> for Pos {
+    fn from(p: PlotPoint) -> Self {
+        Pos::new(p.x as f32, p.y as f32)
+    }
+}
+
+impl From<Pos> for PlotPoint {
+    fn from(p: Pos) -> Self {
+        PlotPoint::new(p.x, p.y)
+    }
+}
+
+impl From<Color> for Color32 {
+    fn from(c: Color) -> Self {
+        Color32::from_rgba_unmultiplied(c.r, c.g, c.b, c.a)
+    }
+}
+
+impl From<Color32> for Color {
+    fn from(c: Color32) -> Self {
+        let [r, g, b, a] = c.to_srgba_unmultiplied();
+        Color::from_rgba(r, g, b, a)
+    }
+}
+
+impl From<Camera> for PlotBounds {
+    fn from(camera: Camera) -> Self {
+        PlotBounds::from_min_max(
+            [camera.min.x as f64, camera.min.y as f64],
+            [camera.max.x as f64, camera.max.y as f64],
+        )
+    }
+}
+
+impl From<PlotBounds> for Camera {
+    fn from(bounds: PlotBounds) -> Self {
+        Camera {
+            min: Pos::new(bounds.min()[0] as f32, bounds.min()[1] as f32),
+            max: Pos::new(bounds.max()[0] as f32, bounds.max()[1] as f32),
+        }
+    }
+}
+
+/// Keys that can be bound, matched by name without regard to case.
+const KEYS: [Key; 65] = [
+    Key::ArrowDown,
+    Key::ArrowLeft,
+    Key::ArrowRight,
+    Key::ArrowUp,
+    Key::Escape,
+    Key::Tab,
+    Key::Backspace,
+    Key::Enter,
+    Key::Space,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Minus,
+    Key::PlusEquals,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+];
+
+/// The egui shortcut for a binding, or `None` if egui has no key by that name.
+pub fn shortcut(binding: &KeyBinding) -> Option<KeyboardShortcut> {
+    let key = KEYS
+        .into_iter()
+        .find(|key| key.name().eq_ignore_ascii_case(&binding.key))?;
+
+    let modifiers = Modifiers {
+        alt: binding.alt,
+        shift: binding.shift,
+        command: binding.ctrl,
+        ..Default::default()
+    };
+
+    Some(KeyboardShortcut::new(modifiers, key))
+}
+
+/// The width of a screen pixel in plot units. The plot keeps its aspect ratio, so this is
+/// the same in both directions.
+pub fn pixel_size(ui: &PlotUi) -> f32 {
+    ui.transform().dvalue_dpos()[0].abs() as f32
+}
+
+/// Draws the constructions, preview, in-progress points and tool guides of an engine.
+pub fn show(engine: &Engine, ui: &mut PlotUi) {
+    if engine.points.is_empty()
+        && engine.constructions.is_empty()
+        && engine.labelled_points.is_empty()
+        && engine.preview.is_empty()
+        && engine.measurements.is_empty()
+        && engine.view.grid.is_none()
+    {
+        return;
+    }
+
+    if let Some(grid) = engine.view.grid {
+        let bounds = ui.plot_bounds();
+        let color = Color32::from(engine.config.grid_color).gamma_multiply(0.5);
+
+        for (a, b) in grid.lines(
+            Pos::new(bounds.min()[0] as f32, bounds.min()[1] as f32),
+            Pos::new(bounds.max()[0] as f32, bounds.max()[1] as f32),
+        ) {
+            ui.line(segment(a, b).color(color).width(0.5));
+        }
+    }
+
+    let current_color = Color32::from(engine.current_color);
+
+    for construction in &engine.preview {
+        for line in construction_lines(construction, ui) {
+            ui.line(
+                line.color(Color32::from(construction.color).gamma_multiply(0.4))
+                    .style(LineStyle::dashed_loose()),
+            );
+        }
+    }
+
+    for &i in engine
+        .selection
+        .iter()
+        .filter(|_| engine.playback.is_none())
+    {
+        if let Some(construction) = engine.constructions.get(i) {
+            for line in construction_lines(construction, ui) {
+                ui.line(
+                    line.color(Color32::from(construction.color).gamma_multiply(0.3))
+                        .width(construction.width + 4.0),
+                );
+            }
+        }
+    }
+
+    let constructions = match &engine.playback {
+        Some(playback) => {
+            let bounds = ui.plot_bounds();
+
+            playback.constructions(
+                engine,
+                Pos::new(bounds.min()[0] as f32, bounds.min()[1] as f32),
+                Pos::new(bounds.max()[0] as f32, bounds.max()[1] as f32),
+            )
+        }
+        None => engine.visible_constructions().cloned().collect(),
+    };
+
+    for construction in &constructions {
+        for line in construction_lines(construction, ui) {
+            ui.line(line);
+        }
+
+        if engine.view.show_intersections {
+            ui.points(
+                Points::new(
+                    construction
+                        .intersections
+                        .iter()
+                        .map(|point| [point.x as f64, point.y as f64])
+                        .collect::<Vec<[f64; 2]>>(),
+                )
+                .color(engine.config.intersection_color)
+                .name(&construction.layer),
+            );
+        }
+    }
+
+    if let Some(mouse_pos) = ui
+        .pointer_coordinate()
+        .filter(|_| engine.playback.is_none())
+    {
+        let mouse_pos = Pos::from(mouse_pos);
+        let snap = engine.snap(mouse_pos, engine.snap_distance());
+        let snap_pos = snap.map_or(mouse_pos, |snap| snap.pos);
+
+        if let Some(snap) = snap {
+            ui.line(
+                segment(mouse_pos, snap_pos)
+                    .color(current_color.gamma_multiply(0.2))
+                    .style(LineStyle::dotted_loose()),
+            );
+            ui.points(
+                Points::new(vec![[snap_pos.x as f64, snap_pos.y as f64]])
+                    .shape(snap_marker(snap.kind))
+                    .filled(false)
+                    .radius(5.0)
+                    .color(current_color),
+            );
+        } else if engine.points.is_empty() {
+            ui.line(
+                circle(mouse_pos, engine.snap_distance())
+                    .color(current_color.gamma_multiply(0.2))
+                    .style(LineStyle::dotted_loose()),
+            );
+        }
+
+        if engine.current_transform.is_some() {
+            for shape in engine.transform_preview(snap_pos) {
+                for line in shape_lines(&shape, &ui.plot_bounds()) {
+                    ui.line(
+                        line.color(current_color.gamma_multiply(0.5))
+                            .width(engine.current_width),
+                    );
+                }
+            }
+        }
+
+        if !engine.points.is_empty()
+            && (engine.current_measure.is_some()
+                || engine.current_transform.is_some()
+                || engine.current_locus)
+        {
+            for pair in engine.points.windows(2) {
+                ui.line(measure_line(pair[0], pair[1], current_color));
+            }
+
+            if let Some(last) = engine.points.last() {
+                ui.line(measure_line(*last, snap_pos, current_color));
+            }
+        } else if !engine.points.is_empty() {
+            for guide in engine.current_tool.get_guides(&engine.points, snap_pos) {
+                for line in shape_lines(&guide, &ui.plot_bounds()) {
+                    ui.line(
+                        line.color(current_color.gamma_multiply(0.5))
+                            .width(engine.current_width),
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(property) = engine
+        .analysis
+        .as_ref()
+        .and_then(|analysis| Some(&analysis.properties[analysis.highlighted?]))
+    {
+        let color = Color32::from(engine.config.intersection_color);
+
+        for i in property.constructions() {
+            if let Some(construction) = engine.constructions.get(i) {
+                for line in construction_lines(construction, ui) {
+                    ui.line(line.color(color).width(construction.width + 2.0));
+                }
+            }
+        }
+
+        ui.points(
+            Points::new(
+                property
+                    .points()
+                    .iter()
+                    .map(|point| [point.x as f64, point.y as f64])
+                    .collect::<Vec<[f64; 2]>>(),
+            )
+            .color(color)
+            .radius(5.0),
+        );
+    }
+
+    let measurements: Vec<&Measurement> = match &engine.playback {
+        Some(playback) => playback.measurements(engine).collect(),
+        None => engine.measurements.iter().collect(),
+    };
+
+    for measurement in measurements {
+        let Some(anchor) = measurement.anchor(&engine.constructions) else {
+            continue;
+        };
+
+        for (a, b) in measurement.guides() {
+            ui.line(measure_line(a, b, engine.config.text_color.into()));
+        }
+
+        ui.text(
+            Text::new(
+                PlotPoint::from(anchor),
+                measurement.format(&engine.constructions, engine.config.precision),
+            )
+            .color(engine.config.text_color)
+            .anchor(Align2::CENTER_BOTTOM),
+        );
+    }
+
+    ui.points(
+        Points::new(
+            engine
+                .points
+                .iter()
+                .map(|point| [point.x as f64, point.y as f64])
+                .collect::<Vec<[f64; 2]>>(),
+        )
+        .color(engine.config.point_color),
+    );
+
+    for point in &engine.labelled_points {
+        let pos = [point.pos.x as f64, point.pos.y as f64];
+
+        ui.points(Points::new(vec![pos]).color(engine.config.point_color));
+        ui.text(
+            Text::new(PlotPoint::from(pos), &point.label)
+                .color(engine.config.text_color)
+                .anchor(Align2::LEFT_BOTTOM),
+        );
+    }
+}
+
+/// The marker drawn where the pointer would snap, so the kind of target can be told apart.
+fn snap_marker(kind: SnapKind) -> MarkerShape {
+    match kind {
+        SnapKind::Intersection => MarkerShape::Circle,
+        SnapKind::Point(_) => MarkerShape::Square,
+        SnapKind::Curve(_) => MarkerShape::Diamond,
+        SnapKind::Grid => MarkerShape::Plus,
+    }
+}
+
+/// A faint dotted segment marking what a measurement measures.
+fn measure_line(a: Pos, b: Pos, color: Color32) -> plot::Line {
+    segment(a, b)
+        .color(color.gamma_multiply(0.5))
+        .style(LineStyle::dotted_dense())
+}
+
+/// A construction styled with its colour, width and layer.
+pub fn construction_lines(construction: &Construction, ui: &PlotUi) -> Vec<plot::Line> {
+    shape_lines(&construction.shape, &ui.plot_bounds())
+        .into_iter()
+        .map(|line| {
+            line.color(construction.color)
+                .width(construction.width)
+                .name(&construction.layer)
+        })
+        .collect()
+}
+
+/// An unstyled shape, as one line for each of its pieces. Lines are drawn across the whole
+/// of `bounds`, and conics are dashed to mark them as outside compass and straight edge.
+pub fn shape_lines(shape: &Shape, bounds: &PlotBounds) -> Vec<plot::Line> {
+    match shape {
+        Shape::Circle(circle_data) => vec![circle(circle_data.pos, circle_data.r)],
+        Shape::Line(line_data) => vec![line(
+            line_data.p1,
+            line_data.p2,
+            bounds.max()[1],
+            bounds.min()[1],
+        )],
+        Shape::Segment(segment_data) => vec![segment(segment_data.p1, segment_data.p2)],
+        Shape::Arc(arc_data) => vec![arc(arc_data.pos, arc_data.r, arc_data.start, arc_data.stop)],
+        Shape::Conic(conic_data) => {
+            let (min, max) = (bounds.min(), bounds.max());
+            let (min, max) = (
+                Pos::new(min[0] as f32, min[1] as f32),
+                Pos::new(max[0] as f32, max[1] as f32),
+            );
+
+            conic_data
+                .branches(min, max)
+                .into_iter()
+                .map(|branch| {
+                    Line::new(
+                        branch
+                            .iter()
+                            .map(|point| [point.x as f64, point.y as f64])
+                            .collect::<PlotPoints>(),
+                    )
+                    .style(LineStyle::dashed_dense())
+                })
+                .collect()
+        }
+        Shape::Locus(locus_data) => locus_data
+            .pieces
+            .iter()
+            .map(|piece| {
+                Line::new(
+                    piece
+                        .iter()
+                        .map(|point| [point.x as f64, point.y as f64])
+                        .collect::<PlotPoints>(),
+                )
+            })
+            .collect(),
+    }
+}
+
+fn circle(pos: Pos, r: f32) -> plot::Line {
+    let n = 512;
+    let circle_points: PlotPoints = (0..=n)
+        .map(|i| {
+            let t = remap(i as f32, 0.0..=(n as f32), 0.0..=TAU);
+            [(r * t.cos() + pos.x) as f64, (r * t.sin() + pos.y) as f64]
+        })
+        .collect();
+
+    Line::new(circle_points)
+}
+
+fn line(p1: Pos, p2: Pos, top: f64, bottom: f64) -> plot::Line {
+    let m = (p2.y - p1.y) as f64 / (p2.x - p1.x) as f64;
+    let c = p1.y as f64 - (m * p1.x as f64);
+
+    if m.is_infinite() {
+        return Line::new(PlotPoints::new(vec![
+            [p1.x as f64, top * 1.5],
+            [p1.x as f64, bottom * 1.5],
+        ]));
+    }
+
+    Line::new(PlotPoints::from_explicit_callback(
+        move |x| (x * m) + c,
+        ..,
+        512,
+    ))
+}
+
+fn segment(p1: Pos, p2: Pos) -> plot::Line {
+    let n = 512;
+
+    let line_points: PlotPoints = (0..=n)
+        .map(|i| {
+            let x = remap(i as f32, 0.0..=(n as f32), p1.x..=p2.x);
+            let y = remap(i as f32, 0.0..=(n as f32), p1.y..=p2.y);
+            [x as f64, y as f64]
+        })
+        .collect();
+
+    Line::new(line_points)
+}
+
+fn arc(pos: Pos, r: f32, a1: f32, a2: f32) -> plot::Line {
+    let stop = if a1 > a2 { a2 + TAU } else { a2 };
+
+    let n = 512;
+    let arc_points: PlotPoints = (0..=n)
+        .map(|i| {
+            let t = remap(i as f32, 0.0..=(n as f32), a1..=stop);
+            [(r * t.cos() + pos.x) as f64, (r * t.sin() + pos.y) as f64]
+        })
+        .collect();
+
+    Line::new(arc_points)
+}
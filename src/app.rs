@@ -1,40 +1,109 @@
-use std::io::Write;
+use std::time::Duration;
 
 use eframe::App;
-use egui::{
-    plot::{Legend, PlotPoint},
-    Pos2, RichText,
+use egui::{plot::Legend, Color32, KeyboardShortcut, Pos2};
+use euclid::{
+    adapter,
+    engine::{
+        config::EngineConfig,
+        document::Format,
+        ggb,
+        keys::Action,
+        measure::MeasureKind,
+        pos::Pos,
+        puzzle::Puzzle,
+        script,
+        snap::{Grid, GridKind},
+        tools,
+        transform::TransformKind,
+        Engine,
+    },
 };
 
-use crate::{
-    engine::{tools, Engine},
-    ui,
-};
+use crate::{recovery::Recovery, ui};
 
 pub struct Euclid {
     engine: Engine,
 
     tools: Vec<&'static dyn tools::Tool>,
+    shortcuts: Vec<(KeyboardShortcut, Action)>,
 
     point_inp: Pos2,
-    show_axes: bool,
+    /// Where the pointer would snap to on the plot, if it is over it.
+    hover: Option<Pos>,
+    /// Whether the pointer was over a point that can be dragged, so the plot should not pan.
+    over_handle: bool,
 
     show_save_window: bool,
     show_open_window: bool,
+    show_import_window: bool,
+    show_export_window: bool,
 
     file_name: Option<String>,
+    import_summary: Option<ggb::ImportSummary>,
+
+    show_script_window: bool,
+    script: String,
+    script_result: Result<script::Script, script::ScriptError>,
+
+    /// Shown until dismissed, when something like opening a file fails.
+    error: Option<String>,
+    recovery: Recovery,
+    /// The document left by a session that did not exit cleanly, until the user chooses
+    /// whether to restore it.
+    recovered: Option<String>,
 }
 
 impl App for Euclid {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Keep the session unfinished so the unanswered restore is offered again
+        if self.recovered.is_some() {
+            return;
+        }
+
+        if let Err(e) = self.recovery.finish(&self.engine) {
+            eprintln!("error: {}", e);
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if self.recovered.is_none() {
+            let result = self.recovery.tick(&self.engine);
+            self.report(result);
+        }
+
         ctx.set_visuals(egui::Visuals {
             dark_mode: true,
-            extreme_bg_color: self.engine.config.extreme_background_color,
-            faint_bg_color: self.engine.config.faint_background_color,
-            override_text_color: Some(self.engine.config.text_color),
+            extreme_bg_color: self.engine.config.extreme_background_color.into(),
+            faint_bg_color: self.engine.config.faint_background_color.into(),
+            override_text_color: Some(self.engine.config.text_color.into()),
             ..Default::default()
         });
 
+        // Typing into a text box should not trigger shortcuts
+        if !ctx.wants_keyboard_input() {
+            let pressed: Vec<Action> = self
+                .shortcuts
+                .iter()
+                .filter(|(shortcut, _)| ctx.input_mut(|input| input.consume_shortcut(shortcut)))
+                .map(|(_, action)| action.clone())
+                .collect();
+
+            for action in pressed {
+                self.run_action(action);
+            }
+        }
+
+        if let Some(playback) = &mut self.engine.playback {
+            if playback.playing {
+                playback.advance(
+                    ctx.input(|input| input.stable_dt),
+                    self.engine.constructions.len(),
+                );
+                ctx.request_repaint();
+            }
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("file", |ui| {
@@ -50,28 +119,66 @@ impl App for Euclid {
                         self.show_open_window = true;
                     }
 
+                    ui.separator();
+
+                    if ui.button("import ggb").clicked() {
+                        self.show_import_window = true;
+                    }
+
+                    if ui.button("export ggb").clicked() {
+                        self.show_export_window = true;
+                    }
+
+                    ui.separator();
+
                     if ui.button("quit").clicked() {
                         frame.close();
                     }
                 });
 
+                ui.menu_button("document", |ui| {
+                    ui::grid::new("document-grid").show(ui, |ui| {
+                        ui::grid::add_row(ui, "title", |ui| {
+                            ui.text_edit_singleline(&mut self.engine.metadata.title);
+                        });
+
+                        ui::grid::add_row(ui, "author", |ui| {
+                            ui.text_edit_singleline(&mut self.engine.metadata.author);
+                        });
+                    });
+
+                    ui.label("description");
+                    ui.text_edit_multiline(&mut self.engine.metadata.description);
+                });
+
+                if ui.button("script").clicked() {
+                    self.show_script_window = !self.show_script_window;
+                }
+
                 ui.menu_button("examples", |ui| {
-                    let example_names = std::fs::read_dir("examples")
-                        .expect("could not read examples folder")
-                        .map(|res| res.map(|e| e.file_name()))
-                        .collect::<Result<Vec<_>, std::io::Error>>()
-                        .expect("could not read examples folder");
-
-                    for example in example_names {
-                        let example = example.to_string_lossy();
-                        if ui
-                            .button(example.clone().strip_suffix(".yml").unwrap())
-                            .clicked()
-                        {
-                            let contents = std::fs::read_to_string(format!("examples/{}", example))
-                                .expect("could not read file");
+                    for example in list_files("examples") {
+                        let Some(format) = Format::from_path(&example) else {
+                            continue;
+                        };
+
+                        let name = example.rsplit_once('.').map_or("", |(stem, _)| stem);
+
+                        if ui.button(name).clicked() {
+                            let result = self.load_file(&format!("examples/{}", example), format);
+                            self.report(result);
+                        }
+                    }
+                });
+
+                ui.menu_button("puzzles", |ui| {
+                    for puzzle in list_files("puzzles") {
+                        let Some(name) = puzzle.strip_suffix(".yml") else {
+                            continue;
+                        };
 
-                            self.engine.load(&contents).expect("could not load file");
+                        if ui.button(name).clicked() {
+                            let result = self.start_puzzle(&format!("puzzles/{}", puzzle));
+                            self.report(result);
                         }
                     }
                 });
@@ -83,6 +190,29 @@ impl App for Euclid {
             .show(ctx, |ui| {
                 ui.add_space(16.0);
 
+                if let (Some(active), Some(status)) =
+                    (&self.engine.puzzle, self.engine.puzzle_status())
+                {
+                    ui.heading(&active.puzzle.title);
+                    ui.label(&active.puzzle.description);
+
+                    ui::grid::new("puzzle-grid").show(ui, |ui| {
+                        ui::grid::add_text_row(ui, "moves", &status.moves.to_string());
+                        ui::grid::add_text_row(ui, "par", &active.puzzle.par.to_string());
+                        ui::grid::add_text_row(
+                            ui,
+                            if status.solved { "solved" } else { "unsolved" },
+                            &format!(
+                                "{}{}",
+                                "★".repeat(status.stars as usize),
+                                "☆".repeat(3 - status.stars as usize)
+                            ),
+                        );
+                    });
+
+                    ui.separator();
+                }
+
                 ui::grid::new("side-grid").show(ui, |ui| {
                     ui::grid::add_text_row(
                         ui,
@@ -92,6 +222,27 @@ impl App for Euclid {
 
                     ui::grid::add_struct(ui, self.engine.stats());
 
+                    let mut removed = None;
+
+                    for (i, measurement) in self.engine.measurements.iter().enumerate() {
+                        ui::grid::add_row(ui, measurement.name(), |ui| {
+                            if ui.small_button("x").clicked() {
+                                removed = Some(i);
+                            }
+
+                            ui.monospace(
+                                measurement.format(
+                                    &self.engine.constructions,
+                                    self.engine.config.precision,
+                                ),
+                            );
+                        });
+                    }
+
+                    if let Some(i) = removed {
+                        self.engine.remove_measurement(i);
+                    }
+
                     ui::grid::separator(ui);
 
                     ui::grid::add_row(ui, "tool", |ui| {
@@ -99,15 +250,119 @@ impl App for Euclid {
                             .selected_text(self.engine.current_tool.name().to_string())
                             .show_ui(ui, |ui| {
                                 for tool in &self.tools {
-                                    ui.selectable_value(
-                                        &mut self.engine.current_tool,
-                                        *tool,
-                                        tool.name(),
-                                    );
+                                    if ui
+                                        .selectable_value(
+                                            &mut self.engine.current_tool,
+                                            *tool,
+                                            tool.name(),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.engine.current_measure = None;
+                                        self.engine.current_transform = None;
+                                        self.engine.current_locus = false;
+                                        self.engine.clear_points();
+                                    }
+                                }
+                            });
+                    });
+
+                    ui::grid::add_row(ui, "measure", |ui| {
+                        egui::ComboBox::from_id_source("measure-select")
+                            .selected_text(
+                                self.engine
+                                    .current_measure
+                                    .map_or("none", |kind| kind.name()),
+                            )
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.engine.current_measure, None, "none");
+
+                                for kind in MeasureKind::ALL {
+                                    if ui
+                                        .selectable_value(
+                                            &mut self.engine.current_measure,
+                                            Some(kind),
+                                            kind.name(),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.engine.current_transform = None;
+                                        self.engine.current_locus = false;
+                                        self.engine.clear_points();
+                                    }
+                                }
+                            });
+                    });
+
+                    ui::grid::add_row(ui, "transform", |ui| {
+                        egui::ComboBox::from_id_source("transform-select")
+                            .selected_text(
+                                self.engine
+                                    .current_transform
+                                    .map_or("none", |kind| kind.name()),
+                            )
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    &mut self.engine.current_transform,
+                                    None,
+                                    "none",
+                                );
+
+                                for kind in TransformKind::ALL {
+                                    if ui
+                                        .selectable_value(
+                                            &mut self.engine.current_transform,
+                                            Some(kind),
+                                            kind.name(),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.engine.current_measure = None;
+                                        self.engine.current_locus = false;
+                                        self.engine.clear_points();
+                                    }
                                 }
                             });
                     });
 
+                    ui::grid::add_row(ui, "trace locus", |ui| {
+                        if ui
+                            .add(egui::Checkbox::new(&mut self.engine.current_locus, ""))
+                            .changed()
+                        {
+                            self.engine.current_measure = None;
+                            self.engine.current_transform = None;
+                            self.engine.clear_points();
+                        }
+                    });
+
+                    ui::grid::add_row(ui, "selected", |ui| {
+                        if ui.small_button("clear").clicked() {
+                            self.engine.selection.clear();
+                        }
+
+                        if ui.small_button("select layer").clicked() {
+                            let layer = self.engine.current_layer.clone();
+                            self.engine.select_layer(&layer);
+                        }
+
+                        ui.monospace(self.engine.selection.len().to_string());
+                    });
+
+                    let mut applied = None;
+
+                    for transform in &self.engine.transforms {
+                        ui::grid::add_row(ui, &transform.to_string(), |ui| {
+                            if ui.small_button("apply").clicked() {
+                                applied = Some(transform.clone());
+                            }
+                        });
+                    }
+
+                    if let Some(transform) = applied {
+                        self.engine.apply_transform(&transform);
+                    }
+
                     ui::grid::add_row(ui, "color", |ui| {
                         egui::ComboBox::from_id_source("color-select")
                             .selected_text(
@@ -122,27 +377,26 @@ impl App for Euclid {
                                     ui.selectable_value(
                                         &mut self.engine.current_color,
                                         *color,
-                                        self.engine.config.get_name(color).unwrap().to_string(),
+                                        self.engine
+                                            .config
+                                            .get_name(color)
+                                            .unwrap_or("custom".to_owned()),
                                     );
                                 }
                             });
 
+                        let mut color = Color32::from(self.engine.current_color);
+
                         egui::color_picker::color_edit_button_srgba(
                             ui,
-                            &mut self.engine.current_color,
+                            &mut color,
                             egui::color_picker::Alpha::OnlyBlend,
                         );
+
+                        self.engine.current_color = color.into();
                     });
 
-                    ui::grid::add_text_row(
-                        ui,
-                        "operation",
-                        self.engine
-                            .current_tool
-                            .instructions()
-                            .get(self.engine.stats().num_points)
-                            .unwrap_or(&"none"),
-                    );
+                    ui::grid::add_text_row(ui, "operation", self.engine.instruction());
 
                     ui.horizontal(|ui| {
                         ui.add(egui::DragValue::new(&mut self.point_inp.x));
@@ -150,8 +404,7 @@ impl App for Euclid {
 
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                             if ui.button("insert point").clicked() {
-                                self.engine
-                                    .click(PlotPoint::new(self.point_inp.x, self.point_inp.y));
+                                self.engine.click(self.point_inp.into());
                                 self.point_inp = Pos2::ZERO;
                             }
                         });
@@ -165,107 +418,308 @@ impl App for Euclid {
                         egui::ComboBox::from_id_source("layer-select")
                             .selected_text(self.engine.current_layer.to_string())
                             .show_ui(ui, |ui| {
-                                for i in 1..=5 {
-                                    let name = format!("Layer {}", i);
+                                for layer in &self.engine.layers {
                                     ui.selectable_value(
                                         &mut self.engine.current_layer,
-                                        name.clone(),
-                                        name,
+                                        layer.name.clone(),
+                                        &layer.name,
                                     );
                                 }
                             });
                     });
 
+                    ui::grid::add_row(ui, "layer visible", |ui| {
+                        if let Some(layer) = self.engine.current_layer_mut() {
+                            ui.add(egui::Checkbox::new(&mut layer.visible, ""));
+                        }
+                    });
+
                     ui::grid::add_row(ui, "line width", |ui| {
                         ui.add(egui::Slider::new(&mut self.engine.current_width, 0.5..=5.0));
                     });
 
                     ui::grid::add_row(ui, "snap radius", |ui| {
-                        ui.add(egui::Slider::new(&mut self.engine.snap_radius, 0.0..=1.0));
+                        ui.add(
+                            egui::Slider::new(&mut self.engine.snap_radius, 0.0..=50.0)
+                                .suffix(" px"),
+                        );
+                    });
+
+                    ui::grid::add_row(ui, "snap to", |ui| {
+                        ui.vertical(|ui| {
+                            let settings = &mut self.engine.snap_settings;
+
+                            for (i, class) in settings.order.clone().into_iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.checkbox(settings.enabled_mut(class), class.name());
+
+                                    if i > 0
+                                        && ui
+                                            .small_button("⬆")
+                                            .on_hover_text("snap to before the one above")
+                                            .clicked()
+                                    {
+                                        settings.raise(class);
+                                    }
+                                });
+                            }
+                        })
+                        .response
+                        .on_hover_text("hold alt to place points without snapping");
                     });
 
                     ui::grid::add_row(ui, "show axes", |ui| {
-                        ui.add(egui::Checkbox::new(&mut self.show_axes, ""));
+                        ui.add(egui::Checkbox::new(&mut self.engine.view.show_axes, ""));
+                    });
+
+                    ui::grid::add_row(ui, "grid", |ui| {
+                        let grid = &mut self.engine.view.grid;
+
+                        if let Some(grid) = grid {
+                            ui.add(
+                                egui::DragValue::new(&mut grid.spacing)
+                                    .speed(0.05)
+                                    .clamp_range(0.05..=100.0),
+                            )
+                            .on_hover_text("spacing");
+                        }
+
+                        egui::ComboBox::from_id_source("grid-select")
+                            .selected_text(grid.map_or("none", |grid| grid.kind.name()))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(grid, None, "none");
+
+                                let spacing = grid.map_or(1.0, |grid| grid.spacing);
+
+                                for kind in GridKind::ALL {
+                                    ui.selectable_value(
+                                        grid,
+                                        Some(Grid { kind, spacing }),
+                                        kind.name(),
+                                    );
+                                }
+                            });
                     });
 
                     ui::grid::add_row(ui, "show intersections", |ui| {
-                        ui.add(egui::Checkbox::new(&mut self.engine.show_intersections, ""));
+                        ui.add(egui::Checkbox::new(
+                            &mut self.engine.view.show_intersections,
+                            "",
+                        ));
+                    });
+
+                    ui::grid::add_row(ui, "find properties", |ui| {
+                        let mut analysis = self.engine.analysis.is_some();
+
+                        if ui.add(egui::Checkbox::new(&mut analysis, "")).changed() {
+                            self.engine.set_analysis(analysis);
+                        }
+                    });
+
+                    ui::grid::add_row(ui, "exact arithmetic", |ui| {
+                        let mut exact = self.engine.exact.is_some();
+
+                        if ui.add(egui::Checkbox::new(&mut exact, "")).changed() {
+                            self.engine.set_exact(exact);
+                        }
                     });
 
+                    if let Some(point) = self.hover.and_then(|pos| self.engine.exact_point(pos)) {
+                        ui::grid::add_text_row(ui, "point", &point.to_string());
+                    }
+
                     ui::grid::separator(ui);
                 });
 
                 ui.horizontal(|ui| {
+                    let (undo_keys, redo_keys) =
+                        (self.keys_for(&Action::Undo), self.keys_for(&Action::Redo));
+
                     ui.add_enabled_ui(self.engine.can_undo(), |ui| {
-                        if ui.button("undo").clicked() {
+                        if ui.button("undo").on_hover_text(undo_keys).clicked() {
                             self.engine.undo();
                         };
                     });
 
                     ui.add_enabled_ui(self.engine.can_redo(), |ui| {
-                        if ui.button("redo").clicked() {
+                        if ui.button("redo").on_hover_text(redo_keys).clicked() {
                             self.engine.redo();
                         };
                     });
 
                     if ui.button("clear").clicked() {
-                        self.engine.clear();
+                        if self.engine.puzzle.is_some() {
+                            self.engine.restart_puzzle();
+                        } else {
+                            self.engine.clear();
+                        }
                     };
                 });
 
                 ui::grid::separator(ui);
 
-                let num = 5;
-                for (i, construction) in
-                    self.engine.constructions.iter().rev().take(num).enumerate()
-                {
-                    let text = RichText::new(format!("{}", construction))
-                        .color(
-                            self.engine
-                                .config
-                                .text_color
-                                .gamma_multiply(1.0 - (i as f32 / num as f32)),
-                        )
-                        .font(egui::FontId::monospace(12.0));
+                egui::CollapsingHeader::new("playback")
+                    .show(ui, |ui| ui::playback::show(ui, &mut self.engine));
 
-                    ui.label(text);
-                    ui.end_row();
-                }
+                egui::CollapsingHeader::new("history")
+                    .default_open(true)
+                    .show(ui, |ui| ui::history::show(ui, &mut self.engine));
             });
 
         egui::CentralPanel::default().show(ctx, |ui| {
             if ui::window::SaveWindow::new(&mut self.show_save_window, &mut self.file_name)
+                .hint("end the name with .json to save as JSON")
                 .show(ctx)
             {
-                let contents = self.engine.save().expect("could not save file");
-
-                let mut file = std::fs::File::create(format!(
-                    "saves/{}.yml",
-                    self.file_name.clone().unwrap_or("unnamed".to_owned())
-                ))
-                .expect("could not create file");
+                let file_name = ui::window::with_extension(
+                    &self.file_name.clone().unwrap_or("unnamed".to_owned()),
+                    Format::Yaml.extension(),
+                    &["yaml", Format::Json.extension()],
+                );
+                let format = Format::from_path(&file_name).unwrap_or(Format::Yaml);
+
+                if self.engine.metadata.title.is_empty() {
+                    self.engine.metadata.title = file_name
+                        .rsplit_once('.')
+                        .map_or(file_name.clone(), |(stem, _)| stem.to_owned());
+                }
 
-                file.write_all(contents.as_bytes())
-                    .expect("could not write save to file");
+                let result = self.save_file(&file_name, format);
+                self.report(result);
 
+                self.file_name = Some(file_name);
                 self.show_save_window = false;
             }
 
             if ui::window::OpenWindow::new(&mut self.show_open_window, &mut self.file_name)
                 .show(ctx)
             {
-                let contents =
-                    std::fs::read_to_string(format!("saves/{}.yml", self.file_name.clone().unwrap()))
-                        .expect("could not read file");
-                self.engine.load(&contents).expect("could not open file");
+                let file_name = self.file_name.clone().unwrap_or_default();
+
+                let result = self.load_file(
+                    &format!("saves/{}", file_name),
+                    Format::from_path(&file_name).unwrap_or(Format::Yaml),
+                );
+                self.report(result);
 
                 self.show_open_window = false;
             }
 
+            if ui::window::OpenWindow::new(&mut self.show_import_window, &mut self.file_name)
+                .title("import ggb")
+                .extensions(&["ggb"])
+                .show(ctx)
+            {
+                let file_name = format!("saves/{}", self.file_name.clone().unwrap_or_default());
+
+                let result = std::fs::read(&file_name)
+                    .map_err(|e| format!("could not read {}: {}", file_name, e))
+                    .and_then(|data| {
+                        self.engine
+                            .import_ggb(&data)
+                            .map_err(|e| format!("could not import {}: {}", file_name, e))
+                    });
+
+                if let Some(summary) = self.report(result) {
+                    self.import_summary = Some(summary);
+                }
+
+                self.show_import_window = false;
+            }
+
+            if ui::window::SaveWindow::new(&mut self.show_export_window, &mut self.file_name)
+                .title("export ggb")
+                .show(ctx)
+            {
+                let file_name = ui::window::with_extension(
+                    &self.file_name.clone().unwrap_or("unnamed".to_owned()),
+                    "ggb",
+                    &[],
+                );
+
+                let result = self
+                    .engine
+                    .export_ggb()
+                    .map_err(|e| format!("could not export {}: {}", file_name, e))
+                    .and_then(|data| write_save(&file_name, &data));
+                self.report(result);
+
+                self.show_export_window = false;
+            }
+
+            ui::window::SummaryWindow::new(&mut self.import_summary).show(ctx);
+            ui::window::ErrorWindow::new(&mut self.error).show(ctx);
+
+            match ui::window::RecoveryWindow::new(&self.recovered).show(ctx) {
+                ui::window::RecoveryAction::Restore => {
+                    // Kept until it loads, so a failed restore can be discarded instead
+                    let contents = self.recovered.clone().unwrap_or_default();
+                    let result = self
+                        .engine
+                        .load(&contents, Format::Yaml)
+                        .map_err(|e| format!("could not restore the last session: {}", e));
+
+                    if result.is_ok() {
+                        self.recovered = None;
+                    }
+
+                    self.report(result);
+                }
+                ui::window::RecoveryAction::Discard => self.recovered = None,
+                ui::window::RecoveryAction::None => (),
+            }
+
+            ui::window::PropertiesWindow::new(
+                &mut self.engine.analysis,
+                &self.engine.constructions,
+            )
+            .show(ctx);
+
+            let source = self.script.clone();
+            let action = ui::window::ScriptWindow::new(
+                &mut self.show_script_window,
+                &mut self.script,
+                self.script_result.as_ref().err(),
+            )
+            .show(ctx);
+
+            match action {
+                ui::window::ScriptAction::Apply => {
+                    if let Ok(script) = &self.script_result {
+                        self.engine.run_script(script);
+                        self.script.clear();
+                    }
+                }
+                ui::window::ScriptAction::LoadDocument => {
+                    self.script = script::to_script(&self.engine.document());
+                }
+                ui::window::ScriptAction::None => (),
+            }
+
+            if self.script != source {
+                self.script_result = script::compile(&self.script);
+            }
+
+            self.engine.preview = match (&self.script_result, self.show_script_window) {
+                (Ok(script), true) => self.engine.script_constructions(script),
+                _ => Vec::new(),
+            };
+
             ui.vertical_centered(|ui| {
-                ui.visuals_mut().widgets.open.fg_stroke.color = self.engine.config.grid_color;
-                ui.visuals_mut().widgets.open.weak_bg_fill = self.engine.config.background_color;
-                ui.visuals_mut().faint_bg_color = self.engine.config.point_color;
+                ui.visuals_mut().widgets.open.fg_stroke.color =
+                    self.engine.config.grid_color.into();
+                ui.visuals_mut().widgets.open.weak_bg_fill =
+                    self.engine.config.background_color.into();
+                ui.visuals_mut().faint_bg_color = self.engine.config.point_color.into();
+
+                if let Some(caption) = self
+                    .engine
+                    .playback
+                    .as_ref()
+                    .and_then(|playback| playback.caption(&self.engine.constructions))
+                {
+                    ui.heading(caption);
+                }
 
                 egui::plot::Plot::new("plot")
                     .allow_double_click_reset(false)
@@ -275,13 +729,25 @@ impl App for Euclid {
                     .data_aspect(1.0)
                     .legend(egui::plot::Legend::default())
                     .set_margin_fraction(egui::vec2(0.2, 0.2))
-                    .show_axes([self.show_axes; 2])
+                    .show_axes([self.engine.view.show_axes; 2])
+                    .allow_drag(!self.over_handle && !self.engine.dragging())
                     .show(ui, |ui| {
+                        if let Some(camera) = self.engine.take_camera() {
+                            ui.set_plot_bounds(camera.into());
+                        }
+
+                        self.engine.set_pixel_size(adapter::pixel_size(ui));
+                        self.engine.snap_suspended = ui.ctx().input(|input| input.modifiers.alt);
+
                         if ui.plot_clicked()
                             && ui.pointer_coordinate_drag_delta().length_sq() == 0.0
                         {
                             if let Some(point) = ui.pointer_coordinate() {
-                                self.engine.click(point);
+                                if ui.ctx().input(|input| input.modifiers.shift) {
+                                    self.engine.toggle_selection(Pos::from(point));
+                                } else {
+                                    self.engine.click(Pos::from(point));
+                                }
                             }
                         }
 
@@ -289,7 +755,51 @@ impl App for Euclid {
                             self.engine.clear_points();
                         }
 
-                        self.engine.show(ui);
+                        let pointer = ui.pointer_coordinate().map(Pos::from);
+                        let (pressed, down, moved, shift) = ui.ctx().input(|input| {
+                            (
+                                input.pointer.primary_pressed(),
+                                input.pointer.primary_down(),
+                                input.pointer.is_decidedly_dragging(),
+                                input.modifiers.shift,
+                            )
+                        });
+
+                        if let Some(pointer) = pointer {
+                            if pressed
+                                && !shift
+                                && ui.plot_hovered()
+                                && self.engine.points.is_empty()
+                            {
+                                self.engine.start_drag(pointer);
+                            }
+
+                            // Clicking a point to use it should not nudge it
+                            if down && moved && self.engine.dragging() {
+                                self.engine.drag_to(pointer);
+                            }
+                        }
+
+                        if !down && self.engine.dragging() {
+                            self.engine.end_drag();
+                        }
+
+                        self.over_handle = self.engine.points.is_empty()
+                            && pointer.is_some_and(|p| self.engine.drag_target(p).is_some());
+
+                        if self.engine.dragging() {
+                            ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+                        } else if self.over_handle {
+                            ui.ctx().set_cursor_icon(egui::CursorIcon::Grab);
+                        }
+
+                        self.hover = ui.pointer_coordinate().map(|point| {
+                            self.engine
+                                .get_snap_pos(Pos::from(point), self.engine.snap_distance())
+                        });
+
+                        adapter::show(&self.engine, ui);
+                        self.engine.update_camera(ui.plot_bounds().into());
                     });
             });
         });
@@ -298,22 +808,155 @@ impl App for Euclid {
 
 impl Euclid {
     pub fn new() -> Self {
+        let (engine, config_error) = match Engine::new("config.yml") {
+            Ok(engine) => (engine, None),
+            Err(e) => (
+                Engine::with_config(EngineConfig::default()),
+                Some(format!("config.yml: {}, so the default config is used", e)),
+            ),
+        };
+
+        let (recovery, recovered, started) = Recovery::start(
+            Duration::try_from_secs_f32(engine.config.autosave_interval).unwrap_or_default(),
+        );
+        let (recovered, recovery_error) = match recovered {
+            Ok(recovered) => (recovered, None),
+            Err(e) => (None, Some(e)),
+        };
+
+        // Each of these is worth knowing about, so none hides another
+        let error = [config_error, recovery_error, started.err()]
+            .into_iter()
+            .flatten()
+            .reduce(|errors, e| format!("{}\n{}", errors, e));
+
         Self {
-            engine: Engine::new("config.yml"),
+            shortcuts: engine
+                .config
+                .key_bindings
+                .iter()
+                .filter_map(|(binding, action)| Some((adapter::shortcut(binding)?, action.clone())))
+                .collect(),
+            engine,
             point_inp: Pos2::ZERO,
-            show_axes: true,
+            hover: None,
+            over_handle: false,
 
             show_save_window: false,
             show_open_window: false,
+            show_import_window: false,
+            show_export_window: false,
 
             file_name: None,
+            import_summary: None,
+
+            show_script_window: false,
+            script: String::new(),
+            script_result: Ok(script::Script::default()),
 
-            tools: vec![
-                &tools::Compass,
-                &tools::StraightEdge,
-                &tools::LineSegment,
-                &tools::Arc,
-            ],
+            tools: tools::ALL.to_vec(),
+
+            error,
+            recovery,
+            recovered,
         }
     }
+
+    /// Shows the error of a failed operation, or gives the result of one that succeeded.
+    fn report<T>(&mut self, result: Result<T, String>) -> Option<T> {
+        result.map_err(|e| self.error = Some(e)).ok()
+    }
+
+    fn load_file(&mut self, path: &str, format: Format) -> Result<(), String> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+
+        self.engine
+            .load(&contents, format)
+            .map_err(|e| format!("could not open {}: {}", path, e))
+    }
+
+    fn start_puzzle(&mut self, path: &str) -> Result<(), String> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+        let puzzle = Puzzle::parse(&contents)
+            .map_err(|e| format!("could not load puzzle {}: {}", path, e))?;
+
+        self.engine.start_puzzle(puzzle);
+
+        Ok(())
+    }
+
+    fn save_file(&mut self, file_name: &str, format: Format) -> Result<(), String> {
+        let contents = self
+            .engine
+            .save(format)
+            .map_err(|e| format!("could not save {}: {}", file_name, e))?;
+
+        write_save(file_name, contents.as_bytes())
+    }
+
+    /// The keys bound to an action, for hints.
+    fn keys_for(&self, action: &Action) -> String {
+        self.engine
+            .config
+            .key_bindings
+            .iter()
+            .filter(|(_, bound)| bound == action)
+            .map(|(binding, _)| binding.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn run_action(&mut self, action: Action) {
+        match action {
+            Action::Undo if self.engine.can_undo() => self.engine.undo(),
+            Action::Redo if self.engine.can_redo() => self.engine.redo(),
+            Action::Undo | Action::Redo => {}
+            Action::Save => self.show_save_window = true,
+            Action::Open => self.show_open_window = true,
+            Action::Cancel => self.engine.clear_points(),
+            Action::NextLayer => self.engine.switch_layer(1),
+            Action::PreviousLayer => self.engine.switch_layer(-1),
+            Action::ToggleIntersections => {
+                self.engine.view.show_intersections = !self.engine.view.show_intersections;
+            }
+            Action::ToggleAxes => self.engine.view.show_axes = !self.engine.view.show_axes,
+            Action::TraceLocus => {
+                self.engine.current_locus = !self.engine.current_locus;
+                self.engine.current_measure = None;
+                self.engine.current_transform = None;
+                self.engine.clear_points();
+            }
+            Action::Tool(name) => {
+                if let Some(tool) = tools::by_name(&name) {
+                    self.engine.current_tool = tool;
+                    self.engine.current_measure = None;
+                    self.engine.current_transform = None;
+                    self.engine.current_locus = false;
+                    self.engine.clear_points();
+                }
+            }
+        }
+    }
+}
+
+/// Names of the files in a folder, or none if it cannot be read.
+fn list_files(folder: &str) -> Vec<String> {
+    std::fs::read_dir(folder)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| Some(entry.ok()?.file_name().to_string_lossy().into_owned()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Writes a file to the saves folder, making the folder if it is not there.
+fn write_save(file_name: &str, data: &[u8]) -> Result<(), String> {
+    let path = format!("saves/{}", file_name);
+
+    std::fs::create_dir_all("saves")
+        .and_then(|()| std::fs::write(&path, data))
+        .map_err(|e| format!("could not write {}: {}", path, e))
 }
@@ -0,0 +1,296 @@
+use euclid::engine::{
+    config::EngineConfig,
+    document::Format,
+    export::{self, animation::Animation, Frame, Style},
+    goals::Goals,
+    Engine,
+};
+
+const USAGE: &str = "usage:
+    euclid                                  open the app
+    euclid stats <file>                     print statistics about a construction
+    euclid validate <file>...               check constructions and their intersections
+    euclid convert <input> <output>         convert between formats
+    euclid grade <goals> <file>...          check constructions against exercise goals
+    euclid animate <input> <output>         draw the constructions being made one by one,
+                                            as a .gif or numbered .png frames
+
+input formats:  .yml .yaml .json .ggb
+output formats: .yml .yaml .json .ggb .svg .png .tex (TikZ)
+
+options:
+    --config <file>     colour configuration (default: the built-in config.yml)
+    --width <pixels>    width of svg and png output (default 800)
+    --height <pixels>   height of svg and png output (default 600)
+    --fps <frames>      frames per second of animations (default 25)
+    --speed <steps>     constructions drawn per second in animations (default 1)
+    --hold <seconds>    time the finished figure is shown at the end (default 2)";
+
+struct Options {
+    /// The built-in configuration is used if none is given, so the commands work anywhere.
+    config: Option<String>,
+    width: u32,
+    height: u32,
+    animation: Animation,
+    files: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            config: None,
+            width: 800,
+            height: 600,
+            animation: Animation::default(),
+            files: Vec::new(),
+        };
+
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .cloned()
+                    .ok_or(format!("{} needs a value", name))
+            };
+
+            match arg.as_str() {
+                "--config" => options.config = Some(value(arg)?),
+                "--width" => {
+                    options.width = value(arg)?
+                        .parse()
+                        .map_err(|_| "--width must be a positive integer")?
+                }
+                "--height" => {
+                    options.height = value(arg)?
+                        .parse()
+                        .map_err(|_| "--height must be a positive integer")?
+                }
+                "--fps" => {
+                    options.animation.fps =
+                        value(arg)?.parse().map_err(|_| "--fps must be a number")?
+                }
+                "--speed" => {
+                    options.animation.speed = value(arg)?
+                        .parse()
+                        .map_err(|_| "--speed must be a number")?
+                }
+                "--hold" => {
+                    options.animation.hold =
+                        value(arg)?.parse().map_err(|_| "--hold must be a number")?
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ => options.files.push(arg.clone()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Runs a command without opening a window, returning the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let (command, rest) = args.split_first().expect("no command given");
+
+    let options = match Options::parse(rest) {
+        Ok(options) => options,
+        Err(e) => return usage_error(&e),
+    };
+
+    let result = match (command.as_str(), &options.files[..]) {
+        ("help" | "--help" | "-h", _) => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        ("stats", [file]) => stats(&options, file),
+        ("validate", files) if !files.is_empty() => validate(&options, files),
+        ("convert", [input, output]) => convert(&options, input, output),
+        ("grade", [goals, files @ ..]) if !files.is_empty() => grade(&options, goals, files),
+        ("animate", [input, output]) => animate(&options, input, output),
+        ("stats" | "validate" | "convert" | "grade" | "animate", _) => {
+            return usage_error(&format!("wrong number of files for {}", command))
+        }
+        _ => return usage_error(&format!("unknown command {}", command)),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("error: {}\n\n{}", message, USAGE);
+    2
+}
+
+fn load(options: &Options, file: &str) -> Result<Engine, String> {
+    let mut engine = match &options.config {
+        Some(config) => Engine::new(config).map_err(|e| format!("{}: {}", config, e))?,
+        None => Engine::with_config(EngineConfig::default()),
+    };
+
+    if file.ends_with(".ggb") {
+        let data = std::fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+        let summary = engine
+            .import_ggb(&data)
+            .map_err(|e| format!("{}: {}", file, e))?;
+
+        for object in &summary.unsupported {
+            eprintln!(
+                "warning: {}: {} ({}) was not imported: {}",
+                file, object.label, object.kind, object.reason
+            );
+        }
+    } else {
+        let format = Format::from_path(file).ok_or(format!("{}: unknown file extension", file))?;
+        let data = std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+
+        engine
+            .load(&data, format)
+            .map_err(|e| format!("{}: {}", file, e))?;
+    }
+
+    Ok(engine)
+}
+
+fn stats(options: &Options, file: &str) -> Result<(), String> {
+    let engine = load(options, file)?;
+
+    print!(
+        "{}",
+        serde_yaml::to_string(&engine.stats()).map_err(|e| e.to_string())?
+    );
+
+    Ok(())
+}
+
+fn validate(options: &Options, files: &[String]) -> Result<(), String> {
+    let mut failed = 0;
+
+    for file in files {
+        match load(options, file) {
+            Ok(engine) => {
+                let problems = engine.validate();
+
+                if problems.is_empty() {
+                    println!("{}: ok", file);
+                } else {
+                    failed += 1;
+
+                    for problem in problems {
+                        println!("{}: {}", file, problem);
+                    }
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{}", e);
+            }
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        n => Err(format!("{} of {} files failed validation", n, files.len())),
+    }
+}
+
+fn convert(options: &Options, input: &str, output: &str) -> Result<(), String> {
+    let engine = load(options, input)?;
+
+    let frame = Frame::fit(&engine, options.width, options.height);
+    let style = Style::from_engine(&engine);
+
+    let extension = output.rsplit_once('.').map_or("", |(_, ext)| ext);
+
+    let data = match extension {
+        "svg" => export::svg::render(&engine, &frame, &style).into_bytes(),
+        "tex" | "tikz" => export::tikz::render(&engine, &frame, &style).into_bytes(),
+        "png" => export::png::render(&engine, &frame, &style)?,
+        "ggb" => engine.export_ggb().map_err(|e| e.to_string())?,
+        _ => {
+            let format =
+                Format::from_path(output).ok_or(format!("{}: unknown file extension", output))?;
+
+            engine.save(format).map_err(|e| e.to_string())?.into_bytes()
+        }
+    };
+
+    std::fs::write(output, data).map_err(|e| format!("{}: {}", output, e))
+}
+
+/// Writes a GIF, or a PNG for each frame named like `output-0001.png`.
+fn animate(options: &Options, input: &str, output: &str) -> Result<(), String> {
+    let engine = load(options, input)?;
+
+    let frame = Frame::fit(&engine, options.width, options.height);
+    let style = Style::from_engine(&engine);
+
+    match output.rsplit_once('.') {
+        Some((_, "gif")) => {
+            let data = options.animation.render_gif(&engine, &frame, &style)?;
+
+            std::fs::write(output, data).map_err(|e| format!("{}: {}", output, e))
+        }
+        Some((stem, "png")) => {
+            let images = options.animation.render_pngs(&engine, &frame, &style)?;
+            let digits = images.len().to_string().len().max(4);
+
+            for (i, data) in images.into_iter().enumerate() {
+                let file = format!("{}-{:0digits$}.png", stem, i + 1, digits = digits);
+
+                std::fs::write(&file, data).map_err(|e| format!("{}: {}", file, e))?;
+            }
+
+            Ok(())
+        }
+        _ => Err(format!(
+            "{}: animations are written as .gif or .png",
+            output
+        )),
+    }
+}
+
+fn grade(options: &Options, goals_file: &str, files: &[String]) -> Result<(), String> {
+    let data = std::fs::read_to_string(goals_file).map_err(|e| format!("{}: {}", goals_file, e))?;
+    let goals = Goals::parse(&data).map_err(|e| format!("{}: {}", goals_file, e))?;
+
+    let mut failed = 0;
+
+    for file in files {
+        let document = match load(options, file) {
+            Ok(engine) => engine.document(),
+            Err(e) => {
+                failed += 1;
+                println!("{}", e);
+                continue;
+            }
+        };
+
+        let results = goals.check(&document);
+
+        for (goal, result) in goals.goals.iter().zip(&results) {
+            match result {
+                Ok(()) => println!("{}: pass: {}", file, goal),
+                Err(failure) => println!("{}: fail: {}: {}", file, goal, failure),
+            }
+        }
+
+        if results.iter().any(Result::is_err) {
+            failed += 1;
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        n => Err(format!(
+            "{} of {} files did not meet every goal",
+            n,
+            files.len()
+        )),
+    }
+}
@@ -1,2 +0,0 @@
-pub mod pos2;
-pub mod color32;
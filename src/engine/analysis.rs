@@ -0,0 +1,464 @@
+//! Finds relationships in a figure that were not constructed directly, such as points that
+//! happen to be collinear or segments that turn out to be the same length.
+
+use std::{collections::HashMap, f32::consts::PI};
+
+use super::{
+    document::Point,
+    pos::Pos,
+    shapes::{CircleData, Construction, LineData, Shape},
+};
+
+/// Concyclic points are found by trying every triple, so only this many points are used.
+const MAX_CONCYCLIC_POINTS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Property {
+    /// Three or more points on a line that has not been drawn.
+    Collinear {
+        points: Vec<Pos>,
+    },
+    /// Four or more points on a circle that has not been drawn.
+    Concyclic {
+        points: Vec<Pos>,
+    },
+    EqualLengths {
+        constructions: Vec<usize>,
+    },
+    Parallel {
+        constructions: Vec<usize>,
+    },
+    /// Every line in `a` is perpendicular to every line in `b`.
+    Perpendicular {
+        a: Vec<usize>,
+        b: Vec<usize>,
+    },
+    /// Three or more lines through a point that none of them were drawn from.
+    Concurrent {
+        point: Pos,
+        constructions: Vec<usize>,
+    },
+}
+
+impl Property {
+    /// A short description, naming constructions by label or by their place in the history.
+    pub fn describe(&self, constructions: &[Construction]) -> String {
+        let names = |indices: &[usize]| {
+            indices
+                .iter()
+                .map(
+                    |&i| match constructions.get(i).and_then(|c| c.label.as_ref()) {
+                        Some(label) => label.clone(),
+                        None => format!("#{}", i + 1),
+                    },
+                )
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match self {
+            Property::Collinear { points } => format!("{} points collinear", points.len()),
+            Property::Concyclic { points } => format!("{} points concyclic", points.len()),
+            Property::EqualLengths { constructions } => {
+                format!("equal lengths: {}", names(constructions))
+            }
+            Property::Parallel { constructions } => format!("parallel: {}", names(constructions)),
+            Property::Perpendicular { a, b } => {
+                format!("perpendicular: {} ⟂ {}", names(a), names(b))
+            }
+            Property::Concurrent {
+                point,
+                constructions,
+            } => format!("concurrent at {:?}: {}", point, names(constructions)),
+        }
+    }
+
+    /// Points to highlight when the property is selected.
+    pub fn points(&self) -> Vec<Pos> {
+        match self {
+            Property::Collinear { points } | Property::Concyclic { points } => points.clone(),
+            Property::Concurrent { point, .. } => vec![*point],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Indices of constructions to highlight when the property is selected.
+    pub fn constructions(&self) -> Vec<usize> {
+        match self {
+            Property::Collinear { .. } | Property::Concyclic { .. } => Vec::new(),
+            Property::EqualLengths { constructions }
+            | Property::Parallel { constructions }
+            | Property::Concurrent { constructions, .. } => constructions.clone(),
+            Property::Perpendicular { a, b } => a.iter().chain(b).copied().collect(),
+        }
+    }
+}
+
+/// The results of scanning a figure, kept up to date by the engine while it is turned on.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// How far apart lengths, angles and positions may be and still count as equal.
+    pub tolerance: f32,
+    pub properties: Vec<Property>,
+    /// The property under the pointer in the properties panel, to highlight on the plot.
+    pub highlighted: Option<usize>,
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Analysis {
+            tolerance: 1e-3,
+            properties: Vec::new(),
+            highlighted: None,
+        }
+    }
+}
+
+impl Analysis {
+    pub fn run(&mut self, constructions: &[Construction], labelled_points: &[Point]) {
+        let points = distinct_points(constructions, labelled_points, self.tolerance);
+
+        self.properties.clear();
+        self.highlighted = None;
+
+        self.collinear(&points, constructions);
+        self.concyclic(&points, constructions);
+        self.equal_lengths(constructions);
+        self.parallel(constructions);
+        self.concurrent(&points, constructions);
+    }
+
+    fn collinear(&mut self, points: &[Pos], constructions: &[Construction]) {
+        // Lines are keyed by angle and distance from the origin, with the angle scaled by
+        // the size of the figure so that both are in units of length
+        let scale = points.iter().map(|p| p.length()).fold(1.0, f32::max);
+
+        let mut lines: SpatialHash<2> = SpatialHash::new(self.tolerance);
+        let mut members: Vec<Vec<usize>> = Vec::new();
+
+        for i in 0..points.len() {
+            for j in i + 1..points.len() {
+                let direction = (points[j] - points[i]).normalized();
+                let angle = direction.y.atan2(direction.x).rem_euclid(PI);
+                let normal = Pos::from_angle(angle + PI / 2.0);
+                let offset = points[i].dot(normal);
+
+                // A line at an angle of almost π is also one at almost 0
+                let keys = [
+                    [angle * scale, offset],
+                    [(angle - PI) * scale, -offset],
+                    [(angle + PI) * scale, -offset],
+                ];
+
+                let found = keys.iter().find_map(|key| lines.find(key, self.tolerance));
+
+                let line = match found {
+                    Some(line) => line,
+                    None => {
+                        members.push(Vec::new());
+                        lines.insert(keys[0], members.len() - 1);
+                        members.len() - 1
+                    }
+                };
+
+                for k in [i, j] {
+                    if !members[line].contains(&k) {
+                        members[line].push(k);
+                    }
+                }
+            }
+        }
+
+        for indices in members.into_iter().filter(|indices| indices.len() >= 3) {
+            let line = Shape::Line(LineData {
+                p1: points[indices[0]],
+                p2: points[indices[1]],
+            });
+
+            if drawn(&line, constructions, self.tolerance) {
+                continue;
+            }
+
+            self.properties.push(Property::Collinear {
+                points: indices.iter().map(|&i| points[i]).collect(),
+            });
+        }
+    }
+
+    fn concyclic(&mut self, points: &[Pos], constructions: &[Construction]) {
+        let points = &points[..points.len().min(MAX_CONCYCLIC_POINTS)];
+
+        let mut circles: SpatialHash<3> = SpatialHash::new(self.tolerance);
+        let mut members: Vec<(Pos, f32, Vec<usize>)> = Vec::new();
+
+        for i in 0..points.len() {
+            for j in i + 1..points.len() {
+                for k in j + 1..points.len() {
+                    let Some((centre, r)) = circumcircle(points[i], points[j], points[k]) else {
+                        continue;
+                    };
+
+                    let key = [centre.x, centre.y, r];
+
+                    let circle = match circles.find(&key, self.tolerance) {
+                        Some(circle) => circle,
+                        None => {
+                            members.push((centre, r, Vec::new()));
+                            circles.insert(key, members.len() - 1);
+                            members.len() - 1
+                        }
+                    };
+
+                    for n in [i, j, k] {
+                        if !members[circle].2.contains(&n) {
+                            members[circle].2.push(n);
+                        }
+                    }
+                }
+            }
+        }
+
+        for (pos, r, indices) in members {
+            let circle = Shape::Circle(CircleData { pos, r });
+
+            if indices.len() < 4 || drawn(&circle, constructions, self.tolerance) {
+                continue;
+            }
+
+            self.properties.push(Property::Concyclic {
+                points: indices.iter().map(|&i| points[i]).collect(),
+            });
+        }
+    }
+
+    fn equal_lengths(&mut self, constructions: &[Construction]) {
+        let lengths = constructions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, construction)| match &construction.shape {
+                Shape::Segment(segment) => Some((i, segment.p1.distance(segment.p2))),
+                _ => None,
+            })
+            .collect();
+
+        for group in groups(lengths, self.tolerance, None) {
+            self.properties.push(Property::EqualLengths {
+                constructions: group,
+            });
+        }
+    }
+
+    fn parallel(&mut self, constructions: &[Construction]) {
+        let angles: Vec<(usize, f32)> = constructions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, construction)| {
+                let (p1, p2) = endpoints(&construction.shape)?;
+                let direction = p2 - p1;
+
+                Some((i, direction.y.atan2(direction.x).rem_euclid(PI)))
+            })
+            .collect();
+
+        let angle = |i: usize| angles.iter().find(|(j, _)| *j == i).map_or(0.0, |a| a.1);
+
+        // Groups of one are kept so that single lines can still be perpendicular to others
+        let groups = groups(angles.clone(), self.tolerance, Some(PI));
+
+        for (n, a) in groups.iter().enumerate() {
+            if a.len() >= 2 {
+                self.properties.push(Property::Parallel {
+                    constructions: a.clone(),
+                });
+            }
+
+            for b in &groups[n + 1..] {
+                let difference = (angle(a[0]) - angle(b[0])).abs();
+
+                if (difference - PI / 2.0).abs() <= self.tolerance {
+                    self.properties.push(Property::Perpendicular {
+                        a: a.clone(),
+                        b: b.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn concurrent(&mut self, points: &[Pos], constructions: &[Construction]) {
+        // Lines drawn from the point pass through it by construction, so don't count
+        for point in points {
+            let through: Vec<usize> = constructions
+                .iter()
+                .enumerate()
+                .filter(|(_, construction)| match endpoints(&construction.shape) {
+                    Some((p1, p2)) => {
+                        p1.distance(*point) > self.tolerance
+                            && p2.distance(*point) > self.tolerance
+                            && construction.shape.distance(*point) <= self.tolerance
+                    }
+                    None => false,
+                })
+                .map(|(i, _)| i)
+                .collect();
+
+            if through.len() >= 3 {
+                self.properties.push(Property::Concurrent {
+                    point: *point,
+                    constructions: through,
+                });
+            }
+        }
+    }
+}
+
+/// Buckets keys into cubes of side `cell` so that keys close to a given one can be found
+/// by looking in the neighbouring cubes, rather than by checking every key.
+struct SpatialHash<const N: usize> {
+    cell: f32,
+    cells: HashMap<[i64; N], Vec<([f32; N], usize)>>,
+}
+
+impl<const N: usize> SpatialHash<N> {
+    fn new(cell: f32) -> Self {
+        SpatialHash {
+            cell,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell_of(&self, key: &[f32; N]) -> [i64; N] {
+        key.map(|x| (x / self.cell).floor() as i64)
+    }
+
+    fn insert(&mut self, key: [f32; N], value: usize) {
+        self.cells
+            .entry(self.cell_of(&key))
+            .or_default()
+            .push((key, value));
+    }
+
+    /// A value whose key is within `tolerance` of `key` in every coordinate. `tolerance`
+    /// must be no more than the cell size.
+    fn find(&self, key: &[f32; N], tolerance: f32) -> Option<usize> {
+        let centre = self.cell_of(key);
+
+        (0..3usize.pow(N as u32)).find_map(|n| {
+            let mut cell = centre;
+            let mut n = n;
+
+            for c in cell.iter_mut() {
+                *c += (n % 3) as i64 - 1;
+                n /= 3;
+            }
+
+            self.cells.get(&cell)?.iter().find_map(|(other, value)| {
+                key.iter()
+                    .zip(other)
+                    .all(|(a, b)| (a - b).abs() <= tolerance)
+                    .then_some(*value)
+            })
+        })
+    }
+}
+
+/// Intersections and labelled points, with duplicates removed.
+fn distinct_points(
+    constructions: &[Construction],
+    labelled_points: &[Point],
+    tolerance: f32,
+) -> Vec<Pos> {
+    let mut hash: SpatialHash<2> = SpatialHash::new(tolerance);
+    let mut points = Vec::new();
+
+    let candidates = labelled_points.iter().map(|point| point.pos).chain(
+        constructions
+            .iter()
+            .flat_map(|construction| construction.intersections.iter().copied()),
+    );
+
+    for point in candidates.filter(|point| point.is_finite()) {
+        let key = [point.x, point.y];
+
+        if hash.find(&key, tolerance).is_none() {
+            hash.insert(key, points.len());
+            points.push(point);
+        }
+    }
+
+    points
+}
+
+/// Indices whose values are within `tolerance` of a neighbour's, in groups of two or more,
+/// or of any size if `period` is given. Values are taken modulo `period` if it is given.
+fn groups(mut values: Vec<(usize, f32)>, tolerance: f32, period: Option<f32>) -> Vec<Vec<usize>> {
+    values.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut last = f32::NEG_INFINITY;
+
+    for (i, value) in &values {
+        match groups.last_mut() {
+            Some(group) if value - last <= tolerance => group.push(*i),
+            _ => groups.push(vec![*i]),
+        }
+
+        last = *value;
+    }
+
+    match period {
+        Some(period) => {
+            // The first and last groups meet if they wrap around
+            if let (Some(first), Some(last)) = (values.first(), values.last()) {
+                if groups.len() > 1 && first.1 + period - last.1 <= tolerance {
+                    let wrapped = groups.pop().unwrap_or_default();
+                    groups[0].extend(wrapped);
+                }
+            }
+
+            groups
+        }
+        None => groups
+            .into_iter()
+            .filter(|group| group.len() >= 2)
+            .collect(),
+    }
+}
+
+/// Whether a construction already draws `shape`.
+fn drawn(shape: &Shape, constructions: &[Construction], tolerance: f32) -> bool {
+    constructions.iter().any(|construction| {
+        let own = match &construction.shape {
+            Shape::Segment(segment) => Shape::Line(segment.line()),
+            Shape::Arc(arc) => Shape::Circle(arc.circle()),
+            other => other.clone(),
+        };
+
+        shape.matches(&own, tolerance)
+    })
+}
+
+/// The defining points of a line or segment.
+fn endpoints(shape: &Shape) -> Option<(Pos, Pos)> {
+    match shape {
+        Shape::Line(line) => Some((line.p1, line.p2)),
+        Shape::Segment(segment) => Some((segment.p1, segment.p2)),
+        _ => None,
+    }
+}
+
+fn circumcircle(a: Pos, b: Pos, c: Pos) -> Option<(Pos, f32)> {
+    let (b, c) = (b - a, c - a);
+    let d = 2.0 * (b.x * c.y - b.y * c.x);
+
+    if d.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    let centre = Pos::new(
+        (c.y * b.length_sq() - b.y * c.length_sq()) / d,
+        (b.x * c.length_sq() - c.x * b.length_sq()) / d,
+    );
+
+    Some((a + centre, centre.length()))
+}
@@ -1,24 +1,44 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use serde::{Deserialize, Serialize};
 use serde_yaml;
 
+use super::{
+    color::Color,
+    keys::{self, Action, KeyBinding},
+    snap::SnapClass,
+};
+
 // Maybe a macro would make this suck less
 
 #[derive(Debug, Clone)]
 pub struct EngineConfig {
-    pub colors: HashMap<String, egui::Color32>,
+    pub colors: HashMap<String, Color>,
+
+    pub tool_colors: Vec<Color>,
+
+    pub background_color: Color,
+    pub faint_background_color: Color,
+    pub extreme_background_color: Color,
+
+    pub grid_color: Color,
+    pub intersection_color: Color,
+    pub point_color: Color,
+    pub text_color: Color,
+
+    /// Decimal places shown in measurements.
+    pub precision: usize,
 
-    pub tool_colors: Vec<egui::Color32>,
+    /// Starting snap radius, in screen pixels.
+    pub snap_radius: f32,
+    /// Snap classes from the highest priority to the lowest.
+    pub snap_order: Vec<SnapClass>,
 
-    pub background_color: egui::Color32,
-    pub faint_background_color: egui::Color32,
-    pub extreme_background_color: egui::Color32,
+    /// Keyboard shortcuts for the app.
+    pub key_bindings: Vec<(KeyBinding, Action)>,
 
-    pub grid_color: egui::Color32,
-    pub intersection_color: egui::Color32,
-    pub point_color: egui::Color32,
-    pub text_color: egui::Color32,
+    /// Seconds between saves of the recovery file, or 0 to only save it on quit.
+    pub autosave_interval: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,83 +52,136 @@ struct Config {
     intersection_color: String,
     point_color: String,
     text_color: String,
+    precision: Option<usize>,
+    snap_radius: Option<f32>,
+    snap_order: Option<Vec<SnapClass>>,
+    /// Key to action, such as `ctrl+z: undo`.
+    key_bindings: Option<HashMap<String, String>>,
+    autosave_interval: Option<f32>,
 }
 
-impl EngineConfig {
-    pub fn read(file_name: &str) -> Self {
-        let raw = std::fs::read_to_string(file_name).unwrap();
-        let config: Config = serde_yaml::from_str(&raw).unwrap();
-
-        Self {
-            colors: config
-                .colors
-                .iter()
-                .map(|(name, hex)| (name.clone(), Self::hex_str_to_rgba(hex)))
-                .collect(),
-
-            tool_colors: config
-                .tool_colors
-                .iter()
-                .map(|color| {
-                    Self::hex_str_to_rgba(config.colors.get(color).expect("tool color not found"))
-                })
-                .collect(),
-
-            background_color: Self::hex_str_to_rgba(
-                config
-                    .colors
-                    .get(&config.background_color)
-                    .expect("background color not found"),
-            ),
-            faint_background_color: Self::hex_str_to_rgba(
-                config
-                    .colors
-                    .get(&config.faint_background_color)
-                    .expect("faint background color not found"),
-            ),
-            extreme_background_color: Self::hex_str_to_rgba(
-                config
-                    .colors
-                    .get(&config.faint_background_color)
-                    .expect("extreme background color not found"),
-            ),
-
-            grid_color: Self::hex_str_to_rgba(
-                config
-                    .colors
-                    .get(&config.grid_color)
-                    .expect("grid color not found"),
-            ),
-            intersection_color: Self::hex_str_to_rgba(
-                config
-                    .colors
-                    .get(&config.intersection_color)
-                    .expect("intersection color not found"),
-            ),
-            point_color: Self::hex_str_to_rgba(
-                config
-                    .colors
-                    .get(&config.point_color)
-                    .expect("point color not found"),
-            ),
-            text_color: Self::hex_str_to_rgba(
-                config
-                    .colors
-                    .get(&config.text_color)
-                    .expect("text color not found"),
-            ),
+/// A setting from the config, or from the built in config if it is left out.
+fn setting<T>(value: Option<T>, default: Option<T>) -> T {
+    value
+        .or(default)
+        .expect("the built in config has every setting")
+}
+
+/// The configuration the app ships with, used when `config.yml` cannot be read.
+const DEFAULT_CONFIG: &str = include_str!("../../config.yml");
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Yaml(serde_yaml::Error),
+    /// A colour is referred to by a name that is not listed under `colors`.
+    UnknownColor(String),
+    InvalidColor(String),
+    NoToolColors,
+    KeyBinding(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "could not read config: {}", e),
+            ConfigError::Yaml(e) => write!(f, "invalid config: {}", e),
+            ConfigError::UnknownColor(name) => write!(f, "color '{}' is not in colors", name),
+            ConfigError::InvalidColor(hex) => write!(f, "invalid color '{}'", hex),
+            ConfigError::NoToolColors => write!(f, "no tool colors are listed"),
+            ConfigError::KeyBinding(e) => write!(f, "invalid key binding: {}", e),
         }
     }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<serde_yaml::Error> for ConfigError {
+    fn from(e: serde_yaml::Error) -> Self {
+        ConfigError::Yaml(e)
+    }
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self::parse(DEFAULT_CONFIG).expect("the built in config is valid")
+    }
+}
+
+impl EngineConfig {
+    pub fn read(file_name: &str) -> Result<Self, ConfigError> {
+        Self::parse(&std::fs::read_to_string(file_name)?)
+    }
 
-    fn hex_str_to_rgba(hex: &str) -> egui::Color32 {
-        let r = u8::from_str_radix(&hex[1..3], 16).unwrap();
-        let g = u8::from_str_radix(&hex[3..5], 16).unwrap();
-        let b = u8::from_str_radix(&hex[5..7], 16).unwrap();
+    pub fn parse(raw: &str) -> Result<Self, ConfigError> {
+        let config: Config = serde_yaml::from_str(raw)?;
+        let defaults: Config = serde_yaml::from_str(DEFAULT_CONFIG)?;
+
+        let colors = config
+            .colors
+            .iter()
+            .map(|(name, hex)| Ok((name.clone(), Self::hex_str_to_rgba(hex)?)))
+            .collect::<Result<HashMap<_, _>, ConfigError>>()?;
+
+        let named = |name: &String| {
+            colors
+                .get(name)
+                .copied()
+                .ok_or_else(|| ConfigError::UnknownColor(name.clone()))
+        };
+
+        let tool_colors = config
+            .tool_colors
+            .iter()
+            .map(named)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if tool_colors.is_empty() {
+            return Err(ConfigError::NoToolColors);
+        }
+
+        Ok(Self {
+            tool_colors,
+
+            background_color: named(&config.background_color)?,
+            faint_background_color: named(&config.faint_background_color)?,
+            extreme_background_color: named(&config.faint_background_color)?,
+
+            grid_color: named(&config.grid_color)?,
+            intersection_color: named(&config.intersection_color)?,
+            point_color: named(&config.point_color)?,
+            text_color: named(&config.text_color)?,
+
+            colors,
+
+            precision: setting(config.precision, defaults.precision),
+
+            snap_radius: setting(config.snap_radius, defaults.snap_radius),
+            snap_order: setting(config.snap_order, defaults.snap_order),
+
+            key_bindings: keys::parse_bindings(
+                setting(config.key_bindings, defaults.key_bindings)
+                    .iter()
+                    .map(|(key, action)| (key.as_str(), action.as_str())),
+            )
+            .map_err(ConfigError::KeyBinding)?,
+
+            autosave_interval: setting(config.autosave_interval, defaults.autosave_interval)
+                .max(0.0),
+        })
+    }
 
-        egui::Color32::from_rgba_premultiplied(r, g, b, 255)
+    fn hex_str_to_rgba(hex: &str) -> Result<Color, ConfigError> {
+        Color::from_hex(hex).ok_or_else(|| ConfigError::InvalidColor(hex.to_owned()))
     }
 
-    pub fn get_name(&self, color: &egui::Color32) -> Option<String> {
+    pub fn get_name(&self, color: &Color) -> Option<String> {
         self.colors
             .iter()
             .find_map(|(name, c)| if c == color { Some(name.clone()) } else { None })
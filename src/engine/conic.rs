@@ -0,0 +1,771 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{pos::Pos, utils};
+
+/// Points taken along each branch of a conic when it is drawn or exported.
+const SAMPLES: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConicKind {
+    Ellipse,
+    Parabola,
+    Hyperbola,
+    /// Pairs of lines, single points and conics with no points at all.
+    Degenerate,
+}
+
+impl ConicKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConicKind::Ellipse => "Ellipse",
+            ConicKind::Parabola => "Parabola",
+            ConicKind::Hyperbola => "Hyperbola",
+            ConicKind::Degenerate => "Conic",
+        }
+    }
+}
+
+/// The conic `a x² + b xy + c y² + d x + e y + f = 0`. Conics cannot be drawn with compass
+/// and straight edge, so they are always drawn dashed to set them apart.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConicData {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl fmt::Display for ConicData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:.3}x² + {:.3}xy + {:.3}y² + {:.3}x + {:.3}y + {:.3} = 0",
+            self.kind().name(),
+            self.a,
+            self.b,
+            self.c,
+            self.d,
+            self.e,
+            self.f
+        )
+    }
+}
+
+/// The shape of a conic in coordinates rotated to remove its xy term.
+enum Form {
+    Ellipse {
+        centre: (f64, f64),
+        p: f64,
+        q: f64,
+    },
+    /// `(x - x0)²/p² - (y - y0)²/q² = ±1`, opening along x when `along_x`.
+    Hyperbola {
+        centre: (f64, f64),
+        p: f64,
+        q: f64,
+        along_x: bool,
+    },
+    /// `y - y0 = k (x - x0)²` when `along_x`, otherwise with x and y swapped.
+    Parabola {
+        vertex: (f64, f64),
+        k: f64,
+        along_x: bool,
+    },
+    /// Lines through a point in a direction.
+    Lines(Vec<((f64, f64), (f64, f64))>),
+    Empty,
+}
+
+struct Canonical {
+    /// Anticlockwise rotation from the canonical coordinates back to the plane.
+    angle: f64,
+    form: Form,
+}
+
+impl Canonical {
+    fn to_plane(&self, point: (f64, f64)) -> Pos {
+        Canonical::rotate(self.angle, point)
+    }
+
+    fn rotate(angle: f64, (x, y): (f64, f64)) -> Pos {
+        let (sin, cos) = angle.sin_cos();
+
+        Pos::new((x * cos - y * sin) as f32, (x * sin + y * cos) as f32)
+    }
+}
+
+impl ConicData {
+    /// The ellipse with foci `f1` and `f2` through `point`.
+    pub fn ellipse(f1: Pos, f2: Pos, point: Pos) -> Self {
+        let a = (point.distance(f1) + point.distance(f2)) as f64 / 2.0;
+
+        ConicData::from_foci(f1, f2, a, 1.0)
+    }
+
+    /// The hyperbola with foci `f1` and `f2` through `point`. Both branches are included.
+    pub fn hyperbola(f1: Pos, f2: Pos, point: Pos) -> Self {
+        let a = (point.distance(f1) - point.distance(f2)).abs() as f64 / 2.0;
+
+        ConicData::from_foci(f1, f2, a, -1.0)
+    }
+
+    /// `b² x² ± a² y² = a² b²` with `b² = ±(a² - c²)`, moved so its foci are `f1` and `f2`.
+    fn from_foci(f1: Pos, f2: Pos, a: f64, sign: f64) -> Self {
+        let c = f1.distance(f2) as f64 / 2.0;
+        let b_sq = sign * (a * a - c * c);
+
+        let centre = (f1 + f2) / 2.0;
+        let axis = if f1 == f2 {
+            Pos::new(1.0, 0.0)
+        } else {
+            (f2 - f1).normalized()
+        };
+
+        ConicData::canonical(b_sq, sign * a * a, 0.0, 0.0, -a * a * b_sq).moved(centre, axis)
+    }
+
+    /// The parabola with `focus` and `vertex`.
+    pub fn parabola(focus: Pos, vertex: Pos) -> Self {
+        let axis = if focus == vertex {
+            Pos::new(1.0, 0.0)
+        } else {
+            (focus - vertex).normalized()
+        };
+
+        // y² = 4px along the axis
+        let p = focus.distance(vertex) as f64;
+
+        ConicData::canonical(0.0, 1.0, -4.0 * p, 0.0, 0.0).moved(vertex, axis)
+    }
+
+    /// The conic through five points, or `None` if they do not fix a single conic, as when
+    /// four of them are collinear or two coincide.
+    pub fn through(points: &[Pos]) -> Option<Self> {
+        let mut rows: Vec<[f64; 6]> = points
+            .iter()
+            .map(|p| {
+                let (x, y) = (p.x as f64, p.y as f64);
+                [x * x, x * y, y * y, x, y, 1.0]
+            })
+            .collect();
+
+        let scale = rows
+            .iter()
+            .flatten()
+            .fold(0.0_f64, |max, v| max.max(v.abs()))
+            .max(1.0);
+
+        // Gaussian elimination with partial pivoting, leaving one column without a pivot
+        let mut pivots = Vec::new();
+        let mut row = 0;
+
+        for column in 0..6 {
+            if row == rows.len() {
+                break;
+            }
+
+            let best = (row..rows.len())
+                .max_by(|&i, &j| rows[i][column].abs().total_cmp(&rows[j][column].abs()))?;
+
+            if rows[best][column].abs() <= 1e-9 * scale {
+                continue;
+            }
+
+            rows.swap(row, best);
+
+            let pivot = rows[row];
+
+            for (i, other) in rows.iter_mut().enumerate() {
+                if i != row {
+                    let factor = other[column] / pivot[column];
+
+                    for (value, pivot_value) in other.iter_mut().zip(pivot) {
+                        *value -= factor * pivot_value;
+                    }
+                }
+            }
+
+            pivots.push(column);
+            row += 1;
+        }
+
+        if pivots.len() != 5 {
+            return None;
+        }
+
+        let free = (0..6).find(|column| !pivots.contains(column))?;
+        let mut coefficients = [0.0; 6];
+        coefficients[free] = 1.0;
+
+        for (row, &column) in pivots.iter().enumerate() {
+            coefficients[column] = -rows[row][free] / rows[row][column];
+        }
+
+        let [a, b, c, d, e, f] = coefficients;
+
+        Some(ConicData::from_f64([a, b, c, d, e, f]))
+    }
+
+    /// A conic with no xy term about the origin, as `a x² + c y² + d x + e y + f = 0`.
+    fn canonical(a: f64, c: f64, d: f64, e: f64, f: f64) -> Self {
+        ConicData::from_f64([a, 0.0, c, d, e, f])
+    }
+
+    /// Scales the coefficients so the largest is 1, keeping them within `f32` range.
+    fn from_f64(coefficients: [f64; 6]) -> Self {
+        let scale = coefficients.iter().fold(0.0_f64, |max, v| max.max(v.abs()));
+        let scale = if scale > 0.0 && scale.is_finite() {
+            scale
+        } else {
+            1.0
+        };
+
+        let [a, b, c, d, e, f] = coefficients.map(|v| (v / scale) as f32);
+
+        ConicData { a, b, c, d, e, f }
+    }
+
+    fn coefficients(&self) -> [f64; 6] {
+        [self.a, self.b, self.c, self.d, self.e, self.f].map(|v| v as f64)
+    }
+
+    fn matrix(&self) -> [[f64; 3]; 3] {
+        let [a, b, c, d, e, f] = self.coefficients();
+
+        [
+            [a, b / 2.0, d / 2.0],
+            [b / 2.0, c, e / 2.0],
+            [d / 2.0, e / 2.0, f],
+        ]
+    }
+
+    fn from_matrix(m: [[f64; 3]; 3]) -> Self {
+        ConicData::from_f64([
+            m[0][0],
+            m[0][1] + m[1][0],
+            m[1][1],
+            m[0][2] + m[2][0],
+            m[1][2] + m[2][1],
+            m[2][2],
+        ])
+    }
+
+    /// The coefficients of GeoGebra's matrix form,
+    /// `A0 x² + A1 y² + A2 + 2 A3 xy + 2 A4 x + 2 A5 y = 0`.
+    pub fn ggb_matrix(&self) -> [f32; 6] {
+        [
+            self.a,
+            self.c,
+            self.f,
+            self.b / 2.0,
+            self.d / 2.0,
+            self.e / 2.0,
+        ]
+    }
+
+    pub fn from_ggb_matrix(m: [f32; 6]) -> Self {
+        let m = m.map(|v| v as f64);
+
+        ConicData::from_f64([m[0], 2.0 * m[3], m[1], 2.0 * m[4], 2.0 * m[5], m[2]])
+    }
+
+    /// Moves a conic about the origin so the origin goes to `origin` and the x axis lies
+    /// along the unit vector `axis`.
+    fn moved(&self, origin: Pos, axis: Pos) -> Self {
+        self.transformed(|p| {
+            let rel = p - origin;
+            Pos::new(rel.dot(axis), rel.x * -axis.y + rel.y * axis.x)
+        })
+    }
+
+    /// The image of the conic under an affine map, given the map's inverse.
+    pub fn transformed(&self, inverse: impl Fn(Pos) -> Pos) -> Self {
+        let origin = inverse(Pos::ZERO);
+        let (u, v) = (inverse(Pos::new(1.0, 0.0)), inverse(Pos::new(0.0, 1.0)));
+        let (u, v) = (u - origin, v - origin);
+
+        // Homogeneous matrix of the inverse map, by columns
+        let h = [
+            [u.x as f64, v.x as f64, origin.x as f64],
+            [u.y as f64, v.y as f64, origin.y as f64],
+            [0.0, 0.0, 1.0],
+        ];
+        let s = self.matrix();
+
+        let mut result = [[0.0; 3]; 3];
+
+        for (i, row) in result.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3)
+                    .flat_map(|k| (0..3).map(move |l| (k, l)))
+                    .map(|(k, l)| h[k][i] * s[k][l] * h[l][j])
+                    .sum();
+            }
+        }
+
+        ConicData::from_matrix(result)
+    }
+
+    /// The left hand side of the conic's equation at `p`.
+    pub fn value(&self, p: Pos) -> f64 {
+        let [a, b, c, d, e, f] = self.coefficients();
+        let (x, y) = (p.x as f64, p.y as f64);
+
+        a * x * x + b * x * y + c * y * y + d * x + e * y + f
+    }
+
+    fn gradient(&self, p: Pos) -> (f64, f64) {
+        let [a, b, c, d, e, _] = self.coefficients();
+        let (x, y) = (p.x as f64, p.y as f64);
+
+        (2.0 * a * x + b * y + d, b * x + 2.0 * c * y + e)
+    }
+
+    /// Moves `p` onto the conic along the gradient, or `None` if it does not settle.
+    pub fn project(&self, p: Pos) -> Option<Pos> {
+        let mut p = p;
+
+        for _ in 0..8 {
+            let value = self.value(p);
+            let (gx, gy) = self.gradient(p);
+            let length_sq = gx * gx + gy * gy;
+
+            if length_sq == 0.0 {
+                return None;
+            }
+
+            p = Pos::new(
+                (p.x as f64 - value * gx / length_sq) as f32,
+                (p.y as f64 - value * gy / length_sq) as f32,
+            );
+        }
+
+        let (gx, gy) = self.gradient(p);
+        let error = self.value(p).abs() / (gx * gx + gy * gy).sqrt();
+
+        (error <= 1e-3 * (1.0 + p.length() as f64)).then_some(p)
+    }
+
+    /// The distance from `p` to the nearest point found by following the gradient, which
+    /// is the true distance for points close to the conic.
+    pub fn distance(&self, p: Pos) -> f32 {
+        match self.project(p) {
+            Some(foot) => p.distance(foot),
+            None => f32::INFINITY,
+        }
+    }
+
+    fn canonical_form(&self) -> Canonical {
+        let [a, b, c, d, e, f] = self.coefficients();
+
+        let angle = 0.5 * b.atan2(a - c);
+        let (sin, cos) = angle.sin_cos();
+
+        let a2 = a * cos * cos + b * cos * sin + c * sin * sin;
+        let c2 = a * sin * sin - b * sin * cos + c * cos * cos;
+        let d2 = d * cos + e * sin;
+        let e2 = -d * sin + e * cos;
+
+        let scale = [a2, c2, d2, e2, f]
+            .iter()
+            .fold(0.0_f64, |max, v| max.max(v.abs()));
+        let small = |v: f64| v.abs() <= 1e-6 * scale;
+
+        let form = match (small(a2), small(c2)) {
+            _ if scale == 0.0 || !scale.is_finite() => Form::Empty,
+            (true, true) if small(d2) && small(e2) => Form::Empty,
+            (true, true) => {
+                // d x + e y + f = 0
+                let length_sq = d2 * d2 + e2 * e2;
+                let point = (-f * d2 / length_sq, -f * e2 / length_sq);
+
+                Form::Lines(vec![(point, (-e2, d2))])
+            }
+            (false, true) if small(e2) => parallel_lines(a2, d2, f, true),
+            (true, false) if small(d2) => parallel_lines(c2, e2, f, false),
+            (false, true) => {
+                let x0 = -d2 / (2.0 * a2);
+
+                Form::Parabola {
+                    vertex: (x0, -(a2 * x0 * x0 + d2 * x0 + f) / e2),
+                    k: -a2 / e2,
+                    along_x: true,
+                }
+            }
+            (true, false) => {
+                let y0 = -e2 / (2.0 * c2);
+
+                Form::Parabola {
+                    vertex: (-(c2 * y0 * y0 + e2 * y0 + f) / d2, y0),
+                    k: -c2 / d2,
+                    along_x: false,
+                }
+            }
+            (false, false) => {
+                let centre = (-d2 / (2.0 * a2), -e2 / (2.0 * c2));
+                let k = a2 * centre.0 * centre.0 + c2 * centre.1 * centre.1 - f;
+
+                let small = |v: f64| {
+                    v.abs()
+                        <= 1e-6
+                            * (f.abs()
+                                + (a2 * centre.0 * centre.0).abs()
+                                + (c2 * centre.1 * centre.1).abs())
+                };
+
+                // a (x - x0)² + c (y - y0)² = k
+                if small(k) && a2 * c2 < 0.0 {
+                    let (p, q) = (c2.abs().sqrt(), a2.abs().sqrt());
+
+                    Form::Lines(vec![(centre, (p, q)), (centre, (p, -q))])
+                } else if small(k) || k / a2 < 0.0 && k / c2 < 0.0 {
+                    Form::Empty
+                } else if a2 * c2 > 0.0 {
+                    Form::Ellipse {
+                        centre,
+                        p: (k / a2).sqrt(),
+                        q: (k / c2).sqrt(),
+                    }
+                } else {
+                    Form::Hyperbola {
+                        centre,
+                        p: (k / a2).abs().sqrt(),
+                        q: (k / c2).abs().sqrt(),
+                        along_x: k / a2 > 0.0,
+                    }
+                }
+            }
+        };
+
+        Canonical { angle, form }
+    }
+
+    pub fn kind(&self) -> ConicKind {
+        match self.canonical_form().form {
+            Form::Ellipse { .. } => ConicKind::Ellipse,
+            Form::Hyperbola { .. } => ConicKind::Hyperbola,
+            Form::Parabola { .. } => ConicKind::Parabola,
+            Form::Lines(_) | Form::Empty => ConicKind::Degenerate,
+        }
+    }
+
+    /// The centre of an ellipse or hyperbola, or the vertex of a parabola.
+    pub fn anchor(&self) -> Option<Pos> {
+        let canonical = self.canonical_form();
+
+        match &canonical.form {
+            Form::Ellipse { centre, .. } | Form::Hyperbola { centre, .. } => {
+                Some(canonical.to_plane(*centre))
+            }
+            Form::Parabola { vertex, .. } => Some(canonical.to_plane(*vertex)),
+            Form::Lines(lines) => lines.first().map(|(point, _)| canonical.to_plane(*point)),
+            Form::Empty => None,
+        }
+    }
+
+    /// A box containing the conic. Unbounded conics are bounded by their anchor.
+    pub fn bounds(&self) -> (Pos, Pos) {
+        if self.kind() == ConicKind::Ellipse {
+            let points = self.branches(Pos::ZERO, Pos::ZERO).concat();
+
+            return points.iter().fold((points[0], points[0]), |(min, max), p| {
+                (min.min(*p), max.max(*p))
+            });
+        }
+
+        let anchor = self.anchor().unwrap_or(Pos::ZERO);
+        (anchor, anchor)
+    }
+
+    /// A length on the scale of the conic's shape: its larger semi-axis, or the distance
+    /// from a parabola's vertex to its focus.
+    fn size(&self) -> f32 {
+        match self.canonical_form().form {
+            Form::Ellipse { p, q, .. } | Form::Hyperbola { p, q, .. } => p.max(q) as f32,
+            Form::Parabola { k, .. } => (0.25 / k.abs()) as f32,
+            Form::Lines(_) | Form::Empty => 1.0,
+        }
+    }
+
+    /// Polylines along each branch of the conic, reaching at least as far as the box from
+    /// `min` to `max`.
+    pub fn branches(&self, min: Pos, max: Pos) -> Vec<Vec<Pos>> {
+        let Canonical { angle, form } = self.canonical_form();
+        let to_plane = |point| Canonical::rotate(angle, point);
+
+        // Rotation keeps distances from the origin, so this reaches the box in both frames
+        let reach = [min, max, Pos::new(min.x, max.y), Pos::new(max.x, min.y)]
+            .iter()
+            .fold(0.0_f64, |reach, corner| reach.max(corner.length() as f64));
+
+        let samples = |from: f64, to: f64, point: &dyn Fn(f64) -> (f64, f64)| {
+            (0..=SAMPLES)
+                .map(|i| {
+                    let t = from + (to - from) * i as f64 / SAMPLES as f64;
+                    to_plane(point(t))
+                })
+                .collect::<Vec<Pos>>()
+        };
+
+        let distance = |(x, y): (f64, f64)| (x * x + y * y).sqrt();
+
+        match form {
+            Form::Ellipse { centre, p, q } => {
+                vec![samples(0.0, std::f64::consts::TAU, &|t| {
+                    (centre.0 + p * t.cos(), centre.1 + q * t.sin())
+                })]
+            }
+            Form::Hyperbola {
+                centre,
+                p,
+                q,
+                along_x,
+            } => {
+                let reach = reach + distance(centre);
+                let s = reach / p.min(q);
+
+                [1.0, -1.0]
+                    .iter()
+                    .map(|&side| {
+                        samples(-s, s, &|s: f64| {
+                            let (along, across) = (side * (1.0 + s * s).sqrt(), s);
+
+                            match along_x {
+                                true => (centre.0 + p * along, centre.1 + q * across),
+                                false => (centre.0 + p * across, centre.1 + q * along),
+                            }
+                        })
+                    })
+                    .collect()
+            }
+            Form::Parabola { vertex, k, along_x } => {
+                let reach = reach + distance(vertex);
+                let u = reach.min((reach / k.abs()).sqrt());
+
+                vec![samples(-u, u, &|u| match along_x {
+                    true => (vertex.0 + u, vertex.1 + k * u * u),
+                    false => (vertex.0 + k * u * u, vertex.1 + u),
+                })]
+            }
+            Form::Lines(lines) => lines
+                .iter()
+                .map(|&(point, direction)| {
+                    let reach = reach + distance(point);
+                    let length = distance(direction);
+
+                    [-reach, reach]
+                        .iter()
+                        .map(|t| {
+                            to_plane((
+                                point.0 + direction.0 * t / length,
+                                point.1 + direction.1 * t / length,
+                            ))
+                        })
+                        .collect()
+                })
+                .collect(),
+            Form::Empty => Vec::new(),
+        }
+    }
+
+    /// Points where the line through `p1` and `p2` meets the conic, from the quadratic in
+    /// `t` for the point `p1 + t (p2 - p1)`.
+    pub fn line_intersections(&self, p1: Pos, p2: Pos) -> Vec<Pos> {
+        if self.is_zero() {
+            return Vec::new();
+        }
+
+        let [a, b, c, d, e, _] = self.coefficients();
+        let (x, y) = (p1.x as f64, p1.y as f64);
+        let (dx, dy) = ((p2.x - p1.x) as f64, (p2.y - p1.y) as f64);
+
+        let quadratic = a * dx * dx + b * dx * dy + c * dy * dy;
+        let linear = 2.0 * a * x * dx + b * (x * dy + y * dx) + 2.0 * c * y * dy + d * dx + e * dy;
+
+        merge(
+            utils::polynomial_roots(&[self.value(p1), linear, quadratic])
+                .into_iter()
+                .map(|t| Pos::new((x + t * dx) as f32, (y + t * dy) as f32))
+                .collect(),
+        )
+    }
+
+    /// Points where the circle about `pos` with radius `r` meets the conic. Substituting
+    /// `u = tan(θ/2)` for the point at angle θ gives a quartic in `u`, which misses θ = π.
+    pub fn circle_intersections(&self, pos: Pos, r: f32) -> Vec<Pos> {
+        if self.is_zero() {
+            return Vec::new();
+        }
+
+        let [a, b, c, d, e, f] = self.coefficients();
+        let (px, py, r) = (pos.x as f64, pos.y as f64, r as f64);
+
+        // x = X/W and y = Y/W, each quadratic in u
+        let w = [1.0, 0.0, 1.0];
+        let x = [px + r, 0.0, px - r];
+        let y = [py, 2.0 * r, py];
+
+        let mut quartic = [0.0; 5];
+
+        for (k, (p, q)) in [
+            (a, (x, x)),
+            (b, (x, y)),
+            (c, (y, y)),
+            (d, (x, w)),
+            (e, (y, w)),
+            (f, (w, w)),
+        ] {
+            for i in 0..3 {
+                for j in 0..3 {
+                    quartic[i + j] += k * p[i] * q[j];
+                }
+            }
+        }
+
+        let mut points: Vec<Pos> = utils::polynomial_roots(&quartic)
+            .into_iter()
+            .map(|u| {
+                let w = 1.0 + u * u;
+
+                Pos::new(
+                    (px + r * (1.0 - u * u) / w) as f32,
+                    (py + r * 2.0 * u / w) as f32,
+                )
+            })
+            .collect();
+
+        let opposite = Pos::new((px - r) as f32, py as f32);
+
+        if self.on_conic(opposite) {
+            points.push(opposite);
+        }
+
+        merge(points)
+    }
+
+    /// Approximate points where two conics meet, found by walking along the branches of this
+    /// one near both and refining crossings of the other.
+    pub fn conic_intersections(&self, other: &ConicData) -> Vec<Pos> {
+        if self.is_zero() || other.is_zero() {
+            return Vec::new();
+        }
+
+        let (min_a, max_a) = self.bounds();
+        let (min_b, max_b) = other.bounds();
+        let (min, max) = (min_a.min(min_b), max_a.max(max_b));
+        let pad = (max - min).length() + 4.0 * self.size().max(other.size());
+        let pad = Pos::new(pad, pad);
+
+        let mut points: Vec<Pos> = Vec::new();
+
+        for branch in self.branches(min - pad, max + pad) {
+            for pair in branch.windows(2) {
+                let (v1, v2) = (other.value(pair[0]), other.value(pair[1]));
+
+                if v1 == 0.0 || v1.signum() == v2.signum() {
+                    continue;
+                }
+
+                let t = (v1 / (v1 - v2)) as f32;
+
+                if let Some(point) = newton(self, other, pair[0] + (pair[1] - pair[0]) * t) {
+                    points.push(point);
+                }
+            }
+        }
+
+        merge(points)
+    }
+
+    /// Whether every coefficient is zero, which every point would satisfy. Such a conic has
+    /// no points, as it does not describe a curve.
+    fn is_zero(&self) -> bool {
+        self.coefficients().iter().all(|v| *v == 0.0)
+    }
+
+    fn on_conic(&self, p: Pos) -> bool {
+        if self.is_zero() {
+            return false;
+        }
+
+        let (gx, gy) = self.gradient(p);
+
+        self.value(p).abs() <= 1e-5 * (1.0 + (gx * gx + gy * gy).sqrt()) * (1.0 + p.length() as f64)
+    }
+
+    /// Whether the conics have the same coefficients up to scale.
+    pub fn matches(&self, other: &ConicData, tolerance: f32) -> bool {
+        let normalised = |conic: &ConicData| {
+            let coefficients = conic.coefficients();
+            let largest = coefficients
+                .iter()
+                .copied()
+                .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+                .unwrap_or(1.0);
+
+            coefficients.map(|v| v / largest)
+        };
+
+        normalised(self)
+            .iter()
+            .zip(normalised(other))
+            .all(|(a, b)| (a - b).abs() <= tolerance as f64)
+    }
+}
+
+/// `a t² + d t + f = 0` in one coordinate, which is a pair of parallel lines.
+fn parallel_lines(a: f64, d: f64, f: f64, along_x: bool) -> Form {
+    Form::Lines(
+        utils::polynomial_roots(&[f, d, a])
+            .into_iter()
+            .map(|t| match along_x {
+                true => ((t, 0.0), (0.0, 1.0)),
+                false => ((0.0, t), (1.0, 0.0)),
+            })
+            .collect(),
+    )
+}
+
+/// Merges points that are equal to within rounding. Tangents give a repeated root that
+/// rounding can split into two close roots, which should be a single intersection.
+fn merge(points: Vec<Pos>) -> Vec<Pos> {
+    let mut merged: Vec<Pos> = Vec::new();
+
+    for point in points {
+        match merged
+            .iter_mut()
+            .find(|p| p.distance(point) <= 1e-3 * (1.0 + p.length()))
+        {
+            Some(p) => *p = (*p + point) / 2.0,
+            None => merged.push(point),
+        }
+    }
+
+    merged
+}
+
+/// Newton's method on both conics' equations together, starting from `p`.
+fn newton(a: &ConicData, b: &ConicData, p: Pos) -> Option<Pos> {
+    let mut p = p;
+
+    for _ in 0..8 {
+        let (f, g) = (a.value(p), b.value(p));
+        let ((fx, fy), (gx, gy)) = (a.gradient(p), b.gradient(p));
+        let determinant = fx * gy - fy * gx;
+
+        if determinant == 0.0 {
+            break;
+        }
+
+        p = Pos::new(
+            (p.x as f64 - (f * gy - g * fy) / determinant) as f32,
+            (p.y as f64 - (g * fx - f * gx) / determinant) as f32,
+        );
+    }
+
+    (p.is_finite() && a.on_conic(p) && b.on_conic(p)).then_some(p)
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    locus::{self, LocusData},
+    pos::Pos,
+    shapes::{ConicKind, Construction, Shape},
+    tools,
+};
+
+/// Where a point clicked while making a construction came from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Input {
+    /// A point placed freely or on a labelled point, which can be moved.
+    Free { pos: Pos },
+    /// The intersection of the constructions at indices `a` and `b` nearest to `pos`, which
+    /// is where it was when last worked out.
+    Intersection { a: usize, b: usize, pos: Pos },
+    /// A point snapped onto the construction at index `on`, which stays on it as it moves.
+    OnCurve { on: usize, pos: Pos },
+}
+
+impl Input {
+    /// Finds where `pos` came from: an intersection of two of `constructions`, or a free point.
+    pub fn find(constructions: &[Construction], pos: Pos) -> Input {
+        let tolerance = 1e-5 * (1.0 + pos.length());
+
+        for (b, later) in constructions.iter().enumerate() {
+            if !later.intersections.contains(&pos) {
+                continue;
+            }
+
+            let earlier = constructions[..b].iter().position(|construction| {
+                later
+                    .shape
+                    .intersections(&construction.shape)
+                    .iter()
+                    .any(|point| point.distance(pos) <= tolerance)
+            });
+
+            if let Some(a) = earlier {
+                return Input::Intersection { a, b, pos };
+            }
+        }
+
+        Input::Free { pos }
+    }
+
+    pub fn pos(&self) -> Pos {
+        match self {
+            Input::Free { pos } | Input::Intersection { pos, .. } | Input::OnCurve { pos, .. } => {
+                *pos
+            }
+        }
+    }
+
+    /// The input after constructions are deleted or reordered, where `map` gives each old
+    /// index's new one and `before` is the new index of the construction the input belongs to.
+    /// It becomes free where it would depend on a construction that is gone or now comes later.
+    pub fn reindex(&self, before: usize, map: &impl Fn(usize) -> Option<usize>) -> Input {
+        let earlier = |i: usize| map(i).filter(|&i| i < before);
+
+        match *self {
+            Input::Intersection { a, b, pos } => match (earlier(a), earlier(b)) {
+                (Some(a), Some(b)) => Input::Intersection { a, b, pos },
+                _ => Input::Free { pos },
+            },
+            Input::OnCurve { on, pos } => match earlier(on) {
+                Some(on) => Input::OnCurve { on, pos },
+                None => Input::Free { pos },
+            },
+            Input::Free { pos } => Input::Free { pos },
+        }
+    }
+
+    /// Where the input is once the point at `from` has moved to `to` and the constructions
+    /// have been made again as `moved`. `None` if it no longer exists.
+    pub fn follow(&self, moved: &[Construction], from: Pos, to: Pos) -> Option<Input> {
+        Some(match self {
+            Input::Free { pos } if *pos == from => Input::Free { pos: to },
+            Input::Free { pos } => Input::Free { pos: *pos },
+            Input::Intersection { a, b, pos } => Input::Intersection {
+                a: *a,
+                b: *b,
+                pos: nearest(
+                    moved.get(*a)?.shape.intersections(&moved.get(*b)?.shape),
+                    *pos,
+                )?,
+            },
+            Input::OnCurve { on, pos } if *pos == from => Input::OnCurve { on: *on, pos: to },
+            Input::OnCurve { on, pos } => Input::OnCurve {
+                on: *on,
+                pos: moved.get(*on)?.shape.project(*pos)?,
+            },
+        })
+    }
+
+    /// Where the input is after the point at `from` moves to `to`, given the shapes worked out
+    /// so far and which of them changed. `None` if it no longer exists.
+    fn resolve(&self, shapes: &[Shape], changed: &[bool], from: Pos, to: Pos) -> Option<Input> {
+        Some(match self {
+            Input::Free { pos } if *pos == from => Input::Free { pos: to },
+            Input::Intersection { a, b, pos } if changed[*a] || changed[*b] => {
+                Input::Intersection {
+                    a: *a,
+                    b: *b,
+                    pos: nearest(shapes[*a].intersections(&shapes[*b]), *pos)?,
+                }
+            }
+            Input::OnCurve { on, pos } if *pos == from => Input::OnCurve { on: *on, pos: to },
+            Input::OnCurve { on, pos } if changed[*on] => Input::OnCurve {
+                on: *on,
+                pos: shapes[*on].project(*pos)?,
+            },
+            _ => self.clone(),
+        })
+    }
+}
+
+/// How a construction was made, so it can be made again when the points it depends on move.
+/// Constructions without one, such as those from scripts or imported files, stay where they are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Definition {
+    /// Name of the tool that made the construction, or [`locus::TOOL`] for a locus.
+    pub tool: String,
+    pub inputs: Vec<Input>,
+}
+
+impl Definition {
+    /// Updates the indices the inputs refer to after constructions are deleted or reordered.
+    /// See [`Input::reindex`].
+    pub fn reindex(&mut self, index: usize, map: impl Fn(usize) -> Option<usize>) {
+        for input in &mut self.inputs {
+            *input = input.reindex(index, &map);
+        }
+    }
+}
+
+/// Moves the point at `from` to `to` and makes every construction that depends on it again
+/// with its tool, in order, so later ones see the earlier ones' new shapes. Points on a curve
+/// that changed are projected back onto it and intersections follow their constructions, and
+/// loci are traced again. `to` should already be on the curve if the moved point is on one.
+/// Intersection lists are left as they were. Returns `None` if a construction can no longer
+/// be made, as when two circles that gave one of its points no longer meet.
+pub fn move_point(constructions: &[Construction], from: Pos, to: Pos) -> Option<Vec<Construction>> {
+    let mut moved: Vec<Construction> = Vec::with_capacity(constructions.len());
+    let mut shapes: Vec<Shape> = Vec::with_capacity(constructions.len());
+    let mut changed = Vec::with_capacity(constructions.len());
+
+    for construction in constructions {
+        let mut construction = construction.clone();
+
+        let mut rebuilt = false;
+
+        if let Some(definition) = &construction.definition {
+            let inputs = definition
+                .inputs
+                .iter()
+                .map(|input| input.resolve(&shapes, &changed, from, to))
+                .collect::<Option<Vec<Input>>>()?;
+
+            if definition.tool == locus::TOOL {
+                // A locus follows everything made before it, not only its own points
+                if let [Input::Free { pos: driver }, Input::OnCurve { on, .. }, traced] =
+                    &inputs[..]
+                {
+                    if changed.contains(&true) || inputs != definition.inputs {
+                        let locus = LocusData::trace(&moved, *driver, &shapes[*on], traced)?;
+
+                        construction.shape = Shape::Locus(locus);
+                        rebuilt = true;
+                    }
+                }
+            } else if inputs != definition.inputs {
+                let points: Vec<Pos> = inputs.iter().map(Input::pos).collect();
+                let tool = tools::by_name(&definition.tool)?;
+
+                let shape = tool.get_shape(&points);
+
+                // The points of a conic may no longer fix one, as when they become collinear
+                if let Shape::Conic(conic) = &shape {
+                    if conic.kind() == ConicKind::Degenerate {
+                        return None;
+                    }
+                }
+
+                construction.shape = shape;
+                rebuilt = true;
+            }
+
+            if rebuilt {
+                construction.definition = Some(Definition {
+                    tool: definition.tool.clone(),
+                    inputs,
+                });
+            }
+        }
+
+        changed.push(rebuilt);
+        shapes.push(construction.shape.clone());
+        moved.push(construction);
+    }
+
+    Some(moved)
+}
+
+/// The point closest to `pos`, for following an intersection as the shapes through it move.
+pub fn nearest(points: Vec<Pos>, pos: Pos) -> Option<Pos> {
+    points
+        .into_iter()
+        .filter(|point| point.is_finite())
+        .min_by(|a, b| a.distance_sq(pos).total_cmp(&b.distance_sq(pos)))
+}
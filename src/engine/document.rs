@@ -0,0 +1,348 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use super::{
+    measure::Measurement, pos::Pos, shapes::Construction, snap::Grid, transform::Transform,
+};
+
+/// Version written by [`Document`]. Older files are upgraded by [`MIGRATIONS`] when loaded.
+pub const VERSION: u64 = 2;
+
+/// Upgrades a document from version `i` to version `i + 1`.
+type Migration = fn(Value) -> Result<Value, DocumentError>;
+
+const MIGRATIONS: [Migration; VERSION as usize] = [v0::migrate, v1::migrate];
+
+/// File formats a document can be written in, chosen by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Json,
+}
+
+impl Format {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?;
+
+        match extension.to_lowercase().as_str() {
+            "yml" | "yaml" => Some(Format::Yaml),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Yaml => "yml",
+            Format::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DocumentError {
+    Yaml(serde_yaml::Error),
+    Json(serde_json::Error),
+    MissingVersion,
+    UnsupportedVersion(u64),
+    /// An entry of a version 0 list that is not a tagged shape, by its index.
+    InvalidConstruction(usize),
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DocumentError::Yaml(e) => write!(f, "invalid document: {}", e),
+            DocumentError::Json(e) => write!(f, "invalid document: {}", e),
+            DocumentError::MissingVersion => write!(f, "document has no version"),
+            DocumentError::UnsupportedVersion(v) => write!(
+                f,
+                "document version {} is newer than the supported version {}",
+                v, VERSION
+            ),
+            DocumentError::InvalidConstruction(i) => {
+                write!(
+                    f,
+                    "construction {} is not a circle, line, segment or arc",
+                    i
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+impl From<serde_yaml::Error> for DocumentError {
+    fn from(e: serde_yaml::Error) -> Self {
+        DocumentError::Yaml(e)
+    }
+}
+
+impl From<serde_json::Error> for DocumentError {
+    fn from(e: serde_json::Error) -> Self {
+        DocumentError::Json(e)
+    }
+}
+
+/// The top level of a save file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document {
+    /// Schema version, see [`VERSION`].
+    pub version: u64,
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub view: View,
+    /// Layers in the order they are listed, including empty ones.
+    #[serde(default)]
+    pub layers: Vec<Layer>,
+    /// Named points, such as those imported from GeoGebra.
+    #[serde(default)]
+    pub points: Vec<Point>,
+    /// Constructions in the order they were made. Intersections are recomputed on load.
+    #[serde(default)]
+    pub constructions: Vec<Construction>,
+    /// Measurements shown on the plot, which refer to constructions and their points by index.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub measurements: Vec<Measurement>,
+    /// Transforms that have been used, kept so they can be applied again.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<Transform>,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Document {
+            version: VERSION,
+            metadata: Metadata::default(),
+            view: View::default(),
+            layers: Vec::new(),
+            points: Vec::new(),
+            constructions: Vec::new(),
+            measurements: Vec::new(),
+            transforms: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    pub title: String,
+    pub author: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct View {
+    pub show_axes: bool,
+    pub show_intersections: bool,
+    /// Visible region of the plot, or `None` to fit the constructions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera: Option<Camera>,
+    /// Background grid, or `None` when it is hidden.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid: Option<Grid>,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        View {
+            show_axes: true,
+            show_intersections: true,
+            camera: None,
+            grid: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Camera {
+    pub min: Pos,
+    pub max: Pos,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Layer {
+    pub name: String,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+}
+
+impl Layer {
+    pub fn new(name: &str) -> Self {
+        Layer {
+            name: name.to_owned(),
+            visible: true,
+        }
+    }
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Point {
+    pub label: String,
+    pub pos: Pos,
+}
+
+impl Document {
+    /// Parses a document of any known version, upgrading it to [`VERSION`].
+    pub fn from_value(mut value: Value) -> Result<Self, DocumentError> {
+        let version = match &value {
+            // Version 0 files are a bare list of constructions
+            Value::Sequence(_) => 0,
+            Value::Mapping(map) => map
+                .get("version")
+                .and_then(Value::as_u64)
+                .ok_or(DocumentError::MissingVersion)?,
+            _ => return Err(DocumentError::MissingVersion),
+        };
+
+        if version > VERSION {
+            return Err(DocumentError::UnsupportedVersion(version));
+        }
+
+        for migration in &MIGRATIONS[version as usize..] {
+            value = migration(value)?;
+        }
+
+        Ok(serde_yaml::from_value(value)?)
+    }
+
+    pub fn parse(data: &str, format: Format) -> Result<Self, DocumentError> {
+        // Both formats are read into the same value tree so migrations only deal with one
+        let value = match format {
+            Format::Yaml => serde_yaml::from_str(data)?,
+            Format::Json => serde_json::from_str(data)?,
+        };
+
+        Document::from_value(value)
+    }
+
+    pub fn write(&self, format: Format) -> Result<String, DocumentError> {
+        Ok(match format {
+            Format::Yaml => serde_yaml::to_string(self)?,
+            Format::Json => serde_json::to_string_pretty(self)?,
+        })
+    }
+}
+
+/// The original format: a bare list of constructions with `!Circle` style tags, tuple
+/// coordinates and stored intersections.
+mod v0 {
+    use serde_yaml::{Mapping, Value};
+
+    use super::DocumentError;
+
+    const POINT_FIELDS: [&str; 3] = ["pos", "p1", "p2"];
+
+    /// The shapes that version 0 could save.
+    const SHAPE_TAGS: [&str; 4] = ["circle", "line", "segment", "arc"];
+
+    pub fn migrate(value: Value) -> Result<Value, DocumentError> {
+        let Value::Sequence(old) = value else {
+            return Err(DocumentError::MissingVersion);
+        };
+
+        let mut layers: Vec<Value> = Vec::new();
+        let mut constructions = Vec::new();
+
+        for (i, mut construction) in old.into_iter().enumerate() {
+            let Some(map) = construction.as_mapping_mut() else {
+                return Err(DocumentError::InvalidConstruction(i));
+            };
+
+            map.remove("intersections");
+
+            // Skipping these would load a file that is not a figure as an empty one
+            let Some(Value::Tagged(shape)) = map.remove("shape") else {
+                return Err(DocumentError::InvalidConstruction(i));
+            };
+
+            let kind = shape.tag.to_string().trim_start_matches('!').to_lowercase();
+
+            if !SHAPE_TAGS.contains(&kind.as_str()) {
+                return Err(DocumentError::InvalidConstruction(i));
+            }
+
+            let mut data = shape.value.as_mapping().cloned().unwrap_or_default();
+
+            for field in POINT_FIELDS {
+                if let Some(Value::Sequence(xy)) = data.get(field).cloned() {
+                    let mut pos = Mapping::new();
+                    pos.insert("x".into(), xy.first().cloned().unwrap_or_default());
+                    pos.insert("y".into(), xy.get(1).cloned().unwrap_or_default());
+                    data.insert(field.into(), Value::Mapping(pos));
+                }
+            }
+
+            data.insert("type".into(), kind.into());
+
+            map.insert("shape".into(), Value::Mapping(data));
+
+            if let Some(layer) = map.get("layer").cloned() {
+                if !layers.iter().any(|l| l.get("name") == Some(&layer)) {
+                    let mut entry = Mapping::new();
+                    entry.insert("name".into(), layer);
+                    entry.insert("visible".into(), true.into());
+                    layers.push(Value::Mapping(entry));
+                }
+            }
+
+            constructions.push(construction);
+        }
+
+        let mut document = Mapping::new();
+        document.insert("version".into(), 1.into());
+        document.insert("layers".into(), Value::Sequence(layers));
+        document.insert("constructions".into(), Value::Sequence(constructions));
+
+        Ok(Value::Mapping(document))
+    }
+}
+
+/// Colours were premultiplied RGBA tuples; they are now hex strings.
+mod v1 {
+    use serde_yaml::Value;
+
+    use super::DocumentError;
+    use crate::engine::color::Color;
+
+    pub fn migrate(mut value: Value) -> Result<Value, DocumentError> {
+        if let Some(Value::Sequence(constructions)) = value.get_mut("constructions") {
+            for construction in constructions {
+                let Some(Value::Sequence(rgba)) = construction.get("color") else {
+                    continue;
+                };
+
+                let channel = |i: usize| rgba.get(i).and_then(Value::as_u64).unwrap_or(255) as u8;
+
+                let alpha = channel(3);
+                let unmultiply = |c: u8| match alpha {
+                    0 => 0,
+                    a => (c as u32 * 255 / a as u32).min(255) as u8,
+                };
+
+                let color = Color::from_rgba(
+                    unmultiply(channel(0)),
+                    unmultiply(channel(1)),
+                    unmultiply(channel(2)),
+                    alpha,
+                );
+
+                construction["color"] = color.to_hex().into();
+            }
+        }
+
+        value["version"] = 2.into();
+
+        Ok(value)
+    }
+}
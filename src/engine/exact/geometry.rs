@@ -0,0 +1,334 @@
+use std::{cmp::Ordering, fmt};
+
+use super::{Exact, ExactError, Tower};
+use crate::engine::{
+    pos::Pos,
+    shapes::{ArcData, Shape},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExactPos {
+    pub x: Exact,
+    pub y: Exact,
+}
+
+impl ExactPos {
+    pub fn new(x: Exact, y: Exact) -> Self {
+        ExactPos { x, y }
+    }
+
+    pub fn from_pos(pos: Pos) -> Result<Self, ExactError> {
+        match (Exact::from_f32(pos.x), Exact::from_f32(pos.y)) {
+            (Some(x), Some(y)) => Ok(ExactPos { x, y }),
+            _ => Err(ExactError::NotFinite),
+        }
+    }
+
+    pub fn to_pos(&self) -> Pos {
+        Pos::new(self.x.to_f64() as f32, self.y.to_f64() as f32)
+    }
+
+    fn add(&self, other: &ExactPos) -> ExactPos {
+        ExactPos::new(&self.x + &other.x, &self.y + &other.y)
+    }
+
+    fn sub(&self, other: &ExactPos) -> ExactPos {
+        ExactPos::new(&self.x - &other.x, &self.y - &other.y)
+    }
+
+    fn scale(&self, k: &Exact) -> ExactPos {
+        ExactPos::new(&self.x * k, &self.y * k)
+    }
+
+    fn dot(&self, other: &ExactPos) -> Exact {
+        &(&self.x * &other.x) + &(&self.y * &other.y)
+    }
+
+    fn cross(&self, other: &ExactPos) -> Exact {
+        &(&self.x * &other.y) - &(&self.y * &other.x)
+    }
+
+    pub fn distance_sq(&self, other: &ExactPos) -> Exact {
+        let d = self.sub(other);
+        d.dot(&d)
+    }
+}
+
+impl fmt::Display for ExactPos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExactCircle {
+    pub pos: ExactPos,
+    pub r_sq: Exact,
+    pub r: Exact,
+}
+
+/// A construction with its defining points and radius known exactly. Arcs keep their
+/// angles as they are, since only their circle is used in exact calculations.
+#[derive(Debug, Clone)]
+pub enum ExactShape {
+    Circle(ExactCircle),
+    Line { p1: ExactPos, p2: ExactPos },
+    Segment { p1: ExactPos, p2: ExactPos },
+    Arc { circle: ExactCircle, arc: ArcData },
+}
+
+impl fmt::Display for ExactShape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExactShape::Circle(circle) => write!(f, "Circle: p={}, r={}", circle.pos, circle.r),
+            ExactShape::Line { p1, p2 } => write!(f, "Line: p1={}, p2={}", p1, p2),
+            ExactShape::Segment { p1, p2 } => write!(f, "Segment: p1={}, p2={}", p1, p2),
+            ExactShape::Arc { circle, arc } => write!(
+                f,
+                "Arc: p={}, r={}, start={:.2}, stop={:.2}",
+                circle.pos, circle.r, arc.start, arc.stop
+            ),
+        }
+    }
+}
+
+/// Exact values for the points and constructions of a document, found by matching the
+/// `f32` positions the engine works with against points already known exactly.
+#[derive(Default)]
+pub struct ExactContext {
+    tower: Tower,
+    points: Vec<(Pos, ExactPos)>,
+    /// Exact shapes of the engine's constructions, by index. `None` where a shape was too
+    /// complex to work with exactly.
+    shapes: Vec<Option<ExactShape>>,
+}
+
+impl ExactContext {
+    pub fn new() -> Self {
+        ExactContext::default()
+    }
+
+    /// Records `pos` as a point known exactly, such as a labelled point or intersection.
+    pub fn add_point(&mut self, pos: ExactPos) {
+        let approximate = pos.to_pos();
+
+        if !self.points.iter().any(|(_, known)| *known == pos) {
+            self.points.push((approximate, pos));
+        }
+    }
+
+    /// The exact point at `pos`, if one is known.
+    pub fn point(&self, pos: Pos) -> Option<&ExactPos> {
+        let tolerance = 1e-4 * (1.0 + pos.length());
+
+        self.points
+            .iter()
+            .map(|(approximate, exact)| (approximate.distance(pos), exact))
+            .filter(|(distance, _)| *distance <= tolerance)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, exact)| exact)
+    }
+
+    /// The exact point at `pos`, or its decimal value if it is not a known point.
+    fn point_or_decimal(&self, pos: Pos) -> Result<ExactPos, ExactError> {
+        match self.point(pos) {
+            Some(exact) => Ok(exact.clone()),
+            None => ExactPos::from_pos(pos),
+        }
+    }
+
+    /// The exact shape of the construction at `index`, if it has been added.
+    pub fn shape(&self, index: usize) -> Option<&ExactShape> {
+        self.shapes.get(index).and_then(Option::as_ref)
+    }
+
+    /// Works out the construction at `index` exactly, with its intersections with the
+    /// constructions before it. Anything after `index` is forgotten, as it was undone.
+    ///
+    /// Returns the intersections with each earlier construction, rounded from their exact
+    /// values so that a point reached two ways has one position. `None` where either shape
+    /// could not be worked with exactly.
+    pub fn add(&mut self, index: usize, shape: &Shape) -> Vec<Option<Vec<Pos>>> {
+        self.shapes.truncate(index);
+
+        let exact = self.exact_shape(shape).ok();
+        let mut intersections = Vec::new();
+
+        for other in self.shapes.clone() {
+            let points = match (&exact, &other) {
+                (Some(exact), Some(other)) => self.intersections(exact, other).ok(),
+                _ => None,
+            };
+
+            intersections.push(points.map(|points| {
+                points
+                    .into_iter()
+                    .map(|point| {
+                        let approximate = point.to_pos();
+                        self.add_point(point);
+                        approximate
+                    })
+                    .collect()
+            }));
+        }
+
+        self.shapes.push(exact);
+
+        intersections
+    }
+
+    fn exact_shape(&self, shape: &Shape) -> Result<ExactShape, ExactError> {
+        Ok(match shape {
+            Shape::Circle(circle) => ExactShape::Circle(self.circle(circle.pos, circle.r)?),
+            Shape::Line(line) => ExactShape::Line {
+                p1: self.point_or_decimal(line.p1)?,
+                p2: self.point_or_decimal(line.p2)?,
+            },
+            Shape::Segment(segment) => ExactShape::Segment {
+                p1: self.point_or_decimal(segment.p1)?,
+                p2: self.point_or_decimal(segment.p2)?,
+            },
+            Shape::Arc(arc) => ExactShape::Arc {
+                circle: self.circle(arc.pos, arc.r)?,
+                arc: arc.clone(),
+            },
+            Shape::Conic(_) | Shape::Locus(_) => return Err(ExactError::NotConstructible),
+        })
+    }
+
+    /// A circle about `pos`, with its radius taken from a known point on it if there is
+    /// one, as the compass would have been opened to that point.
+    fn circle(&self, pos: Pos, r: f32) -> Result<ExactCircle, ExactError> {
+        let centre = self.point_or_decimal(pos)?;
+        let tolerance = 1e-4 * (1.0 + r);
+
+        let on_circle = self
+            .points
+            .iter()
+            .find(|(approximate, _)| (approximate.distance(pos) - r).abs() <= tolerance);
+
+        let r_sq = match on_circle {
+            Some((_, point)) => centre.distance_sq(point),
+            None => {
+                let r = Exact::from_f32(r).ok_or(ExactError::NotFinite)?;
+                &r * &r
+            }
+        };
+
+        let r = self.sqrt(&r_sq)?.ok_or(ExactError::NotFinite)?;
+
+        Ok(ExactCircle {
+            pos: centre,
+            r_sq,
+            r,
+        })
+    }
+
+    fn sqrt(&self, x: &Exact) -> Result<Option<Exact>, ExactError> {
+        self.tower.sqrt(x)
+    }
+
+    pub fn intersections(
+        &self,
+        a: &ExactShape,
+        b: &ExactShape,
+    ) -> Result<Vec<ExactPos>, ExactError> {
+        use ExactShape::*;
+
+        let possible = match (a, b) {
+            (
+                Line { p1, p2 } | Segment { p1, p2 },
+                Line { p1: q1, p2: q2 } | Segment { p1: q1, p2: q2 },
+            ) => line_line(p1, p2, q1, q2),
+            (Circle(c) | Arc { circle: c, .. }, Line { p1, p2 } | Segment { p1, p2 })
+            | (Line { p1, p2 } | Segment { p1, p2 }, Circle(c) | Arc { circle: c, .. }) => {
+                self.circle_line(c, p1, p2)?
+            }
+            (Circle(c) | Arc { circle: c, .. }, Circle(d) | Arc { circle: d, .. }) => {
+                self.circle_circle(c, d)?
+            }
+        };
+
+        Ok(possible
+            .into_iter()
+            .filter(|point| on_shape(a, point) && on_shape(b, point))
+            .collect())
+    }
+
+    /// Points `p1 + t(p2 - p1)` on the circle, solving the quadratic in `t`.
+    fn circle_line(
+        &self,
+        circle: &ExactCircle,
+        p1: &ExactPos,
+        p2: &ExactPos,
+    ) -> Result<Vec<ExactPos>, ExactError> {
+        let d = p2.sub(p1);
+        let f = p1.sub(&circle.pos);
+
+        let a = d.dot(&d);
+        let b = d.dot(&f);
+        let c = &f.dot(&f) - &circle.r_sq;
+
+        if a.is_zero() {
+            return Ok(Vec::new());
+        }
+
+        let discriminant = &(&b * &b) - &(&a * &c);
+
+        let Some(root) = self.sqrt(&discriminant)? else {
+            return Ok(Vec::new());
+        };
+
+        let at = |t: Exact| p1.add(&d.scale(&t));
+
+        if root.is_zero() {
+            return Ok(vec![at(&(-&b) / &a)]);
+        }
+
+        Ok(vec![at(&(&(-&b) - &root) / &a), at(&(&(-&b) + &root) / &a)])
+    }
+
+    /// Intersects the first circle with the line through both intersections, found by
+    /// subtracting one circle's equation from the other's.
+    fn circle_circle(&self, a: &ExactCircle, b: &ExactCircle) -> Result<Vec<ExactPos>, ExactError> {
+        let two = Exact::from_integer(2);
+        let normal = b.pos.sub(&a.pos).scale(&two);
+
+        if normal.x.is_zero() && normal.y.is_zero() {
+            return Ok(Vec::new());
+        }
+
+        // normal · p = k on the radical line
+        let k = &(&(&b.pos.dot(&b.pos) - &a.pos.dot(&a.pos)) - &b.r_sq) + &a.r_sq;
+
+        let p1 = normal.scale(&(&k / &normal.dot(&normal)));
+        let p2 = p1.add(&ExactPos::new(-&normal.y, normal.x.clone()));
+
+        self.circle_line(a, &p1, &p2)
+    }
+}
+
+fn line_line(p1: &ExactPos, p2: &ExactPos, q1: &ExactPos, q2: &ExactPos) -> Vec<ExactPos> {
+    let (d1, d2) = (p2.sub(p1), q2.sub(q1));
+    let denominator = d1.cross(&d2);
+
+    if denominator.is_zero() {
+        return Vec::new();
+    }
+
+    let t = &q1.sub(p1).cross(&d2) / &denominator;
+
+    vec![p1.add(&d1.scale(&t))]
+}
+
+/// Whether a point already known to be on the shape's line or circle is on the shape itself.
+fn on_shape(shape: &ExactShape, point: &ExactPos) -> bool {
+    match shape {
+        ExactShape::Circle(_) | ExactShape::Line { .. } => true,
+        ExactShape::Segment { p1, p2 } => {
+            point.sub(p1).dot(&p2.sub(p1)).signum() != Ordering::Less
+                && point.sub(p2).dot(&p1.sub(p2)).signum() != Ordering::Less
+        }
+        ExactShape::Arc { arc, .. } => !arc.valid_points(vec![point.to_pos()]).is_empty(),
+    }
+}
@@ -0,0 +1,547 @@
+//! Exact arithmetic for compass and straight edge constructions.
+//!
+//! Constructible numbers lie in a tower of quadratic extensions of the rationals,
+//! Q ⊂ Q(√r₀) ⊂ Q(√r₀, √r₁) ⊂ …, where each radicand rᵢ is in the field below it but √rᵢ
+//! is not. A [`Tower`] records radicands as square roots need them, and every [`Exact`]
+//! number is written as `a + b√rᵢ` with `a` and `b` using only lower levels. As no radical
+//! can be written in terms of the ones before it this form is unique, so numbers are equal
+//! exactly when their forms are. Numbers from different towers must not be mixed.
+
+pub mod geometry;
+
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Add, Div, Mul, Neg, Sub},
+    rc::Rc,
+};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+/// Radicals a tower may hold before square roots give up.
+const MAX_LEVELS: usize = 24;
+
+/// Steps a single square root may take looking for a root in the tower.
+const MAX_WORK: usize = 50_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExactError {
+    /// The tower has grown too tall to search for square roots in reasonable time.
+    TooComplex,
+    /// A coordinate was infinite or NaN.
+    NotFinite,
+    /// The shape cannot be drawn with compass and straight edge, so its points are not
+    /// constructible numbers.
+    NotConstructible,
+}
+
+impl fmt::Display for ExactError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExactError::TooComplex => write!(f, "too many radicals for exact arithmetic"),
+            ExactError::NotFinite => write!(f, "coordinate is not finite"),
+            ExactError::NotConstructible => write!(f, "shape is not constructible"),
+        }
+    }
+}
+
+impl std::error::Error for ExactError {}
+
+#[derive(Clone)]
+pub enum Exact {
+    Rational(BigRational),
+    Surd(Rc<Surd>),
+}
+
+/// `a + b√r`, where `r` is the radicand of level `level` of the tower and `a` and `b` only
+/// use lower levels. `b` is never zero.
+pub struct Surd {
+    level: usize,
+    radicand: Exact,
+    a: Exact,
+    b: Exact,
+}
+
+impl Exact {
+    pub fn zero() -> Self {
+        Exact::Rational(BigRational::zero())
+    }
+
+    pub fn one() -> Self {
+        Exact::Rational(BigRational::one())
+    }
+
+    pub fn from_integer(n: i64) -> Self {
+        Exact::Rational(BigRational::from_integer(n.into()))
+    }
+
+    /// The decimal shown for `value`, so that `0.1` becomes 1/10 rather than its binary fraction.
+    pub fn from_f32(value: f32) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        let text = value.to_string();
+        let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+
+        let digits: BigInt = format!("{}{}", whole, fraction).parse().ok()?;
+        let scale = num_traits::pow(BigInt::from(10), fraction.len());
+
+        Some(Exact::Rational(BigRational::new(digits, scale)))
+    }
+
+    fn surd(level: usize, radicand: &Exact, a: Exact, b: Exact) -> Self {
+        if b.is_zero() {
+            a
+        } else {
+            Exact::Surd(Rc::new(Surd {
+                level,
+                radicand: radicand.clone(),
+                a,
+                b,
+            }))
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Exact::Rational(x) if x.is_zero())
+    }
+
+    pub fn is_rational(&self) -> bool {
+        matches!(self, Exact::Rational(_))
+    }
+
+    /// The highest level of the tower this number uses, or `None` if it is rational.
+    fn level(&self) -> Option<usize> {
+        match self {
+            Exact::Rational(_) => None,
+            Exact::Surd(surd) => Some(surd.level),
+        }
+    }
+
+    /// Writes the number as `a + b√r` for the radicand at `level`, which must be at least
+    /// as high as the number's own level.
+    fn split(&self, level: usize) -> (Exact, Exact) {
+        match self {
+            Exact::Surd(surd) if surd.level == level => (surd.a.clone(), surd.b.clone()),
+            _ => (self.clone(), Exact::zero()),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.approximate().0
+    }
+
+    /// An approximate value, and the sum of the sizes of its terms which bounds its error.
+    fn approximate(&self) -> (f64, f64) {
+        match self {
+            Exact::Rational(x) => {
+                let value = x.to_f64().unwrap_or(f64::NAN);
+                (value, value.abs())
+            }
+            Exact::Surd(surd) => {
+                let root = surd.radicand.approximate().0.sqrt();
+                let (a, a_size) = surd.a.approximate();
+                let (b, b_size) = surd.b.approximate();
+
+                (a + b * root, a_size + b_size * root)
+            }
+        }
+    }
+
+    /// The sign of the number, compared with zero.
+    pub fn signum(&self) -> Ordering {
+        let surd = match self {
+            Exact::Rational(x) => return x.cmp(&BigRational::zero()),
+            Exact::Surd(surd) => surd,
+        };
+
+        // The approximation is only trusted when it is well clear of its own rounding error
+        let (value, size) = self.approximate();
+
+        if value.abs() > size * 1e-9 {
+            return value.partial_cmp(&0.0).unwrap_or(Ordering::Equal);
+        }
+
+        let (a, b) = (surd.a.signum(), surd.b.signum());
+
+        if a == Ordering::Equal || a == b {
+            return if a == Ordering::Equal { b } else { a };
+        }
+
+        // a and b√r have opposite signs, so the larger in size wins. They can't cancel as
+        // √r is not in the field of a and b
+        let difference = &(&surd.a * &surd.a) - &(&(&surd.b * &surd.b) * &surd.radicand);
+
+        match difference.signum() {
+            Ordering::Greater => a,
+            _ => b,
+        }
+    }
+
+    pub fn cmp_exact(&self, other: &Exact) -> Ordering {
+        (self - other).signum()
+    }
+
+    pub fn abs(&self) -> Exact {
+        match self.signum() {
+            Ordering::Less => -self,
+            _ => self.clone(),
+        }
+    }
+
+    pub fn recip(&self) -> Exact {
+        match self {
+            Exact::Rational(x) => Exact::Rational(x.recip()),
+            Exact::Surd(surd) => {
+                let norm = &(&surd.a * &surd.a) - &(&(&surd.b * &surd.b) * &surd.radicand);
+
+                Exact::surd(
+                    surd.level,
+                    &surd.radicand,
+                    &surd.a / &norm,
+                    -&(&surd.b / &norm),
+                )
+            }
+        }
+    }
+
+    /// The square root, if the number is a square of a rational.
+    fn rational_sqrt(&self) -> Option<Exact> {
+        let Exact::Rational(x) = self else {
+            return None;
+        };
+
+        if x.is_negative() {
+            return None;
+        }
+
+        let (numer, denom) = (x.numer().sqrt(), x.denom().sqrt());
+
+        (&numer * &numer == *x.numer() && &denom * &denom == *x.denom())
+            .then(|| Exact::Rational(BigRational::new(numer, denom)))
+    }
+}
+
+impl PartialEq for Exact {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Exact::Rational(x), Exact::Rational(y)) => x == y,
+            (Exact::Surd(x), Exact::Surd(y)) => {
+                Rc::ptr_eq(x, y) || (x.level == y.level && x.a == y.a && x.b == y.b)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Exact {}
+
+impl Hash for Exact {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Exact::Rational(x) => {
+                0u8.hash(state);
+                x.hash(state);
+            }
+            Exact::Surd(surd) => {
+                1u8.hash(state);
+                surd.level.hash(state);
+                surd.a.hash(state);
+                surd.b.hash(state);
+            }
+        }
+    }
+}
+
+impl Add for &Exact {
+    type Output = Exact;
+
+    fn add(self, other: &Exact) -> Exact {
+        if let (Exact::Rational(x), Exact::Rational(y)) = (self, other) {
+            return Exact::Rational(x + y);
+        }
+
+        let (level, radicand) = top(self, other);
+        let ((a, b), (c, d)) = (self.split(level), other.split(level));
+
+        Exact::surd(level, radicand, &a + &c, &b + &d)
+    }
+}
+
+impl Sub for &Exact {
+    type Output = Exact;
+
+    fn sub(self, other: &Exact) -> Exact {
+        self + &-other
+    }
+}
+
+impl Mul for &Exact {
+    type Output = Exact;
+
+    fn mul(self, other: &Exact) -> Exact {
+        if let (Exact::Rational(x), Exact::Rational(y)) = (self, other) {
+            return Exact::Rational(x * y);
+        }
+
+        if self.is_zero() || other.is_zero() {
+            return Exact::zero();
+        }
+
+        let (level, radicand) = top(self, other);
+        let ((a, b), (c, d)) = (self.split(level), other.split(level));
+
+        // (a + b√r)(c + d√r) = ac + bdr + (ad + bc)√r, skipping the zero terms
+        let rational_part = if b.is_zero() || d.is_zero() {
+            &a * &c
+        } else {
+            &(&a * &c) + &(&(&b * &d) * radicand)
+        };
+
+        Exact::surd(level, radicand, rational_part, &(&a * &d) + &(&b * &c))
+    }
+}
+
+impl Div for &Exact {
+    type Output = Exact;
+
+    fn div(self, other: &Exact) -> Exact {
+        match other {
+            Exact::Rational(y) => self * &Exact::Rational(y.recip()),
+            Exact::Surd(_) => self * &other.recip(),
+        }
+    }
+}
+
+impl Neg for &Exact {
+    type Output = Exact;
+
+    fn neg(self) -> Exact {
+        match self {
+            Exact::Rational(x) => Exact::Rational(-x),
+            Exact::Surd(surd) => Exact::surd(surd.level, &surd.radicand, -&surd.a, -&surd.b),
+        }
+    }
+}
+
+impl Neg for Exact {
+    type Output = Exact;
+
+    fn neg(self) -> Exact {
+        -&self
+    }
+}
+
+/// The highest level used by either number, and its radicand.
+fn top<'a>(x: &'a Exact, y: &'a Exact) -> (usize, &'a Exact) {
+    let source = if x.level() >= y.level() { x } else { y };
+
+    match source {
+        Exact::Surd(surd) => (surd.level, &surd.radicand),
+        Exact::Rational(_) => unreachable!("at least one number is a surd"),
+    }
+}
+
+impl fmt::Display for Exact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let surd = match self {
+            Exact::Rational(x) if x.is_integer() => return write!(f, "{}", x.numer()),
+            Exact::Rational(x) => return write!(f, "{}/{}", x.numer(), x.denom()),
+            Exact::Surd(surd) => surd,
+        };
+
+        let radical = match &surd.radicand {
+            Exact::Rational(r) => format!("√{}", r),
+            radicand => format!("√({})", radicand),
+        };
+
+        let negative = surd.b.is_rational() && surd.b.signum() == Ordering::Less;
+
+        let term = match &surd.b {
+            Exact::Rational(b) => {
+                let numer = b.numer().abs();
+
+                let coefficient = if numer.is_one() {
+                    radical
+                } else {
+                    format!("{}{}", numer, radical)
+                };
+
+                if b.is_integer() {
+                    coefficient
+                } else {
+                    format!("{}/{}", coefficient, b.denom())
+                }
+            }
+            b => format!("({}){}", b, radical),
+        };
+
+        match (surd.a.is_zero(), negative) {
+            (true, false) => write!(f, "{}", term),
+            (true, true) => write!(f, "-{}", term),
+            (false, false) => write!(f, "{} + {}", surd.a, term),
+            (false, true) => write!(f, "{} - {}", surd.a, term),
+        }
+    }
+}
+
+impl fmt::Debug for Exact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// The radicands of a tower of quadratic extensions, added to as square roots need them.
+#[derive(Default)]
+pub struct Tower {
+    radicands: RefCell<Vec<Exact>>,
+    /// Known answers to "is x a square in the first k levels".
+    squares: RefCell<HashMap<(Exact, usize), Option<Exact>>>,
+    work: Cell<usize>,
+}
+
+impl Tower {
+    pub fn new() -> Self {
+        Tower::default()
+    }
+
+    pub fn levels(&self) -> usize {
+        self.radicands.borrow().len()
+    }
+
+    /// The non-negative square root, or `None` if `x` is negative. If the root is not already
+    /// in the tower a level is added for it.
+    pub fn sqrt(&self, x: &Exact) -> Result<Option<Exact>, ExactError> {
+        match x.signum() {
+            Ordering::Less => return Ok(None),
+            Ordering::Equal => return Ok(Some(Exact::zero())),
+            Ordering::Greater => (),
+        }
+
+        self.work.set(0);
+
+        let levels = self.levels();
+
+        if let Some(root) = self.sqrt_in(x, levels)? {
+            return Ok(Some(root.abs()));
+        }
+
+        if levels >= MAX_LEVELS {
+            return Err(ExactError::TooComplex);
+        }
+
+        let (coefficient, radicand) = match x {
+            Exact::Rational(x) => square_free(x),
+            _ => (Exact::one(), x.clone()),
+        };
+
+        self.radicands.borrow_mut().push(radicand.clone());
+
+        Ok(Some(Exact::surd(
+            levels,
+            &radicand,
+            Exact::zero(),
+            coefficient,
+        )))
+    }
+
+    /// A square root of `x` using the first `k` levels, where `x` itself is in them.
+    fn sqrt_in(&self, x: &Exact, k: usize) -> Result<Option<Exact>, ExactError> {
+        self.work.set(self.work.get() + 1);
+
+        if self.work.get() > MAX_WORK {
+            return Err(ExactError::TooComplex);
+        }
+
+        // Every level is real, so negative numbers have no roots in it
+        match x.signum() {
+            Ordering::Less => return Ok(None),
+            Ordering::Equal => return Ok(Some(Exact::zero())),
+            Ordering::Greater => (),
+        }
+
+        if k == 0 {
+            return Ok(x.rational_sqrt());
+        }
+
+        let key = (x.clone(), k);
+
+        if let Some(known) = self.squares.borrow().get(&key) {
+            return Ok(known.clone());
+        }
+
+        let level = k - 1;
+        let radicand = self.radicands.borrow()[level].clone();
+        let root = Exact::surd(level, &radicand, Exact::zero(), Exact::one());
+
+        let (a, b) = x.split(level);
+
+        // Look for (c + d√r)² = c² + d²r + 2cd√r = a + b√r
+        let result = if b.is_zero() {
+            match self.sqrt_in(&a, level)? {
+                Some(c) => Some(c),
+                None => self.sqrt_in(&(&a / &radicand), level)?.map(|d| &d * &root),
+            }
+        } else {
+            let norm = &(&a * &a) - &(&(&b * &b) * &radicand);
+
+            match self.sqrt_in(&norm, level)? {
+                None => None,
+                Some(n) => {
+                    let half = Exact::Rational(BigRational::new(1.into(), 2.into()));
+                    let mut found = None;
+
+                    for c_sq in [&(&a + &n) * &half, &(&a - &n) * &half] {
+                        if c_sq.is_zero() {
+                            continue;
+                        }
+
+                        if let Some(c) = self.sqrt_in(&c_sq, level)? {
+                            let d = &b / &(&c + &c);
+                            found = Some(&c + &(&d * &root));
+                            break;
+                        }
+                    }
+
+                    found
+                }
+            }
+        };
+
+        self.squares.borrow_mut().insert(key, result.clone());
+
+        Ok(result)
+    }
+}
+
+/// Writes a positive rational `x` as `s²m`, returning `s` and `m`, with `m` an integer free of
+/// small square factors.
+fn square_free(x: &BigRational) -> (Exact, Exact) {
+    // √(p/q) = √(pq)/q
+    let mut m = x.numer() * x.denom();
+    let mut s = BigInt::one();
+
+    for p in 2u32..1000 {
+        let square = BigInt::from(p * p);
+
+        if square > m {
+            break;
+        }
+
+        while (&m % &square).is_zero() {
+            m /= &square;
+            s *= p;
+        }
+    }
+
+    (
+        Exact::Rational(BigRational::new(s, x.denom().clone())),
+        Exact::Rational(BigRational::from_integer(m)),
+    )
+}
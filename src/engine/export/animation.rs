@@ -0,0 +1,142 @@
+use tiny_skia::Pixmap;
+
+use super::{png, Frame, Style};
+use crate::engine::{playback::Playback, Engine};
+
+/// How the constructions are played back when drawn to a sequence of images.
+#[derive(Debug, Clone, Copy)]
+pub struct Animation {
+    pub fps: f32,
+    /// Constructions drawn per second.
+    pub speed: f32,
+    /// Seconds the finished figure is shown for at the end.
+    pub hold: f32,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation {
+            fps: 25.0,
+            speed: 1.0,
+            hold: 2.0,
+        }
+    }
+}
+
+impl Animation {
+    /// Draws each frame of the constructions being made in order, ending with the finished
+    /// figure. The hold at the end is not included.
+    pub fn frames(
+        &self,
+        engine: &Engine,
+        frame: &Frame,
+        style: &Style,
+    ) -> Result<Vec<Pixmap>, String> {
+        if !(self.fps > 0.0 && self.speed > 0.0) {
+            return Err("frame rate and speed must be positive".to_owned());
+        }
+
+        let len = engine.constructions.len();
+        let mut playback = Playback {
+            playing: true,
+            speed: self.speed,
+            ..Default::default()
+        };
+        let mut frames = Vec::new();
+
+        loop {
+            let constructions = playback.constructions(engine, frame.min, frame.max);
+
+            frames.push(
+                png::draw(&constructions, frame, style).ok_or("image size must not be zero")?,
+            );
+
+            if playback.finished(len) {
+                return Ok(frames);
+            }
+
+            playback.advance(1.0 / self.fps, len);
+        }
+    }
+
+    /// Number of times the last frame is repeated to show the finished figure for `hold`.
+    fn hold_frames(&self) -> usize {
+        (self.hold * self.fps).round().max(0.0) as usize
+    }
+
+    /// An animated GIF that loops forever.
+    pub fn render_gif(
+        &self,
+        engine: &Engine,
+        frame: &Frame,
+        style: &Style,
+    ) -> Result<Vec<u8>, String> {
+        let (width, height) = (
+            u16::try_from(frame.width).map_err(|_| "gif width must be below 65536")?,
+            u16::try_from(frame.height).map_err(|_| "gif height must be below 65536")?,
+        );
+
+        // GIF delays are in hundredths of a second
+        let delay = (100.0 / self.fps).round().max(1.0) as u16;
+
+        let mut data = Vec::new();
+        let mut encoder =
+            gif::Encoder::new(&mut data, width, height, &[]).map_err(|e| e.to_string())?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|e| e.to_string())?;
+
+        let frames = self.frames(engine, frame, style)?;
+        let last = frames.len() - 1;
+
+        for (i, pixmap) in frames.into_iter().enumerate() {
+            let mut rgba = unpremultiplied(&pixmap);
+            let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut rgba, 10);
+
+            gif_frame.delay = if i == last {
+                (delay as usize * (1 + self.hold_frames())).min(u16::MAX as usize) as u16
+            } else {
+                delay
+            };
+
+            encoder.write_frame(&gif_frame).map_err(|e| e.to_string())?;
+        }
+
+        encoder.into_inner().map_err(|e| e.to_string())?;
+
+        Ok(data)
+    }
+
+    /// Each frame as a PNG, with the last repeated for the hold so the sequence can be turned
+    /// into a video at the same frame rate.
+    pub fn render_pngs(
+        &self,
+        engine: &Engine,
+        frame: &Frame,
+        style: &Style,
+    ) -> Result<Vec<Vec<u8>>, String> {
+        let mut images = self
+            .frames(engine, frame, style)?
+            .iter()
+            .map(|pixmap| pixmap.encode_png().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(last) = images.last().cloned() {
+            images.extend(std::iter::repeat_n(last, self.hold_frames()));
+        }
+
+        Ok(images)
+    }
+}
+
+/// The pixels as straight RGBA, which is what GIF frames are made from.
+fn unpremultiplied(pixmap: &Pixmap) -> Vec<u8> {
+    pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let pixel = pixel.demultiply();
+            [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+        })
+        .collect()
+}
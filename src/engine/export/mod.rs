@@ -0,0 +1,146 @@
+pub mod animation;
+pub mod png;
+pub mod svg;
+pub mod tikz;
+
+use std::f32::consts::TAU;
+
+use super::{color::Color, pos::Pos, shapes::Shape, Engine};
+
+/// Colours used when drawing outside of the app.
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    pub background: Color,
+    pub intersections: Option<Color>,
+}
+
+impl Style {
+    pub fn from_engine(engine: &Engine) -> Self {
+        Style {
+            background: engine.config.background_color,
+            intersections: engine
+                .view
+                .show_intersections
+                .then_some(engine.config.intersection_color),
+        }
+    }
+}
+
+/// A region of the plot and the size of the image it is drawn to.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pub min: Pos,
+    pub max: Pos,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Frame {
+    /// Fits the document's camera, or its constructions, into an image of the given size.
+    pub fn fit(engine: &Engine, width: u32, height: u32) -> Self {
+        let (min, max) = match &engine.view.camera {
+            Some(camera) => (camera.min, camera.max),
+            None => engine
+                .bounds()
+                .map(|(min, max)| {
+                    let margin = ((max - min) * 0.1).max(Pos::new(0.1, 0.1));
+                    (min - margin, max + margin)
+                })
+                .unwrap_or((Pos::new(-1.0, -1.0), Pos::new(1.0, 1.0))),
+        };
+
+        // Grow the shorter side so that the plot keeps its aspect ratio
+        let center = min + (max - min) / 2.0;
+        let scale = f32::max(
+            (max.x - min.x) / width as f32,
+            (max.y - min.y) / height as f32,
+        );
+        let half = Pos::new(width as f32, height as f32) * scale / 2.0;
+
+        Frame {
+            min: center - half,
+            max: center + half,
+            width,
+            height,
+        }
+    }
+
+    /// Pixels per plot unit.
+    pub fn scale(&self) -> f32 {
+        self.width as f32 / (self.max.x - self.min.x)
+    }
+
+    /// Converts plot coordinates to image coordinates, where y points down.
+    pub fn screen_pos(&self, point: Pos) -> Pos {
+        Pos::new(
+            (point.x - self.min.x) * self.scale(),
+            (self.max.y - point.y) * self.scale(),
+        )
+    }
+
+    /// Clips the infinite line through two points to the frame.
+    pub fn clip_line(&self, p1: Pos, p2: Pos) -> Option<(Pos, Pos)> {
+        let direction = p2 - p1;
+
+        let mut t_min = f32::NEG_INFINITY;
+        let mut t_max = f32::INFINITY;
+
+        for (p, d, min, max) in [
+            (p1.x, direction.x, self.min.x, self.max.x),
+            (p1.y, direction.y, self.min.y, self.max.y),
+        ] {
+            if d == 0.0 {
+                if p < min || p > max {
+                    return None;
+                }
+            } else {
+                let (a, b) = ((min - p) / d, (max - p) / d);
+                t_min = t_min.max(a.min(b));
+                t_max = t_max.min(a.max(b));
+            }
+        }
+
+        (t_min <= t_max).then(|| (p1 + direction * t_min, p1 + direction * t_max))
+    }
+}
+
+/// The counter-clockwise angle swept by an arc from `start` to `stop`.
+pub fn arc_span(start: f32, stop: f32) -> f32 {
+    (stop - start).rem_euclid(TAU)
+}
+
+/// Dash and gap lengths in pixels for conics, which are drawn dashed as they are not
+/// compass and straight edge constructions.
+pub const CONIC_DASH: [f32; 2] = [6.0, 4.0];
+
+/// Points along each piece of a shape in plot coordinates, with lines clipped to the frame.
+/// Only hyperbolas, degenerate conics and broken loci have more than one piece.
+pub fn polylines(shape: &Shape, frame: &Frame) -> Vec<Vec<Pos>> {
+    let n = 256;
+
+    let along_circle = |pos: Pos, r: f32, start: f32, span: f32| {
+        (0..=n)
+            .map(|i| {
+                let t = start + span * i as f32 / n as f32;
+                pos + r * Pos::from_angle(t)
+            })
+            .collect()
+    };
+
+    match shape {
+        Shape::Circle(circle) => vec![along_circle(circle.pos, circle.r, 0.0, TAU)],
+        Shape::Line(line) => frame
+            .clip_line(line.p1, line.p2)
+            .map(|(a, b)| vec![vec![a, b]])
+            .unwrap_or_default(),
+        Shape::Segment(segment) => vec![vec![segment.p1, segment.p2]],
+        Shape::Arc(arc) => vec![along_circle(
+            arc.pos,
+            arc.r,
+            arc.start,
+            arc_span(arc.start, arc.stop),
+        )],
+        Shape::Conic(conic) => conic.branches(frame.min, frame.max),
+        Shape::Locus(locus) => locus.pieces.clone(),
+    }
+}
@@ -0,0 +1,92 @@
+use tiny_skia::{Paint, PathBuilder, Pixmap, Stroke, StrokeDash, Transform};
+
+use super::{polylines, Frame, Style, CONIC_DASH};
+use crate::engine::{
+    color::Color,
+    shapes::{Construction, Shape},
+    Engine,
+};
+
+/// Draws the visible constructions into a new image.
+pub fn rasterize(engine: &Engine, frame: &Frame, style: &Style) -> Option<Pixmap> {
+    let constructions: Vec<Construction> = engine.visible_constructions().cloned().collect();
+
+    draw(&constructions, frame, style)
+}
+
+/// Draws constructions into a new image, whether or not they are part of a document.
+pub fn draw(constructions: &[Construction], frame: &Frame, style: &Style) -> Option<Pixmap> {
+    let mut pixmap = Pixmap::new(frame.width, frame.height)?;
+    pixmap.fill(color(style.background));
+
+    for construction in constructions {
+        let mut path = PathBuilder::new();
+
+        for points in polylines(&construction.shape, frame) {
+            for (i, point) in points.iter().enumerate() {
+                let point = frame.screen_pos(*point);
+
+                if i == 0 {
+                    path.move_to(point.x, point.y);
+                } else {
+                    path.line_to(point.x, point.y);
+                }
+            }
+        }
+
+        let Some(path) = path.finish() else {
+            continue;
+        };
+
+        let mut paint = Paint::default();
+        paint.set_color(color(construction.color));
+        paint.anti_alias = true;
+
+        let stroke = Stroke {
+            width: construction.width,
+            dash: match construction.shape {
+                Shape::Conic(_) => StrokeDash::new(CONIC_DASH.to_vec(), 0.0),
+                _ => None,
+            },
+            ..Default::default()
+        };
+
+        pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+    }
+
+    if let Some(intersection_color) = style.intersections {
+        let mut paint = Paint::default();
+        paint.set_color(color(intersection_color));
+        paint.anti_alias = true;
+
+        for construction in constructions {
+            for point in &construction.intersections {
+                let point = frame.screen_pos(*point);
+
+                if let Some(dot) = PathBuilder::from_circle(point.x, point.y, 2.0) {
+                    pixmap.fill_path(
+                        &dot,
+                        &paint,
+                        tiny_skia::FillRule::Winding,
+                        Transform::identity(),
+                        None,
+                    );
+                }
+            }
+        }
+    }
+
+    Some(pixmap)
+}
+
+pub fn render(engine: &Engine, frame: &Frame, style: &Style) -> Result<Vec<u8>, String> {
+    rasterize(engine, frame, style)
+        .ok_or("image size must not be zero".to_owned())?
+        .encode_png()
+        .map_err(|e| e.to_string())
+}
+
+fn color(color: Color) -> tiny_skia::Color {
+    let [r, g, b, a] = color.to_array();
+    tiny_skia::Color::from_rgba8(r, g, b, a)
+}
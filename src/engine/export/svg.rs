@@ -0,0 +1,145 @@
+use std::fmt::Write;
+
+use super::{arc_span, polylines, Frame, Style, CONIC_DASH};
+use crate::engine::{color::Color, shapes::Shape, Engine};
+
+pub fn render(engine: &Engine, frame: &Frame, style: &Style) -> String {
+    let mut svg = String::new();
+
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = frame.width,
+        h = frame.height
+    )
+    .unwrap();
+
+    writeln!(
+        svg,
+        "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+        color(style.background)
+    )
+    .unwrap();
+
+    for construction in engine.visible_constructions() {
+        let stroke = format!(
+            "fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"{}",
+            color(construction.color),
+            construction.width,
+            opacity(construction.color)
+        );
+
+        match &construction.shape {
+            Shape::Circle(circle) => {
+                let pos = frame.screen_pos(circle.pos);
+
+                writeln!(
+                    svg,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
+                    pos.x,
+                    pos.y,
+                    circle.r * frame.scale(),
+                    stroke
+                )
+                .unwrap();
+            }
+            Shape::Line(line) => {
+                if let Some((p1, p2)) = frame.clip_line(line.p1, line.p2) {
+                    let (p1, p2) = (frame.screen_pos(p1), frame.screen_pos(p2));
+
+                    writeln!(
+                        svg,
+                        "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}/>",
+                        p1.x, p1.y, p2.x, p2.y, stroke
+                    )
+                    .unwrap();
+                }
+            }
+            Shape::Segment(segment) => {
+                let p1 = frame.screen_pos(segment.p1);
+                let p2 = frame.screen_pos(segment.p2);
+
+                writeln!(
+                    svg,
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}/>",
+                    p1.x, p1.y, p2.x, p2.y, stroke
+                )
+                .unwrap();
+            }
+            Shape::Arc(arc) => {
+                let (start, stop) = arc.endpoints();
+                let (start, stop) = (frame.screen_pos(start), frame.screen_pos(stop));
+                let r = arc.r * frame.scale();
+
+                // Counter-clockwise in the plot is clockwise on screen, so the sweep flag is 0
+                let large = arc_span(arc.start, arc.stop) > std::f32::consts::PI;
+
+                writeln!(
+                    svg,
+                    "<path d=\"M {} {} A {} {} 0 {} 0 {} {}\" {}/>",
+                    start.x, start.y, r, r, large as u8, stop.x, stop.y, stroke
+                )
+                .unwrap();
+            }
+            Shape::Conic(_) | Shape::Locus(_) => {
+                let dash = match construction.shape {
+                    Shape::Conic(_) => {
+                        format!(" stroke-dasharray=\"{} {}\"", CONIC_DASH[0], CONIC_DASH[1])
+                    }
+                    _ => String::new(),
+                };
+
+                for points in polylines(&construction.shape, frame) {
+                    let points: Vec<String> = points
+                        .iter()
+                        .map(|point| {
+                            let point = frame.screen_pos(*point);
+                            format!("{},{}", point.x, point.y)
+                        })
+                        .collect();
+
+                    writeln!(
+                        svg,
+                        "<polyline points=\"{}\"{} {}/>",
+                        points.join(" "),
+                        dash,
+                        stroke
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+
+    if let Some(intersection_color) = style.intersections {
+        for construction in engine.visible_constructions() {
+            for point in &construction.intersections {
+                let pos = frame.screen_pos(*point);
+
+                writeln!(
+                    svg,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"2\" fill=\"{}\"/>",
+                    pos.x,
+                    pos.y,
+                    color(intersection_color)
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn color(color: Color) -> String {
+    let [r, g, b, _] = color.to_array();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn opacity(color: Color) -> String {
+    match color.a {
+        255 => String::new(),
+        a => format!(" stroke-opacity=\"{}\"", a as f32 / 255.0),
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt::Write;
+
+use super::{arc_span, polylines, Frame, Style};
+use crate::engine::{color::Color, shapes::Shape, Engine};
+
+/// Writes a `tikzpicture` in plot units, clipped to the frame.
+pub fn render(engine: &Engine, frame: &Frame, style: &Style) -> String {
+    let mut tikz = String::new();
+
+    writeln!(tikz, "\\begin{{tikzpicture}}").unwrap();
+    writeln!(
+        tikz,
+        "\\clip ({}, {}) rectangle ({}, {});",
+        frame.min.x, frame.min.y, frame.max.x, frame.max.y
+    )
+    .unwrap();
+
+    for construction in engine.visible_constructions() {
+        let options = format!(
+            "color={}, line width={}pt",
+            color(construction.color),
+            construction.width * 0.5
+        );
+
+        match &construction.shape {
+            Shape::Circle(circle) => writeln!(
+                tikz,
+                "\\draw[{}] ({}, {}) circle ({});",
+                options, circle.pos.x, circle.pos.y, circle.r
+            )
+            .unwrap(),
+            Shape::Line(line) => {
+                if let Some((p1, p2)) = frame.clip_line(line.p1, line.p2) {
+                    writeln!(
+                        tikz,
+                        "\\draw[{}] ({}, {}) -- ({}, {});",
+                        options, p1.x, p1.y, p2.x, p2.y
+                    )
+                    .unwrap();
+                }
+            }
+            Shape::Segment(segment) => writeln!(
+                tikz,
+                "\\draw[{}] ({}, {}) -- ({}, {});",
+                options, segment.p1.x, segment.p1.y, segment.p2.x, segment.p2.y
+            )
+            .unwrap(),
+            Shape::Arc(arc) => {
+                let start = arc.start.to_degrees();
+                let stop = (arc.start + arc_span(arc.start, arc.stop)).to_degrees();
+
+                writeln!(
+                    tikz,
+                    "\\draw[{}] ({}, {}) arc[start angle={}, end angle={}, radius={}];",
+                    options,
+                    arc.pos.x + arc.r * arc.start.cos(),
+                    arc.pos.y + arc.r * arc.start.sin(),
+                    start,
+                    stop,
+                    arc.r
+                )
+                .unwrap();
+            }
+            Shape::Conic(_) | Shape::Locus(_) => {
+                let options = match construction.shape {
+                    Shape::Conic(_) => format!("{}, dashed", options),
+                    _ => options,
+                };
+
+                for points in polylines(&construction.shape, frame) {
+                    let points: Vec<String> = points
+                        .iter()
+                        .map(|point| format!("({}, {})", point.x, point.y))
+                        .collect();
+
+                    writeln!(tikz, "\\draw[{}] {};", options, points.join(" -- ")).unwrap();
+                }
+            }
+        }
+    }
+
+    if let Some(intersection_color) = style.intersections {
+        for construction in engine.visible_constructions() {
+            for point in &construction.intersections {
+                writeln!(
+                    tikz,
+                    "\\fill[color={}] ({}, {}) circle (1pt);",
+                    color(intersection_color),
+                    point.x,
+                    point.y
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    writeln!(tikz, "\\end{{tikzpicture}}").unwrap();
+    tikz
+}
+
+fn color(color: Color) -> String {
+    let [r, g, b, _] = color.to_array();
+    format!("{{rgb,255:red,{};green,{};blue,{}}}", r, g, b)
+}
@@ -0,0 +1,683 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{Cursor, Read, Write},
+};
+
+use super::{
+    color::Color,
+    definition::{Definition, Input},
+    document::{self, Document},
+    pos::Pos,
+    shapes::{ArcData, CircleData, ConicData, Construction, LineData, SegmentData, Shape},
+    utils,
+};
+
+/// Name of the construction file inside a .ggb archive.
+const XML_FILE: &str = "geogebra.xml";
+
+/// GeoGebra's default line thickness, treated as a width of 1.0.
+const DEFAULT_THICKNESS: f32 = 5.0;
+
+#[derive(Debug)]
+pub enum GgbError {
+    Io(std::io::Error),
+    Zip(zip::result::ZipError),
+    Xml(roxmltree::Error),
+    MissingConstruction,
+}
+
+impl fmt::Display for GgbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GgbError::Io(e) => write!(f, "io error: {}", e),
+            GgbError::Zip(e) => write!(f, "invalid ggb archive: {}", e),
+            GgbError::Xml(e) => write!(f, "invalid {}: {}", XML_FILE, e),
+            GgbError::MissingConstruction => write!(f, "{} has no construction", XML_FILE),
+        }
+    }
+}
+
+impl std::error::Error for GgbError {}
+
+impl From<std::io::Error> for GgbError {
+    fn from(e: std::io::Error) -> Self {
+        GgbError::Io(e)
+    }
+}
+
+impl From<zip::result::ZipError> for GgbError {
+    fn from(e: zip::result::ZipError) -> Self {
+        GgbError::Zip(e)
+    }
+}
+
+impl From<roxmltree::Error> for GgbError {
+    fn from(e: roxmltree::Error) -> Self {
+        GgbError::Xml(e)
+    }
+}
+
+/// A GeoGebra object that could not be turned into a construction.
+#[derive(Debug, Clone)]
+pub struct Unsupported {
+    pub label: String,
+    pub kind: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportSummary {
+    pub points: usize,
+    pub constructions: usize,
+    pub unsupported: Vec<Unsupported>,
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "points: {}", self.points)?;
+        writeln!(f, "constructions: {}", self.constructions)?;
+        writeln!(f, "unsupported: {}", self.unsupported.len())?;
+
+        for object in &self.unsupported {
+            writeln!(f, "  {} ({}): {}", object.label, object.kind, object.reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Command {
+    name: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+/// Reads the points, circles, lines, segments and circular arcs of a .ggb file.
+pub fn import(data: &[u8]) -> Result<(Document, ImportSummary), GgbError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+
+    let mut xml = String::new();
+    archive.by_name(XML_FILE)?.read_to_string(&mut xml)?;
+
+    import_xml(&xml)
+}
+
+pub fn import_xml(xml: &str) -> Result<(Document, ImportSummary), GgbError> {
+    let document = roxmltree::Document::parse(xml)?;
+
+    let construction = document
+        .descendants()
+        .find(|node| node.has_tag_name("construction"))
+        .ok_or(GgbError::MissingConstruction)?;
+
+    let elements: Vec<roxmltree::Node> = construction
+        .children()
+        .filter(|node| node.has_tag_name("element"))
+        .collect();
+
+    let mut points: HashMap<&str, Pos> = HashMap::new();
+
+    for element in &elements {
+        if element.attribute("type") == Some("point") {
+            if let (Some(label), Some(pos)) = (element.attribute("label"), point_coords(element)) {
+                points.insert(label, pos);
+            }
+        }
+    }
+
+    // Maps each output label to the command that created it and its position in the outputs
+    let mut commands: HashMap<String, (usize, usize)> = HashMap::new();
+    let command_list: Vec<Command> = construction
+        .children()
+        .filter(|node| node.has_tag_name("command"))
+        .map(|node| Command {
+            name: node.attribute("name").unwrap_or_default().to_owned(),
+            inputs: numbered_attributes(node, "input"),
+            outputs: numbered_attributes(node, "output"),
+        })
+        .collect();
+
+    for (i, command) in command_list.iter().enumerate() {
+        for (j, output) in command.outputs.iter().enumerate() {
+            commands.insert(output.clone(), (i, j));
+        }
+    }
+
+    let mut summary = ImportSummary::default();
+    let mut result = Document::default();
+
+    result.metadata.title = construction
+        .attribute("title")
+        .unwrap_or_default()
+        .to_owned();
+    result.metadata.author = construction
+        .attribute("author")
+        .unwrap_or_default()
+        .to_owned();
+
+    for element in &elements {
+        let kind = element.attribute("type").unwrap_or("unknown");
+        let label = element.attribute("label").unwrap_or("unnamed");
+
+        let command = commands.get(label).map(|(i, j)| (&command_list[*i], *j));
+
+        let shape = match kind {
+            "point" => {
+                if let Some(pos) = points.get(label) {
+                    result.points.push(document::Point {
+                        label: label.to_owned(),
+                        pos: *pos,
+                    });
+                    summary.points += 1;
+                    continue;
+                }
+
+                Err("point is at infinity".to_owned())
+            }
+            "line" => import_line(element, command, &points),
+            "segment" => import_segment(command, &points),
+            "conic" => import_circle(element, command, &points),
+            "conicpart" => import_arc(command, &points),
+            _ => Err("object type is not supported".to_owned()),
+        };
+
+        match shape {
+            Ok(shape) => {
+                let layer = format!("Layer {}", layer(element) + 1);
+
+                if !result.layers.iter().any(|l| l.name == layer) {
+                    result.layers.push(document::Layer::new(&layer));
+                }
+
+                result.constructions.push(Construction {
+                    shape,
+                    layer,
+                    color: color(element),
+                    width: width(element),
+                    label: Some(label.to_owned()),
+                    definition: definition(kind, command, &points),
+                    hidden: false,
+                    caption: None,
+                    cost: 0,
+                    intersections: Vec::new(),
+                });
+                summary.constructions += 1;
+            }
+            Err(reason) => summary.unsupported.push(Unsupported {
+                label: label.to_owned(),
+                kind: kind.to_owned(),
+                reason,
+            }),
+        }
+    }
+
+    Ok((result, summary))
+}
+
+fn import_line(
+    element: &roxmltree::Node,
+    command: Option<(&Command, usize)>,
+    points: &HashMap<&str, Pos>,
+) -> Result<Shape, String> {
+    if let Some((command, _)) = command.filter(|(c, _)| c.name == "Line") {
+        if let Ok([p1, p2]) = resolve_points(&command.inputs, points).as_deref() {
+            return Ok(Shape::Line(LineData { p1: *p1, p2: *p2 }));
+        }
+    }
+
+    // Fall back to the stored equation x*X + y*Y + z = 0
+    let coords = element
+        .children()
+        .find(|node| node.has_tag_name("coords"))
+        .ok_or("line has no coordinates")?;
+
+    let x = float_attribute(&coords, "x").ok_or("line has no x coefficient")?;
+    let y = float_attribute(&coords, "y").ok_or("line has no y coefficient")?;
+    let z = float_attribute(&coords, "z").ok_or("line has no z coefficient")?;
+
+    let norm = x * x + y * y;
+    if norm == 0.0 {
+        return Err("line is at infinity".to_owned());
+    }
+
+    let p1 = Pos::new(-x * z / norm, -y * z / norm);
+    let p2 = p1 + Pos::new(-y, x).normalized();
+
+    Ok(Shape::Line(LineData { p1, p2 }))
+}
+
+fn import_segment(
+    command: Option<(&Command, usize)>,
+    points: &HashMap<&str, Pos>,
+) -> Result<Shape, String> {
+    let (command, output) = command.ok_or("segment has no defining command")?;
+
+    let (p1, p2) = match command.name.as_str() {
+        "Segment" => match resolve_points(&command.inputs, points)?[..] {
+            [p1, p2] => (p1, p2),
+            _ => return Err("segment is not defined by two points".to_owned()),
+        },
+        // Polygon(A, B, C) outputs the polygon followed by the sides AB, BC, CA
+        "Polygon" if output > 0 => {
+            let vertices = resolve_points(&command.inputs, points)?;
+            if vertices.len() < 3 {
+                return Err("polygon side is not defined by points".to_owned());
+            }
+
+            (
+                vertices[(output - 1) % vertices.len()],
+                vertices[output % vertices.len()],
+            )
+        }
+        name => return Err(format!("segments from {} are not supported", name)),
+    };
+
+    Ok(Shape::Segment(SegmentData { p1, p2 }))
+}
+
+fn import_circle(
+    element: &roxmltree::Node,
+    command: Option<(&Command, usize)>,
+    points: &HashMap<&str, Pos>,
+) -> Result<Shape, String> {
+    if let Some((command, _)) = command.filter(|(c, _)| c.name == "Circle") {
+        if let [center, radius] = &command.inputs[..] {
+            if let Some(pos) = points.get(center.as_str()) {
+                let r = match (points.get(radius.as_str()), radius.parse::<f32>()) {
+                    (Some(through), _) => Some(pos.distance(*through)),
+                    (None, Ok(r)) => Some(r),
+                    _ => None,
+                };
+
+                if let Some(r) = r {
+                    return Ok(Shape::Circle(CircleData { pos: *pos, r }));
+                }
+            }
+        }
+    }
+
+    // Fall back to the stored matrix A0 x² + A1 y² + A2 + 2 A3 xy + 2 A4 x + 2 A5 y = 0
+    let matrix = element
+        .children()
+        .find(|node| node.has_tag_name("matrix"))
+        .ok_or("conic has no matrix")?;
+
+    let a: Vec<f32> = (0..6)
+        .map(|i| float_attribute(&matrix, &format!("A{}", i)))
+        .collect::<Option<Vec<f32>>>()
+        .ok_or("conic matrix is incomplete")?;
+
+    if a[0] == 0.0 || (a[0] - a[1]).abs() > 1e-6 * a[0].abs() || a[3].abs() > 1e-6 * a[0].abs() {
+        return Ok(Shape::Conic(ConicData::from_ggb_matrix([
+            a[0], a[1], a[2], a[3], a[4], a[5],
+        ])));
+    }
+
+    let pos = Pos::new(-a[4] / a[0], -a[5] / a[0]);
+    let r_sq = pos.length_sq() - a[2] / a[0];
+
+    if r_sq <= 0.0 {
+        return Err("circle has no real points".to_owned());
+    }
+
+    Ok(Shape::Circle(CircleData {
+        pos,
+        r: r_sq.sqrt(),
+    }))
+}
+
+fn import_arc(
+    command: Option<(&Command, usize)>,
+    points: &HashMap<&str, Pos>,
+) -> Result<Shape, String> {
+    let (command, _) = command.ok_or("arc has no defining command")?;
+
+    if command.name != "CircleArc" {
+        return Err(format!("{} is not supported", command.name));
+    }
+
+    match resolve_points(&command.inputs, points)?[..] {
+        [pos, start, stop] => Ok(Shape::Arc(ArcData {
+            pos,
+            r: pos.distance(start),
+            start: utils::arc_angle(start, pos),
+            stop: utils::arc_angle(stop, pos),
+        })),
+        _ => Err("arc is not defined by three points".to_owned()),
+    }
+}
+
+/// How a shape was made from points, so that it follows them when they are dragged. `None`
+/// for shapes given by equations or made by commands the tools cannot repeat.
+fn definition(
+    kind: &str,
+    command: Option<(&Command, usize)>,
+    points: &HashMap<&str, Pos>,
+) -> Option<Definition> {
+    let (command, output) = command?;
+    let inputs = resolve_points(&command.inputs, points).ok()?;
+
+    let (tool, inputs) = match (kind, command.name.as_str(), &inputs[..]) {
+        ("segment", "Segment", [p1, p2]) => ("line segment", vec![*p1, *p2]),
+        ("segment", "Polygon", vertices) if output > 0 && vertices.len() >= 3 => (
+            "line segment",
+            vec![
+                vertices[(output - 1) % vertices.len()],
+                vertices[output % vertices.len()],
+            ],
+        ),
+        ("line", "Line", [p1, p2]) => ("straight edge", vec![*p1, *p2]),
+        ("conic", "Circle", [center, through]) => ("compass", vec![*center, *through]),
+        // The arc tool takes the radius and the start as separate points
+        ("conicpart", "CircleArc", [center, start, stop]) => {
+            ("arc", vec![*center, *start, *start, *stop])
+        }
+        _ => return None,
+    };
+
+    Some(Definition {
+        tool: tool.to_owned(),
+        inputs: inputs.into_iter().map(|pos| Input::Free { pos }).collect(),
+    })
+}
+
+fn resolve_points(labels: &[String], points: &HashMap<&str, Pos>) -> Result<Vec<Pos>, String> {
+    labels
+        .iter()
+        .map(|label| {
+            points
+                .get(label.as_str())
+                .copied()
+                .ok_or(format!("input {} is not a point", label))
+        })
+        .collect()
+}
+
+fn numbered_attributes(node: roxmltree::Node, tag: &str) -> Vec<String> {
+    node.children()
+        .find(|child| child.has_tag_name(tag))
+        .map(|child| {
+            (0..)
+                .map_while(|i| child.attribute(format!("a{}", i).as_str()))
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn float_attribute(node: &roxmltree::Node, name: &str) -> Option<f32> {
+    node.attribute(name).and_then(|v| v.parse().ok())
+}
+
+fn child_attribute(node: &roxmltree::Node, tag: &str, name: &str) -> Option<f32> {
+    node.children()
+        .find(|child| child.has_tag_name(tag))
+        .and_then(|child| float_attribute(&child, name))
+}
+
+fn point_coords(element: &roxmltree::Node) -> Option<Pos> {
+    let x = child_attribute(element, "coords", "x")?;
+    let y = child_attribute(element, "coords", "y")?;
+    let z = child_attribute(element, "coords", "z").unwrap_or(1.0);
+
+    if z == 0.0 {
+        None
+    } else {
+        Some(Pos::new(x / z, y / z))
+    }
+}
+
+fn color(element: &roxmltree::Node) -> Color {
+    let channel = |name| child_attribute(element, "objColor", name).unwrap_or(0.0) as u8;
+    Color::from_rgb(channel("r"), channel("g"), channel("b"))
+}
+
+fn width(element: &roxmltree::Node) -> f32 {
+    let thickness = child_attribute(element, "lineStyle", "thickness").unwrap_or(DEFAULT_THICKNESS);
+
+    (thickness / DEFAULT_THICKNESS).clamp(0.5, 5.0)
+}
+
+fn layer(element: &roxmltree::Node) -> u32 {
+    child_attribute(element, "layer", "val").unwrap_or(0.0) as u32
+}
+
+/// Writes the document's points and constructions as a .ggb archive.
+pub fn export(document: &Document) -> Result<Vec<u8>, GgbError> {
+    let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+
+    archive.start_file(XML_FILE, zip::write::FileOptions::default())?;
+    archive.write_all(export_xml(document).as_bytes())?;
+
+    Ok(archive.finish()?.into_inner())
+}
+
+pub fn export_xml(document: &Document) -> String {
+    let mut points: Vec<(Pos, String)> = Vec::new();
+    let mut point_xml = String::new();
+    let mut object_xml = String::new();
+
+    let mut write_point = |pos: Pos, label: &str, style: String| {
+        point_xml.push_str(&format!(
+            "<element type=\"point\" label=\"{}\">\n\
+             \t<show object=\"true\" label=\"true\"/>\n\
+             {}\
+             \t<coords x=\"{}\" y=\"{}\" z=\"1\"/>\n\
+             \t<pointSize val=\"5\"/>\n\
+             </element>\n",
+            escape(label),
+            style,
+            pos.x,
+            pos.y,
+        ));
+    };
+
+    for point in &document.points {
+        let pos = point.pos;
+        write_point(pos, &point.label, style_xml(Color::BLACK, ""));
+        points.push((pos, point.label.clone()));
+    }
+
+    // Returns the label of a point, writing it out the first time it is seen
+    let mut point_label = |pos: Pos, construction: &Construction| -> String {
+        if let Some((_, label)) = points.iter().find(|(p, _)| *p == pos) {
+            return label.clone();
+        }
+
+        let label = (0..)
+            .map(point_name)
+            .find(|name| !points.iter().any(|(_, label)| label == name))
+            .unwrap();
+
+        write_point(
+            pos,
+            &label,
+            style_xml(construction.color, &construction.layer),
+        );
+        points.push((pos, label.clone()));
+
+        label
+    };
+
+    for (i, construction) in document.constructions.iter().enumerate() {
+        let (command, kind, inputs, geometry) = match &construction.shape {
+            Shape::Circle(circle) => {
+                let pos = circle.pos;
+                // The point the compass was opened to, or one made up for circles without one
+                let through = construction
+                    .definition
+                    .as_ref()
+                    .and_then(|definition| definition.inputs.get(1))
+                    .map_or(pos + Pos::new(circle.r, 0.0), |input| input.pos());
+
+                (
+                    "Circle",
+                    "conic",
+                    vec![
+                        point_label(pos, construction),
+                        point_label(through, construction),
+                    ],
+                    circle_matrix(pos, circle.r),
+                )
+            }
+            Shape::Line(line) => (
+                "Line",
+                "line",
+                vec![
+                    point_label(line.p1, construction),
+                    point_label(line.p2, construction),
+                ],
+                line_coords(line.p1, line.p2),
+            ),
+            Shape::Segment(segment) => (
+                "Segment",
+                "segment",
+                vec![
+                    point_label(segment.p1, construction),
+                    point_label(segment.p2, construction),
+                ],
+                line_coords(segment.p1, segment.p2),
+            ),
+            Shape::Arc(arc) => {
+                let pos = arc.pos;
+                let (start, stop) = arc.endpoints();
+
+                (
+                    "CircleArc",
+                    "conicpart",
+                    vec![
+                        point_label(pos, construction),
+                        point_label(start, construction),
+                        point_label(stop, construction),
+                    ],
+                    circle_matrix(pos, arc.r),
+                )
+            }
+            // Written as a free conic, given by its matrix alone
+            Shape::Conic(conic) => ("", "conic", Vec::new(), conic_matrix(conic.ggb_matrix())),
+            // GeoGebra makes loci from the construction they depend on, which is not kept
+            Shape::Locus(_) => continue,
+        };
+
+        let label = construction
+            .label
+            .clone()
+            .unwrap_or_else(|| format!("{}_{{{}}}", kind_prefix(kind), i + 1));
+
+        let input_attributes: String = inputs
+            .iter()
+            .enumerate()
+            .map(|(j, input)| format!(" a{}=\"{}\"", j, escape(input)))
+            .collect();
+
+        if !inputs.is_empty() {
+            object_xml.push_str(&format!(
+                "<command name=\"{}\">\n\
+                 \t<input{}/>\n\
+                 \t<output a0=\"{}\"/>\n\
+                 </command>\n",
+                command,
+                input_attributes,
+                escape(&label),
+            ));
+        }
+
+        // GeoGebra's long dashes, marking conics as outside compass and straight edge
+        let line_type = match construction.shape {
+            Shape::Conic(_) => 15,
+            _ => 0,
+        };
+
+        object_xml.push_str(&format!(
+            "<element type=\"{}\" label=\"{}\">\n\
+             \t<show object=\"true\" label=\"false\"/>\n\
+             {}\
+             \t<lineStyle thickness=\"{}\" type=\"{}\"/>\n\
+             {}\
+             </element>\n",
+            kind,
+            escape(&label),
+            style_xml(construction.color, &construction.layer),
+            (construction.width * DEFAULT_THICKNESS)
+                .round()
+                .clamp(1.0, 13.0),
+            line_type,
+            geometry,
+        ));
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <geogebra format=\"5.0\">\n\
+         <construction title=\"{}\" author=\"{}\" date=\"\">\n\
+         {}{}\
+         </construction>\n\
+         </geogebra>\n",
+        escape(&document.metadata.title),
+        escape(&document.metadata.author),
+        point_xml,
+        object_xml
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn style_xml(color: Color, layer: &str) -> String {
+    let [r, g, b, _] = color.to_array();
+
+    let layer = layer
+        .strip_prefix("Layer ")
+        .and_then(|n| n.parse::<u32>().ok())
+        .map(|n| n.saturating_sub(1))
+        .unwrap_or(0);
+
+    format!(
+        "\t<objColor r=\"{}\" g=\"{}\" b=\"{}\" alpha=\"0\"/>\n\t<layer val=\"{}\"/>\n",
+        r, g, b, layer
+    )
+}
+
+fn circle_matrix(pos: Pos, r: f32) -> String {
+    conic_matrix([1.0, 1.0, pos.length_sq() - r * r, 0.0, -pos.x, -pos.y])
+}
+
+fn conic_matrix(a: [f32; 6]) -> String {
+    format!(
+        "\t<matrix A0=\"{}\" A1=\"{}\" A2=\"{}\" A3=\"{}\" A4=\"{}\" A5=\"{}\"/>\n",
+        a[0], a[1], a[2], a[3], a[4], a[5]
+    )
+}
+
+fn line_coords(p1: Pos, p2: Pos) -> String {
+    format!(
+        "\t<coords x=\"{}\" y=\"{}\" z=\"{}\"/>\n",
+        p1.y - p2.y,
+        p2.x - p1.x,
+        p1.x * p2.y - p2.x * p1.y
+    )
+}
+
+fn kind_prefix(kind: &str) -> &str {
+    match kind {
+        "conic" => "c",
+        "line" => "f",
+        "segment" => "s",
+        _ => "d",
+    }
+}
+
+/// Point labels in GeoGebra's style: A, B, ..., Z, A_1, B_1, ...
+fn point_name(index: usize) -> String {
+    let letter = (b'A' + (index % 26) as u8) as char;
+
+    match index / 26 {
+        0 => letter.to_string(),
+        n => format!("{}_{{{}}}", letter, n),
+    }
+}
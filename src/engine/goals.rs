@@ -0,0 +1,427 @@
+//! Checks a document against goals such as "a line through P perpendicular to l", for marking
+//! exercises. Goals refer to labelled points and constructions by their labels.
+//!
+//! ```yaml
+//! tolerance: 0.001
+//! goals:
+//!   - type: perpendicular
+//!     through: P
+//!     to: l
+//!   - type: tangent_circle
+//!     to: [c1, c2]
+//!   - type: inscribed_polygon
+//!     circle: c
+//!     sides: 6
+//!   - type: distance_ratio
+//!     from: A
+//!     to: B
+//!     ratio: 1.41421356
+//! ```
+
+use std::{f32::consts::PI, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    document::Document,
+    pos::Pos,
+    shapes::{CircleData, Construction, LineData, Shape},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Goal {
+    /// A line through the point `through` perpendicular to the line `to`.
+    Perpendicular { through: String, to: String },
+    /// A circle tangent to both shapes, which may be circles or lines.
+    TangentCircle { to: [String; 2] },
+    /// A regular polygon with `sides` sides, made of segments, inscribed in `circle`.
+    InscribedPolygon { circle: String, sides: u32 },
+    /// A point X with |`from` X| = `ratio` · |`from` `to`|. If `point` is given it must be
+    /// that point, otherwise any labelled point or intersection will do.
+    DistanceRatio {
+        from: String,
+        to: String,
+        ratio: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        point: Option<String>,
+    },
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Goal::Perpendicular { through, to } => {
+                write!(f, "line through {} perpendicular to {}", through, to)
+            }
+            Goal::TangentCircle { to: [a, b] } => write!(f, "circle tangent to {} and {}", a, b),
+            Goal::InscribedPolygon { circle, sides } => {
+                write!(f, "regular {}-gon inscribed in {}", sides, circle)
+            }
+            Goal::DistanceRatio {
+                from,
+                to,
+                ratio,
+                point,
+            } => {
+                let point = point.as_deref().unwrap_or("X");
+                write!(
+                    f,
+                    "point {0} with |{1}{0}| = {2}·|{1}{3}|",
+                    point, from, ratio, to
+                )
+            }
+        }
+    }
+}
+
+/// Why a goal was not met, naming the object at fault if there is one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub object: Option<String>,
+    pub reason: String,
+}
+
+impl Failure {
+    fn new(object: Option<String>, reason: &str) -> Self {
+        Failure {
+            object,
+            reason: reason.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.object {
+            Some(object) => write!(f, "{}: {}", object, self.reason),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// A list of goals, as read from a goal file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Goals {
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f32,
+    pub goals: Vec<Goal>,
+}
+
+fn default_tolerance() -> f32 {
+    1e-3
+}
+
+impl Goals {
+    pub fn parse(data: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(data)
+    }
+
+    /// Checks every goal, in order.
+    pub fn check(&self, document: &Document) -> Vec<Result<(), Failure>> {
+        self.goals
+            .iter()
+            .map(|goal| check(document, goal, self.tolerance))
+            .collect()
+    }
+}
+
+/// Checks whether `document` meets `goal`, with lengths and angles compared to within `tolerance`.
+pub fn check(document: &Document, goal: &Goal, tolerance: f32) -> Result<(), Failure> {
+    let checker = Checker {
+        document,
+        tolerance,
+    };
+
+    match goal {
+        Goal::Perpendicular { through, to } => checker.perpendicular(through, to),
+        Goal::TangentCircle { to } => checker.tangent_circle(to),
+        Goal::InscribedPolygon { circle, sides } => checker.inscribed_polygon(circle, *sides),
+        Goal::DistanceRatio {
+            from,
+            to,
+            ratio,
+            point,
+        } => checker.distance_ratio(from, to, *ratio, point.as_deref()),
+    }
+}
+
+struct Checker<'a> {
+    document: &'a Document,
+    tolerance: f32,
+}
+
+impl<'a> Checker<'a> {
+    fn point(&self, label: &str) -> Result<Pos, Failure> {
+        self.document
+            .points
+            .iter()
+            .find(|point| point.label == label)
+            .map(|point| point.pos)
+            .ok_or_else(|| Failure::new(Some(label.to_owned()), "no point with this label"))
+    }
+
+    fn shape(&self, label: &str) -> Result<&'a Construction, Failure> {
+        self.document
+            .constructions
+            .iter()
+            .find(|construction| construction.label.as_deref() == Some(label))
+            .ok_or_else(|| Failure::new(Some(label.to_owned()), "no construction with this label"))
+    }
+
+    /// Constructions other than those in `exclude`, with a name to report them by.
+    fn candidates<'b>(
+        &'b self,
+        exclude: &'b [&Construction],
+    ) -> impl Iterator<Item = (String, &'a Construction)> + 'b {
+        self.document
+            .constructions
+            .iter()
+            .enumerate()
+            .filter(|(_, construction)| !exclude.iter().any(|e| std::ptr::eq(*e, *construction)))
+            .map(|(i, construction)| (name(i, construction), construction))
+    }
+
+    fn perpendicular(&self, through: &str, to: &str) -> Result<(), Failure> {
+        let point = self.point(through)?;
+        let target = self.shape(to)?;
+
+        let Some(target_direction) = direction(&target.shape) else {
+            return Err(Failure::new(Some(to.to_owned()), "is not a line"));
+        };
+
+        let mut offending = None;
+
+        for (name, construction) in self.candidates(&[target]) {
+            let Some(other) = direction(&construction.shape) else {
+                continue;
+            };
+
+            if construction.shape.distance(point) > self.tolerance {
+                continue;
+            }
+
+            if target_direction.dot(other).abs() <= self.tolerance {
+                return Ok(());
+            }
+
+            offending.get_or_insert(name);
+        }
+
+        Err(match offending {
+            Some(name) => Failure::new(
+                Some(name),
+                "passes through the point but is not perpendicular",
+            ),
+            None => Failure::new(None, &format!("no line passes through {}", through)),
+        })
+    }
+
+    fn tangent_circle(&self, to: &[String; 2]) -> Result<(), Failure> {
+        let a = self.shape(&to[0])?;
+        let b = self.shape(&to[1])?;
+
+        let mut offending = None;
+
+        for (name, construction) in self.candidates(&[a, b]) {
+            let Shape::Circle(circle) = &construction.shape else {
+                continue;
+            };
+
+            match (
+                tangent(circle, &a.shape, self.tolerance),
+                tangent(circle, &b.shape, self.tolerance),
+            ) {
+                (true, true) => return Ok(()),
+                (true, false) | (false, true) => {
+                    offending.get_or_insert(name);
+                }
+                _ => (),
+            }
+        }
+
+        Err(match offending {
+            Some(name) => Failure::new(Some(name), "is only tangent to one of the shapes"),
+            None => Failure::new(None, &format!("no circle is tangent to {}", to[0])),
+        })
+    }
+
+    fn inscribed_polygon(&self, label: &str, sides: u32) -> Result<(), Failure> {
+        let circle = match &self.shape(label)?.shape {
+            Shape::Circle(circle) => circle.clone(),
+            Shape::Arc(arc) => arc.circle(),
+            _ => return Err(Failure::new(Some(label.to_owned()), "is not a circle")),
+        };
+
+        if sides < 3 {
+            return Err(Failure::new(None, "a polygon needs at least 3 sides"));
+        }
+
+        let side = 2.0 * circle.r * (PI / sides as f32).sin();
+        let on_circle = |p: Pos| (p.distance(circle.pos) - circle.r).abs() <= self.tolerance;
+
+        // Vertices, and the pairs of them joined by a side of the right length
+        let mut vertices: Vec<Pos> = Vec::new();
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+
+        let mut vertex = |p: Pos| match vertices
+            .iter()
+            .position(|v| v.distance(p) <= self.tolerance)
+        {
+            Some(i) => i,
+            None => {
+                vertices.push(p);
+                vertices.len() - 1
+            }
+        };
+
+        for construction in &self.document.constructions {
+            let Shape::Segment(segment) = &construction.shape else {
+                continue;
+            };
+
+            if on_circle(segment.p1)
+                && on_circle(segment.p2)
+                && (segment.p1.distance(segment.p2) - side).abs() <= self.tolerance
+            {
+                let edge = (vertex(segment.p1), vertex(segment.p2));
+
+                if !edges.contains(&edge) && !edges.contains(&(edge.1, edge.0)) {
+                    edges.push(edge);
+                }
+            }
+        }
+
+        // Equal chords of a circle can only meet two to a vertex, so walking from any vertex
+        // either closes a polygon or runs out of sides
+        let closed =
+            (0..vertices.len()).any(|start| cycle_length(start, &edges) == Some(sides as usize));
+
+        if closed {
+            Ok(())
+        } else {
+            Err(Failure::new(
+                Some(label.to_owned()),
+                &format!("no regular {}-gon is inscribed in this circle", sides),
+            ))
+        }
+    }
+
+    fn distance_ratio(
+        &self,
+        from: &str,
+        to: &str,
+        ratio: f32,
+        point: Option<&str>,
+    ) -> Result<(), Failure> {
+        let a = self.point(from)?;
+        let b = self.point(to)?;
+        let target = ratio * a.distance(b);
+
+        let matches = |p: Pos| (a.distance(p) - target).abs() <= self.tolerance;
+
+        if let Some(label) = point {
+            return match matches(self.point(label)?) {
+                true => Ok(()),
+                false => Err(Failure::new(
+                    Some(label.to_owned()),
+                    &format!("is not {}·|{}{}| from {}", ratio, from, to, from),
+                )),
+            };
+        }
+
+        let constructions = &self.document.constructions;
+
+        let intersections = constructions.iter().enumerate().flat_map(|(i, a)| {
+            constructions[i + 1..]
+                .iter()
+                .flat_map(move |b| a.shape.intersections(&b.shape))
+        });
+
+        let found = self
+            .document
+            .points
+            .iter()
+            .map(|point| point.pos)
+            .chain(intersections)
+            .any(matches);
+
+        match found {
+            true => Ok(()),
+            false => Err(Failure::new(
+                None,
+                &format!("no point is {}·|{}{}| from {}", ratio, from, to, from),
+            )),
+        }
+    }
+}
+
+fn name(i: usize, construction: &Construction) -> String {
+    match &construction.label {
+        Some(label) => label.clone(),
+        None => format!("construction {}", i),
+    }
+}
+
+/// The unit direction of a line or segment.
+fn direction(shape: &Shape) -> Option<Pos> {
+    let (p1, p2) = match shape {
+        Shape::Line(line) => (line.p1, line.p2),
+        Shape::Segment(segment) => (segment.p1, segment.p2),
+        _ => return None,
+    };
+
+    Some((p2 - p1).normalized())
+}
+
+fn tangent(circle: &CircleData, shape: &Shape, tolerance: f32) -> bool {
+    let other = match shape {
+        Shape::Circle(other) => other.clone(),
+        Shape::Arc(arc) => arc.circle(),
+        Shape::Line(line) => return line_tangent(circle, line, tolerance),
+        Shape::Segment(segment) => return line_tangent(circle, &segment.line(), tolerance),
+        Shape::Conic(_) | Shape::Locus(_) => return false,
+    };
+
+    let d = circle.pos.distance(other.pos);
+
+    (d - (circle.r + other.r)).abs() <= tolerance
+        || (d - (circle.r - other.r).abs()).abs() <= tolerance
+}
+
+fn line_tangent(circle: &CircleData, line: &LineData, tolerance: f32) -> bool {
+    (Shape::Line(line.clone()).distance(circle.pos) - circle.r).abs() <= tolerance
+}
+
+/// The number of sides in the closed path of `edges` through `start`, if there is one.
+fn cycle_length(start: usize, edges: &[(usize, usize)]) -> Option<usize> {
+    let neighbours = |v: usize| {
+        edges.iter().filter_map(move |&(a, b)| {
+            if a == v {
+                Some(b)
+            } else if b == v {
+                Some(a)
+            } else {
+                None
+            }
+        })
+    };
+
+    let (mut previous, mut current) = (start, neighbours(start).next()?);
+    let mut length = 1;
+
+    while current != start {
+        let next = neighbours(current).find(|&v| v != previous)?;
+
+        (previous, current) = (current, next);
+        length += 1;
+
+        if length > edges.len() {
+            return None;
+        }
+    }
+
+    Some(length)
+}
@@ -0,0 +1,108 @@
+use std::{fmt, str::FromStr};
+
+use super::tools;
+
+/// A key and the modifiers that must be held with it, written like `ctrl+shift+z`. Ctrl
+/// stands for command on macOS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    /// Lowercase name of the key, such as `z`, `escape` or `f1`.
+    pub key: String,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut binding = KeyBinding {
+            key: String::new(),
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+
+        let mut parts: Vec<String> = s.split('+').map(|p| p.trim().to_lowercase()).collect();
+        let key = parts.pop().filter(|key| !key.is_empty());
+
+        for modifier in parts {
+            match modifier.as_str() {
+                "ctrl" | "cmd" | "command" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                "alt" | "option" => binding.alt = true,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", modifier, s)),
+            }
+        }
+
+        binding.key = key.ok_or_else(|| format!("no key in '{}'", s))?;
+
+        Ok(binding)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "ctrl"),
+            (self.shift, "shift"),
+            (self.alt, "alt"),
+        ] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+
+        write!(f, "{}", self.key)
+    }
+}
+
+/// Something a key binding can do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Undo,
+    Redo,
+    Save,
+    Open,
+    /// Drops the points of the operation in progress.
+    Cancel,
+    NextLayer,
+    PreviousLayer,
+    ToggleIntersections,
+    ToggleAxes,
+    TraceLocus,
+    /// Switches to the tool with this name.
+    Tool(String),
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim() {
+            "undo" => Action::Undo,
+            "redo" => Action::Redo,
+            "save" => Action::Save,
+            "open" => Action::Open,
+            "cancel" => Action::Cancel,
+            "next layer" => Action::NextLayer,
+            "previous layer" => Action::PreviousLayer,
+            "toggle intersections" => Action::ToggleIntersections,
+            "toggle axes" => Action::ToggleAxes,
+            "trace locus" => Action::TraceLocus,
+            name if tools::by_name(name).is_some() => Action::Tool(name.to_owned()),
+            name => return Err(format!("unknown action '{}'", name)),
+        })
+    }
+}
+
+/// Parses pairs of key and action, as written in the config.
+pub fn parse_bindings<'a>(
+    bindings: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Result<Vec<(KeyBinding, Action)>, String> {
+    bindings
+        .into_iter()
+        .map(|(key, action)| Ok((key.parse()?, action.parse()?)))
+        .collect()
+}
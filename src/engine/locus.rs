@@ -0,0 +1,186 @@
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    definition::{self, Input},
+    pos::Pos,
+    shapes::{Construction, SegmentData, Shape},
+};
+
+/// Positions the driver is moved to along its path.
+const SAMPLES: usize = 360;
+
+/// The tool name in the definition of a locus, whose inputs are the driver, the driver on its
+/// path and the traced point.
+pub const TOOL: &str = "locus";
+
+pub const INSTRUCTIONS: [&str; 3] = [
+    "select driver point",
+    "select circle, line or segment for the driver",
+    "select traced point",
+];
+
+/// The path of a point as another point moves, kept as the positions it was sampled at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocusData {
+    /// Runs of samples, split where the traced point did not exist.
+    pub pieces: Vec<Vec<Pos>>,
+}
+
+impl LocusData {
+    /// Moves `driver` along `path`, making everything that depends on it again, and records
+    /// where `traced` goes. `None` if the path is not a circle, line or segment.
+    pub fn trace(
+        constructions: &[Construction],
+        driver: Pos,
+        path: &Shape,
+        traced: &Input,
+    ) -> Option<Self> {
+        let mut pieces = Vec::new();
+
+        for walk in walks(constructions, driver, path)? {
+            let mut working = constructions.to_vec();
+            let mut current = driver;
+            let mut traced = traced.clone();
+            let mut piece = Vec::new();
+
+            for target in walk {
+                let next = definition::move_point(&working, current, target).and_then(|moved| {
+                    let followed = traced.follow(&moved, current, target)?;
+                    Some((moved, followed))
+                });
+
+                match next {
+                    Some((moved, followed)) => {
+                        working = moved;
+                        current = target;
+                        traced = followed;
+                        piece.push(traced.pos());
+                    }
+                    None if !piece.is_empty() => pieces.push(std::mem::take(&mut piece)),
+                    None => {}
+                }
+            }
+
+            if !piece.is_empty() {
+                pieces.push(piece);
+            }
+        }
+
+        // Walks in both directions from the driver meet where they started
+        if let [first, second] = &mut pieces[..] {
+            if first.first() == second.first() {
+                let mut joined: Vec<Pos> = second.drain(..).rev().collect();
+                joined.extend(first.drain(1..));
+                pieces = vec![joined];
+            }
+        }
+
+        pieces.retain(|piece| piece.len() > 1);
+
+        Some(LocusData { pieces })
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = SegmentData> + '_ {
+        self.pieces.iter().flat_map(|piece| {
+            piece.windows(2).map(|pair| SegmentData {
+                p1: pair[0],
+                p2: pair[1],
+            })
+        })
+    }
+
+    pub fn bounds(&self) -> (Pos, Pos) {
+        self.pieces
+            .iter()
+            .flatten()
+            .fold(None, |bounds: Option<(Pos, Pos)>, p| match bounds {
+                Some((min, max)) => Some((min.min(*p), max.max(*p))),
+                None => Some((*p, *p)),
+            })
+            .unwrap_or((Pos::ZERO, Pos::ZERO))
+    }
+
+    pub fn distance(&self, point: Pos) -> f32 {
+        self.segments()
+            .map(|segment| Shape::Segment(segment).distance(point))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    pub fn project(&self, point: Pos) -> Option<Pos> {
+        self.segments()
+            .filter_map(|segment| Shape::Segment(segment).project(point))
+            .min_by(|a, b| a.distance_sq(point).total_cmp(&b.distance_sq(point)))
+    }
+
+    /// Approximate intersections, treating the locus as the segments between its samples.
+    pub fn intersections(&self, other: &Shape) -> Vec<Pos> {
+        let mut points: Vec<Pos> = Vec::new();
+
+        for segment in self.segments() {
+            for point in Shape::Segment(segment).intersections(other) {
+                // Neighbouring segments share an end, which may be found twice
+                if !points
+                    .iter()
+                    .any(|p| p.distance(point) <= 1e-5 * (1.0 + p.length()))
+                {
+                    points.push(point);
+                }
+            }
+        }
+
+        points
+    }
+}
+
+/// Positions along the path, starting from the driver. A circle is walked once around, while
+/// lines and segments are walked in each direction from the driver separately.
+fn walks(constructions: &[Construction], driver: Pos, path: &Shape) -> Option<Vec<Vec<Pos>>> {
+    let along = |start: Pos, step: Pos, steps: usize| -> Vec<Pos> {
+        (0..=steps).map(|i| start + step * i as f32).collect()
+    };
+
+    Some(match path {
+        Shape::Circle(circle) => {
+            let start = (driver - circle.pos).y.atan2((driver - circle.pos).x);
+
+            vec![(0..=SAMPLES)
+                .map(|i| {
+                    circle.pos + Pos::from_angle(start + TAU * i as f32 / SAMPLES as f32) * circle.r
+                })
+                .collect()]
+        }
+        Shape::Segment(segment) => {
+            let (p1, p2) = (segment.p1, segment.p2);
+            let t = ((driver - p1).dot(p2 - p1) / (p2 - p1).length_sq()).clamp(0.0, 1.0);
+            let start = p1 + (p2 - p1) * t;
+            let steps = |length: f32| ((SAMPLES as f32 * length).ceil() as usize).max(1);
+
+            vec![
+                along(start, (p2 - start) / steps(1.0 - t) as f32, steps(1.0 - t)),
+                along(start, (p1 - start) / steps(t) as f32, steps(t)),
+            ]
+        }
+        Shape::Line(line) => {
+            let direction = (line.p2 - line.p1).normalized();
+            let start = line.p1 + direction * (driver - line.p1).dot(direction);
+
+            // Far enough past the rest of the figure to show the locus's shape within it
+            let (min, max) = constructions
+                .iter()
+                .map(|construction| construction.shape.bounds())
+                .fold((start, start), |(min, max), (a, b)| {
+                    (min.min(a), max.max(b))
+                });
+            let reach = (max - min).length().max(1.0) * 2.0;
+            let step = direction * (reach / (SAMPLES / 2) as f32);
+
+            vec![
+                along(start, step, SAMPLES / 2),
+                along(start, -step, SAMPLES / 2),
+            ]
+        }
+        _ => return None,
+    })
+}
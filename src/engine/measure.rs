@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    definition::Input,
+    pos::Pos,
+    shapes::{Construction, Shape},
+};
+
+/// The kinds of measurement that can be placed with clicks, like a tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeasureKind {
+    Distance,
+    Angle,
+    Radius,
+    Circumference,
+    Area,
+    Ratio,
+}
+
+impl MeasureKind {
+    pub const ALL: [MeasureKind; 6] = [
+        MeasureKind::Distance,
+        MeasureKind::Angle,
+        MeasureKind::Radius,
+        MeasureKind::Circumference,
+        MeasureKind::Area,
+        MeasureKind::Ratio,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MeasureKind::Distance => "distance",
+            MeasureKind::Angle => "angle",
+            MeasureKind::Radius => "radius",
+            MeasureKind::Circumference => "circumference",
+            MeasureKind::Area => "area",
+            MeasureKind::Ratio => "ratio",
+        }
+    }
+
+    pub fn instructions(&self) -> Vec<&'static str> {
+        match self {
+            MeasureKind::Distance => vec!["select first point", "select second point"],
+            MeasureKind::Angle => vec!["select first point", "select vertex", "select last point"],
+            MeasureKind::Radius | MeasureKind::Circumference => vec!["select circle"],
+            MeasureKind::Area => vec!["select vertices, then the first again"],
+            MeasureKind::Ratio => vec![
+                "select first point of numerator",
+                "select second point of numerator",
+                "select first point of denominator",
+                "select second point of denominator",
+            ],
+        }
+    }
+
+    /// Clicks needed, or `None` for polygons which are closed by clicking their first vertex.
+    pub fn num_points(&self) -> Option<usize> {
+        match self {
+            MeasureKind::Distance => Some(2),
+            MeasureKind::Angle => Some(3),
+            MeasureKind::Radius | MeasureKind::Circumference => Some(1),
+            MeasureKind::Area => None,
+            MeasureKind::Ratio => Some(4),
+        }
+    }
+}
+
+/// A readout that is recalculated from the construction whenever it is shown. Circles are
+/// referred to by their index in the construction list, and points by where they came from
+/// as in a [`Definition`](super::definition::Definition).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Measurement {
+    Distance {
+        a: Input,
+        b: Input,
+    },
+    /// The angle at `vertex` between the rays to `a` and `b`, between 0° and 180°.
+    Angle {
+        a: Input,
+        vertex: Input,
+        b: Input,
+    },
+    Radius {
+        construction: usize,
+    },
+    Circumference {
+        construction: usize,
+    },
+    Area {
+        points: Vec<Input>,
+    },
+    /// The distance between the first pair of points divided by that between the second.
+    Ratio {
+        a: [Input; 2],
+        b: [Input; 2],
+    },
+}
+
+impl Measurement {
+    /// Updates the constructions the measurement refers to after constructions are deleted or
+    /// reordered. `None` if the circle of a radius or circumference was deleted. Points that
+    /// depended on a deleted construction stay where they were.
+    pub fn reindex(self, map: impl Fn(usize) -> Option<usize>) -> Option<Self> {
+        // Measurements come after every construction, so any of them may be depended on
+        let reindex = |input: Input| input.reindex(usize::MAX, &map);
+
+        Some(match self {
+            Measurement::Radius { construction } => Measurement::Radius {
+                construction: map(construction)?,
+            },
+            Measurement::Circumference { construction } => Measurement::Circumference {
+                construction: map(construction)?,
+            },
+            other => other.map_inputs(reindex),
+        })
+    }
+
+    /// The measurement with each of its points replaced by `f`.
+    pub fn map_inputs(self, mut f: impl FnMut(Input) -> Input) -> Self {
+        match self {
+            Measurement::Distance { a, b } => Measurement::Distance { a: f(a), b: f(b) },
+            Measurement::Angle { a, vertex, b } => Measurement::Angle {
+                a: f(a),
+                vertex: f(vertex),
+                b: f(b),
+            },
+            Measurement::Area { points } => Measurement::Area {
+                points: points.into_iter().map(f).collect(),
+            },
+            Measurement::Ratio { a, b } => Measurement::Ratio {
+                a: a.map(&mut f),
+                b: b.map(&mut f),
+            },
+            other => other,
+        }
+    }
+
+    /// The points the measurement is taken between, in the order they were clicked.
+    pub fn inputs(&self) -> Vec<&Input> {
+        match self {
+            Measurement::Distance { a, b } => vec![a, b],
+            Measurement::Angle { a, vertex, b } => vec![a, vertex, b],
+            Measurement::Radius { .. } | Measurement::Circumference { .. } => Vec::new(),
+            Measurement::Area { points } => points.iter().collect(),
+            Measurement::Ratio { a, b } => a.iter().chain(b).collect(),
+        }
+    }
+
+    /// Makes a measurement from clicked points. Radius and circumference measure the circle or
+    /// arc closest to the point clicked.
+    pub fn new(
+        kind: MeasureKind,
+        points: &[Input],
+        constructions: &[Construction],
+    ) -> Option<Self> {
+        let closest_circle = || {
+            constructions
+                .iter()
+                .enumerate()
+                .filter(|(_, construction)| {
+                    matches!(construction.shape, Shape::Circle(_) | Shape::Arc(_))
+                })
+                .map(|(i, construction)| (i, construction.shape.distance(points[0].pos())))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i)
+        };
+
+        Some(match kind {
+            MeasureKind::Distance => Measurement::Distance {
+                a: points[0].clone(),
+                b: points[1].clone(),
+            },
+            MeasureKind::Angle => Measurement::Angle {
+                a: points[0].clone(),
+                vertex: points[1].clone(),
+                b: points[2].clone(),
+            },
+            MeasureKind::Radius => Measurement::Radius {
+                construction: closest_circle()?,
+            },
+            MeasureKind::Circumference => Measurement::Circumference {
+                construction: closest_circle()?,
+            },
+            MeasureKind::Area if points.len() >= 3 => Measurement::Area {
+                points: points.to_vec(),
+            },
+            MeasureKind::Area => return None,
+            MeasureKind::Ratio => Measurement::Ratio {
+                a: [points[0].clone(), points[1].clone()],
+                b: [points[2].clone(), points[3].clone()],
+            },
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Measurement::Distance { .. } => "distance",
+            Measurement::Angle { .. } => "angle",
+            Measurement::Radius { .. } => "radius",
+            Measurement::Circumference { .. } => "circumference",
+            Measurement::Area { .. } => "area",
+            Measurement::Ratio { .. } => "ratio",
+        }
+    }
+
+    /// The measured value, with angles in degrees, or `None` if the circle it refers to
+    /// has gone or it has no value, as for an angle with a leg of no length or a ratio to a
+    /// length of zero.
+    pub fn value(&self, constructions: &[Construction]) -> Option<f32> {
+        Some(match self {
+            Measurement::Distance { a, b } => a.pos().distance(b.pos()),
+            Measurement::Angle { a, vertex, b } => {
+                let (a, vertex, b) = (a.pos(), vertex.pos(), b.pos());
+
+                if a == vertex || b == vertex {
+                    return None;
+                }
+
+                let (u, v) = ((a - vertex).normalized(), (b - vertex).normalized());
+                u.dot(v).clamp(-1.0, 1.0).acos().to_degrees()
+            }
+            Measurement::Radius { construction } => radius(constructions, *construction)?,
+            Measurement::Circumference { construction } => {
+                2.0 * std::f32::consts::PI * radius(constructions, *construction)?
+            }
+            Measurement::Area { points } => {
+                let points: Vec<Pos> = points.iter().map(Input::pos).collect();
+                let twice_area: f32 = points
+                    .iter()
+                    .zip(points.iter().cycle().skip(1))
+                    .map(|(p, q)| p.x * q.y - q.x * p.y)
+                    .sum();
+
+                twice_area.abs() / 2.0
+            }
+            Measurement::Ratio { a, b } => {
+                let denominator = b[0].pos().distance(b[1].pos());
+
+                if denominator == 0.0 {
+                    return None;
+                }
+
+                a[0].pos().distance(a[1].pos()) / denominator
+            }
+        })
+    }
+
+    /// The value to `precision` decimal places, with units where it has them.
+    pub fn format(&self, constructions: &[Construction], precision: usize) -> String {
+        match (self, self.value(constructions)) {
+            (_, None) => "-".to_owned(),
+            (Measurement::Angle { .. }, Some(value)) => format!("{:.*}°", precision, value),
+            (_, Some(value)) => format!("{:.*}", precision, value),
+        }
+    }
+
+    /// Where the readout is drawn on the plot.
+    pub fn anchor(&self, constructions: &[Construction]) -> Option<Pos> {
+        Some(match self {
+            Measurement::Distance { a, b } => (a.pos() + b.pos()) / 2.0,
+            Measurement::Angle { vertex, .. } => vertex.pos(),
+            Measurement::Radius { construction } | Measurement::Circumference { construction } => {
+                match &constructions.get(*construction)?.shape {
+                    Shape::Circle(circle) => circle.pos,
+                    Shape::Arc(arc) => arc.pos,
+                    _ => return None,
+                }
+            }
+            Measurement::Area { points } => {
+                points.iter().fold(Pos::ZERO, |sum, p| sum + p.pos()) / points.len() as f32
+            }
+            Measurement::Ratio { a, .. } => (a[0].pos() + a[1].pos()) / 2.0,
+        })
+    }
+
+    /// Segments to draw alongside the readout, showing what is being measured.
+    pub fn guides(&self) -> Vec<(Pos, Pos)> {
+        match self {
+            Measurement::Distance { a, b } => vec![(a.pos(), b.pos())],
+            Measurement::Angle { a, vertex, b } => {
+                vec![(vertex.pos(), a.pos()), (vertex.pos(), b.pos())]
+            }
+            Measurement::Radius { .. } | Measurement::Circumference { .. } => Vec::new(),
+            Measurement::Area { points } => points
+                .iter()
+                .zip(points.iter().cycle().skip(1))
+                .map(|(p, q)| (p.pos(), q.pos()))
+                .collect(),
+            Measurement::Ratio { a, b } => {
+                vec![(a[0].pos(), a[1].pos()), (b[0].pos(), b[1].pos())]
+            }
+        }
+    }
+}
+
+fn radius(constructions: &[Construction], index: usize) -> Option<f32> {
+    match &constructions.get(index)?.shape {
+        Shape::Circle(circle) => Some(circle.r),
+        Shape::Arc(arc) => Some(arc.r),
+        _ => None,
+    }
+}
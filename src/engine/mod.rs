@@ -1,44 +1,123 @@
+pub mod analysis;
+pub mod color;
 pub mod config;
+pub mod conic;
+pub mod definition;
+pub mod document;
+pub mod exact;
+pub mod export;
+pub mod ggb;
+pub mod goals;
+pub mod keys;
+pub mod locus;
+pub mod measure;
+pub mod playback;
+pub mod pos;
+pub mod puzzle;
+pub mod script;
 pub mod shapes;
+pub mod snap;
 pub mod tools;
+pub mod transform;
 pub mod utils;
 
-use egui::{
-    plot::{LineStyle, PlotPoint, Points},
-    Color32, Pos2,
-};
 use serde::Serialize;
 
-use self::{config::EngineConfig, shapes::Construction};
+use self::{
+    analysis::Analysis,
+    color::Color,
+    config::{ConfigError, EngineConfig},
+    definition::{Definition, Input},
+    document::{Camera, Document, DocumentError, Format, Layer, Metadata, Point, View},
+    exact::geometry::{ExactContext, ExactPos},
+    locus::LocusData,
+    measure::{MeasureKind, Measurement},
+    playback::Playback,
+    pos::Pos,
+    puzzle::{ActivePuzzle, Puzzle, PuzzleStatus},
+    shapes::{ConicKind, Construction, Shape},
+    snap::{Snap, SnapClass, SnapKind, SnapSettings},
+    transform::{Transform, TransformKind},
+};
 
-enum RedoFrame {
-    Single(Construction),
-    Group(Vec<Construction>),
+/// A change to the constructions that can be undone and redone.
+enum Command {
+    /// A construction added to the end.
+    Add(Construction),
+    /// Every construction replaced at once, as when a point is dragged or the figure cleared,
+    /// or the measurements changed.
+    Replace { before: Snapshot, after: Snapshot },
 }
 
-impl RedoFrame {
-    fn constructions(&self) -> Vec<Construction> {
-        match self {
-            RedoFrame::Single(construction) => vec![construction.clone()],
-            RedoFrame::Group(constructions) => constructions.clone(),
-        }
-    }
+/// The constructions and the measurements that refer to them by index, with the labelled
+/// points that move along with them.
+#[derive(Clone)]
+struct Snapshot {
+    constructions: Vec<Construction>,
+    measurements: Vec<Measurement>,
+    labelled_points: Vec<Point>,
+}
+
+/// A point being dragged.
+struct Drag {
+    /// The figure when the point was picked up, so the whole drag is undone at once.
+    before: Snapshot,
+    pos: Pos,
+    /// From the pointer to the point when it was picked up, so it does not jump to the pointer.
+    offset: Pos,
+    /// The construction the point is kept on, if it was placed on one.
+    on: Option<usize>,
 }
 
 pub struct Engine {
     pub config: EngineConfig,
 
-    pub points: Vec<Pos2>,
+    pub metadata: Metadata,
+    pub view: View,
+    pub layers: Vec<Layer>,
+    pub labelled_points: Vec<Point>,
+
+    pub points: Vec<Pos>,
+    /// Where each of `points` came from, for the definition of the construction they make.
+    inputs: Vec<Input>,
     pub constructions: Vec<Construction>,
+    pub measurements: Vec<Measurement>,
+    /// Transforms that have been defined, so they can be applied again.
+    pub transforms: Vec<Transform>,
+    /// Indices of the constructions that transforms apply to.
+    pub selection: Vec<usize>,
+    /// Drawn faintly but not part of the document, such as the result of a script being edited.
+    pub preview: Vec<Construction>,
+    pub puzzle: Option<ActivePuzzle>,
+    /// Exact coordinates of points and constructions, when exact arithmetic is on.
+    pub exact: Option<ExactContext>,
+    /// Relationships found in the figure, when the analyser is on.
+    pub analysis: Option<Analysis>,
+    /// Step by step replay of the constructions. Clicks and drags are ignored while it is on.
+    pub playback: Option<Playback>,
 
-    redo_stack: Vec<RedoFrame>,
+    undo_stack: Vec<Command>,
+    redo_stack: Vec<Command>,
+    drag: Option<Drag>,
+    camera_changed: bool,
 
     pub current_tool: &'static dyn tools::Tool,
+    /// Clicks place a measurement of this kind instead of using the current tool.
+    pub current_measure: Option<MeasureKind>,
+    /// Clicks define a transform of this kind, which is applied to the selection.
+    pub current_transform: Option<TransformKind>,
+    /// Clicks choose the driver point, its path and the traced point of a locus.
+    pub current_locus: bool,
     pub current_layer: String,
-    pub current_color: Color32,
+    pub current_color: Color,
     pub current_width: f32,
+    /// In screen pixels.
     pub snap_radius: f32,
-    pub show_intersections: bool,
+    pub snap_settings: SnapSettings,
+    /// Set while snapping is turned off for the moment, as when a modifier key is held.
+    pub snap_suspended: bool,
+    /// Plot units per screen pixel, as of the last time the plot was drawn.
+    pixel_size: f32,
 }
 
 #[derive(Debug, Clone, Copy, Serialize)]
@@ -68,123 +147,219 @@ impl EngineStats {
 }
 
 impl Engine {
-    pub fn new(config_file: &str) -> Self {
-        let config = EngineConfig::read(config_file);
+    pub fn new(config_file: &str) -> Result<Self, ConfigError> {
+        Ok(Engine::with_config(EngineConfig::read(config_file)?))
+    }
 
+    pub fn with_config(config: EngineConfig) -> Self {
         Engine {
             config: config.clone(),
 
+            metadata: Metadata::default(),
+            view: View::default(),
+            layers: (1..=5)
+                .map(|i| Layer::new(&format!("Layer {}", i)))
+                .collect(),
+            labelled_points: Vec::new(),
+
             points: Vec::new(),
+            inputs: Vec::new(),
             constructions: Vec::new(),
+            measurements: Vec::new(),
+            transforms: Vec::new(),
+            selection: Vec::new(),
+            preview: Vec::new(),
+            puzzle: None,
+            exact: None,
+            analysis: None,
+            playback: None,
 
+            undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            drag: None,
+            camera_changed: false,
 
             current_tool: &tools::Compass,
+            current_measure: None,
+            current_transform: None,
+            current_locus: false,
             current_layer: String::from("Layer 1"),
-            current_color: *config.tool_colors.get(0).expect("no tools colors"),
+            current_color: config
+                .tool_colors
+                .first()
+                .copied()
+                .unwrap_or(config.text_color),
             current_width: 1.0,
-            snap_radius: 0.1,
-            show_intersections: true,
+            snap_radius: config.snap_radius,
+            snap_settings: SnapSettings::with_order(&config.snap_order),
+            snap_suspended: false,
+            pixel_size: 0.01,
         }
     }
 
-    pub fn show(&self, ui: &mut egui::plot::PlotUi) {
-        if self.points.is_empty() && self.constructions.is_empty() {
-            return;
+    /// Sets the size of a screen pixel in plot units, which the snap radius is scaled by.
+    pub fn set_pixel_size(&mut self, size: f32) {
+        if size.is_finite() && size > 0.0 {
+            self.pixel_size = size;
         }
+    }
 
-        for construction in &self.constructions {
-            ui.line(construction.get_line(ui));
-
-            if self.show_intersections {
-                ui.points(
-                    Points::new(
-                        construction
-                            .intersections
-                            .iter()
-                            .map(|point| [point.x as f64, point.y as f64])
-                            .collect::<Vec<[f64; 2]>>(),
-                    )
-                    .color(self.config.intersection_color)
-                    .name(&construction.layer),
-                );
-            }
+    /// The snap radius in plot units.
+    pub fn snap_distance(&self) -> f32 {
+        self.snap_radius * self.pixel_size
+    }
+
+    /// Moves `mouse_pos` onto the closest snap target within `snap_radius` plot units, if there
+    /// is one.
+    pub fn get_snap_pos(&self, mouse_pos: Pos, snap_radius: f32) -> Pos {
+        self.snap(mouse_pos, snap_radius)
+            .map_or(mouse_pos, |snap| snap.pos)
+    }
+
+    /// The snap target for `mouse_pos` within `snap_radius` plot units, from the highest
+    /// priority class that is turned on and has one in range. `None` while snapping is
+    /// suspended.
+    pub fn snap(&self, mouse_pos: Pos, snap_radius: f32) -> Option<Snap> {
+        if self.snap_suspended {
+            return None;
         }
 
-        if let Some(mouse_pos) = ui.pointer_coordinate() {
-            let mouse_pos = mouse_pos.to_pos2();
-            let snap_pos = self.get_snap_pos(mouse_pos, self.snap_radius);
-
-            if snap_pos != mouse_pos {
-                ui.line(
-                    utils::segment(mouse_pos, snap_pos)
-                        .color(self.current_color.gamma_multiply(0.2))
-                        .style(LineStyle::dotted_loose()),
-                );
-            } else if self.points.is_empty() {
-                ui.line(
-                    utils::circle(mouse_pos, self.snap_radius)
-                        .color(self.current_color.gamma_multiply(0.2))
-                        .style(LineStyle::dotted_loose()),
-                );
-            }
+        self.snap_settings
+            .order
+            .iter()
+            .copied()
+            .filter(|&class| self.snap_settings.enabled(class))
+            .find_map(|class| {
+                self.closest_snap(class, mouse_pos)
+                    .filter(|snap| snap.pos.distance_sq(mouse_pos) <= snap_radius * snap_radius)
+            })
+    }
 
-            if !self.points.is_empty() {
-                for line in self.current_tool.get_guides(&self.points, snap_pos, ui) {
-                    ui.line(
-                        line.color(self.current_color.gamma_multiply(0.5))
-                            .width(self.current_width),
-                    );
-                }
+    /// The closest target of a class to `mouse_pos`, ignoring points already clicked.
+    fn closest_snap(&self, class: SnapClass, mouse_pos: Pos) -> Option<Snap> {
+        let closest = |a: &(usize, Pos), b: &(usize, Pos)| {
+            a.1.distance_sq(mouse_pos)
+                .total_cmp(&b.1.distance_sq(mouse_pos))
+        };
+        let visible = self
+            .constructions
+            .iter()
+            .enumerate()
+            .filter(|(_, construction)| self.is_visible(construction));
+
+        match class {
+            SnapClass::Intersections => {
+                self.closest_snap_target(mouse_pos, &self.points)
+                    .map(|pos| Snap {
+                        pos,
+                        kind: SnapKind::Intersection,
+                    })
             }
+            SnapClass::Points => visible
+                .flat_map(|(i, construction)| {
+                    construction
+                        .shape
+                        .defining_points()
+                        .into_iter()
+                        .map(move |pos| (i, pos))
+                })
+                .filter(|(_, pos)| !self.points.contains(pos))
+                .min_by(closest)
+                .map(|(i, pos)| Snap {
+                    pos,
+                    kind: SnapKind::Point(i),
+                }),
+            SnapClass::Curves => visible
+                .filter_map(|(i, construction)| Some((i, construction.shape.project(mouse_pos)?)))
+                .min_by(closest)
+                .map(|(i, pos)| Snap {
+                    pos,
+                    kind: SnapKind::Curve(i),
+                }),
+            SnapClass::Grid => self.view.grid?.nearest(mouse_pos).map(|pos| Snap {
+                pos,
+                kind: SnapKind::Grid,
+            }),
         }
+    }
 
-        ui.points(
-            Points::new(
-                self.points
-                    .iter()
-                    .map(|point| [point.x as f64, point.y as f64])
-                    .collect::<Vec<[f64; 2]>>(),
-            )
-            .color(self.config.point_color),
-        );
+    /// Finds the closest intersection or labelled point that is not in `ignore`.
+    pub fn closest_snap_target(&self, mouse_pos: Pos, ignore: &[Pos]) -> Option<Pos> {
+        let intersections = self
+            .visible_constructions()
+            .flat_map(|construction| construction.intersections.iter());
+
+        intersections
+            .chain(self.labelled_points.iter().map(|point| &point.pos))
+            .copied()
+            .filter(|point| !ignore.contains(point))
+            .min_by(|a, b| {
+                a.distance_sq(mouse_pos)
+                    .total_cmp(&b.distance_sq(mouse_pos))
+            })
     }
 
-    fn get_snap_pos(&self, mouse_pos: Pos2, snap_radius: f32) -> Pos2 {
-        let mut snap_pos = self
-            .closest_intersection(mouse_pos, &self.points)
-            .unwrap_or(mouse_pos);
+    pub fn visible_constructions(&self) -> impl Iterator<Item = &Construction> {
+        self.constructions
+            .iter()
+            .filter(|construction| self.is_visible(construction))
+    }
 
-        if snap_pos.distance_sq(mouse_pos) > snap_radius * snap_radius {
-            snap_pos = mouse_pos;
-        }
+    /// Whether a construction is drawn: it is not hidden and neither is its layer.
+    pub fn is_visible(&self, construction: &Construction) -> bool {
+        !construction.hidden && self.layer_visible(&construction.layer)
+    }
 
-        snap_pos
+    pub fn layer_visible(&self, name: &str) -> bool {
+        self.layers
+            .iter()
+            .find(|layer| layer.name == name)
+            .is_none_or(|layer| layer.visible)
     }
 
-    pub fn closest_intersection(&self, mouse_pos: Pos2, ignore: &[Pos2]) -> Option<Pos2> {
-        let mut closest: Option<Pos2> = None;
+    /// Makes the layer `offset` places after the current one current, wrapping around.
+    pub fn switch_layer(&mut self, offset: isize) {
+        let count = self.layers.len() as isize;
 
-        for construction in &self.constructions {
-            for intersection in construction
-                .intersections
-                .iter()
-                .filter(|i| !ignore.contains(&i.to_pos2()))
-            {
-                if let Some(c) = closest {
-                    if intersection.to_pos2().distance_sq(mouse_pos) < c.distance_sq(mouse_pos) {
-                        closest = Some(intersection.to_pos2());
-                    }
-                } else {
-                    closest = Some(intersection.to_pos2());
-                }
-            }
+        if count == 0 {
+            return;
         }
 
-        closest
+        let current = self
+            .layers
+            .iter()
+            .position(|layer| layer.name == self.current_layer)
+            .unwrap_or(0) as isize;
+
+        self.current_layer = self.layers[(current + offset).rem_euclid(count) as usize]
+            .name
+            .clone();
+    }
+
+    pub fn current_layer_mut(&mut self) -> Option<&mut Layer> {
+        self.layers
+            .iter_mut()
+            .find(|layer| layer.name == self.current_layer)
+    }
+
+    pub fn add_construction(&mut self, construction: Construction) {
+        self.push_construction(construction);
+
+        if let Some(construction) = self.constructions.last() {
+            self.record(Command::Add(construction.clone()));
+        }
     }
 
-    pub fn add_construction(&mut self, mut construction: Construction) {
+    /// Adds a construction without recording it, for changes that are undone as a whole.
+    fn push_construction(&mut self, mut construction: Construction) {
+        if !self
+            .layers
+            .iter()
+            .any(|layer| layer.name == construction.layer)
+        {
+            self.layers.push(Layer::new(&construction.layer));
+        }
+
         for other in self.constructions.iter() {
             construction
                 .intersections
@@ -192,20 +367,168 @@ impl Engine {
         }
 
         self.constructions.push(construction);
+        self.update_exact(self.constructions.len() - 1);
+        self.update_analysis();
     }
 
-    pub fn click(&mut self, point: PlotPoint) {
-        self.points
-            .push(self.get_snap_pos(point.to_pos2(), self.snap_radius));
+    /// Adds a change to the undo stack. Anything that was undone can no longer be redone.
+    fn record(&mut self, command: Command) {
+        self.undo_stack.push(command);
+        self.redo_stack.clear();
+    }
+
+    /// Works out the intersections of every construction with those before it again.
+    fn update_intersections(&mut self) {
+        for i in 0..self.constructions.len() {
+            let (earlier, rest) = self.constructions.split_at_mut(i);
+            let construction = &mut rest[0];
+
+            construction.intersections = earlier
+                .iter()
+                .flat_map(|other| construction.shape.intersections(&other.shape))
+                .collect();
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            constructions: self.constructions.clone(),
+            measurements: self.measurements.clone(),
+            labelled_points: self.labelled_points.clone(),
+        }
+    }
+
+    /// Swaps in a snapshot, whose constructions already have their intersections.
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.constructions = snapshot.constructions;
+        self.measurements = snapshot.measurements;
+        self.labelled_points = snapshot.labelled_points;
+
+        let len = self.constructions.len();
+        self.selection.retain(|&i| i < len);
+
+        self.set_exact(self.exact.is_some());
+        self.update_analysis();
+    }
+
+    /// Turns the property analyser on or off.
+    pub fn set_analysis(&mut self, analysis: bool) {
+        self.analysis = analysis.then(Analysis::default);
+        self.update_analysis();
+    }
+
+    /// Scans the figure again, if the analyser is on.
+    pub fn update_analysis(&mut self) {
+        if let Some(analysis) = &mut self.analysis {
+            analysis.run(&self.constructions, &self.labelled_points);
+        }
+    }
+
+    /// Turns exact arithmetic on or off. Turning it on works out every point and construction
+    /// again, which can be slow for large documents.
+    pub fn set_exact(&mut self, exact: bool) {
+        self.exact = exact.then(ExactContext::new);
+        self.update_intersections();
+
+        if let Some(context) = &mut self.exact {
+            for point in &self.labelled_points {
+                if let Ok(pos) = ExactPos::from_pos(point.pos) {
+                    context.add_point(pos);
+                }
+            }
+        }
+
+        for i in 0..self.constructions.len() {
+            self.update_exact(i);
+        }
+    }
+
+    /// Works out the construction at `index` exactly, and replaces its intersections with the
+    /// exact ones where they could be found.
+    fn update_exact(&mut self, index: usize) {
+        let Some(context) = &mut self.exact else {
+            return;
+        };
+
+        let exact = context.add(index, &self.constructions[index].shape);
+        let (earlier, rest) = self.constructions.split_at_mut(index);
+        let construction = &mut rest[0];
+
+        construction.intersections = earlier
+            .iter()
+            .zip(exact)
+            .flat_map(|(other, exact)| {
+                exact.unwrap_or_else(|| construction.shape.intersections(&other.shape))
+            })
+            .collect();
+    }
+
+    /// The exact form of the point at `pos`, if exact arithmetic is on and it is a known point.
+    pub fn exact_point(&self, pos: Pos) -> Option<&ExactPos> {
+        self.exact.as_ref()?.point(pos)
+    }
+
+    /// Describes the construction at `index`, in exact form if it is known.
+    pub fn describe(&self, index: usize) -> String {
+        let construction = &self.constructions[index];
+
+        let Some(shape) = self.exact.as_ref().and_then(|context| context.shape(index)) else {
+            return construction.to_string();
+        };
+
+        match &construction.label {
+            Some(label) => format!("{} = {}", label, shape),
+            None => shape.to_string(),
+        }
+    }
+
+    pub fn click(&mut self, point: Pos) {
+        if self.playback.is_some() {
+            return;
+        }
+
+        if let Some(kind) = self.current_measure {
+            return self.measure_click(kind, point);
+        }
+
+        if let Some(kind) = self.current_transform {
+            return self.transform_click(kind, point);
+        }
+
+        if self.current_locus {
+            return self.locus_click(point);
+        }
+
+        let snap = self.snap(point, self.snap_distance());
+
+        self.points.push(snap.map_or(point, |snap| snap.pos));
+        self.inputs.push(match snap {
+            Some(snap) => snap.input(&self.constructions),
+            None => Input::Free { pos: point },
+        });
 
         if self.points.len() as u8 == self.current_tool.num_points() {
             let shape = self.current_tool.get_shape(&self.points);
 
+            // As when five points do not fix a conic, or a point lies between an ellipse's foci
+            if matches!(&shape, Shape::Conic(conic) if conic.kind() == ConicKind::Degenerate) {
+                self.clear_points();
+                return;
+            }
+
             let construction = Construction {
                 shape,
                 layer: self.current_layer.to_owned(),
-                color: self.current_color.into(),
+                color: self.current_color,
                 width: self.current_width,
+                label: None,
+                definition: Some(Definition {
+                    tool: self.current_tool.name().to_owned(),
+                    inputs: std::mem::take(&mut self.inputs),
+                }),
+                hidden: false,
+                caption: None,
+                cost: self.current_tool.cost(),
                 intersections: Vec::new(),
             };
 
@@ -214,51 +537,800 @@ impl Engine {
         }
     }
 
+    fn measure_click(&mut self, kind: MeasureKind, point: Pos) {
+        let done = match kind.num_points() {
+            Some(n) => self.points.len() + 1 == n,
+            // Polygons are finished by clicking near their first vertex again, which snapping
+            // skips as it has already been clicked
+            None => {
+                self.points.len() >= 3
+                    && self.points.first().is_some_and(|first| {
+                        first.distance_sq(point) <= self.snap_distance() * self.snap_distance()
+                    })
+            }
+        };
+
+        let snap = self.snap(point, self.snap_distance());
+        let point = snap.map_or(point, |snap| snap.pos);
+        let input = match snap {
+            Some(snap) => snap.input(&self.constructions),
+            None => Input::Free { pos: point },
+        };
+
+        if !done || kind.num_points().is_some() {
+            self.points.push(point);
+            self.inputs.push(input);
+        }
+
+        if !done {
+            return;
+        }
+
+        if let Some(measurement) = Measurement::new(kind, &self.inputs, &self.constructions) {
+            let before = self.snapshot();
+            self.measurements.push(measurement);
+
+            self.record(Command::Replace {
+                before,
+                after: self.snapshot(),
+            });
+        }
+
+        self.clear_points();
+    }
+
+    /// Removes the measurement at `index`, as a step that can be undone.
+    pub fn remove_measurement(&mut self, index: usize) {
+        if index >= self.measurements.len() {
+            return;
+        }
+
+        let before = self.snapshot();
+        self.measurements.remove(index);
+
+        self.record(Command::Replace {
+            before,
+            after: self.snapshot(),
+        });
+    }
+
+    fn transform_click(&mut self, kind: TransformKind, point: Pos) {
+        self.points
+            .push(self.get_snap_pos(point, self.snap_distance()));
+
+        if self.points.len() < kind.num_points() {
+            return;
+        }
+
+        // Only kept for applying again if it made something
+        if let Some(transform) = Transform::from_points(kind, &self.points) {
+            if self.apply_transform(&transform) {
+                self.transforms.push(transform);
+            }
+        }
+
+        self.points.clear();
+    }
+
+    fn locus_click(&mut self, point: Pos) {
+        let snap = self.snap(point, self.snap_distance());
+
+        // The path is picked by clicking on it, so that click is not snapped
+        let point = match (self.points.len(), snap) {
+            (1, _) | (_, None) => point,
+            (_, Some(snap)) => snap.pos,
+        };
+
+        // Only a free point that something was made from changes anything when it moves
+        if self.points.is_empty() && !self.is_driver(point) {
+            return;
+        }
+
+        self.points.push(point);
+
+        if self.points.len() < locus::INSTRUCTIONS.len() {
+            return;
+        }
+
+        let (driver, path, traced) = (self.points[0], self.points[1], self.points[2]);
+        self.points.clear();
+
+        let Some(on) = self.closest_construction(path) else {
+            return;
+        };
+
+        let traced = snap.map_or(Input::Free { pos: traced }, |snap| {
+            snap.input(&self.constructions)
+        });
+        let path = self.constructions[on].shape.clone();
+
+        if let Some(locus) = LocusData::trace(&self.constructions, driver, &path, &traced) {
+            self.add_construction(Construction {
+                shape: Shape::Locus(locus),
+                layer: self.current_layer.to_owned(),
+                color: self.current_color,
+                width: self.current_width,
+                label: None,
+                definition: Some(Definition {
+                    tool: locus::TOOL.to_owned(),
+                    inputs: vec![
+                        Input::Free { pos: driver },
+                        Input::OnCurve { on, pos: driver },
+                        traced,
+                    ],
+                }),
+                hidden: false,
+                caption: None,
+                cost: 0,
+                intersections: Vec::new(),
+            });
+        }
+    }
+
+    /// Adds a transformed copy of each selected construction, then selects the copies so
+    /// that applying the transform again continues the pattern. The copies are undone as one
+    /// step. Returns whether anything was transformed.
+    pub fn apply_transform(&mut self, transform: &Transform) -> bool {
+        let selected: Vec<Construction> = self
+            .selection
+            .iter()
+            .filter_map(|&i| self.constructions.get(i))
+            .filter_map(|construction| {
+                Some(Construction {
+                    shape: transform.apply(&construction.shape)?,
+                    label: None,
+                    definition: None,
+                    hidden: false,
+                    caption: None,
+                    cost: 0,
+                    intersections: Vec::new(),
+                    ..construction.clone()
+                })
+            })
+            .collect();
+
+        // Labelled points on the selection are carried along, primed
+        let carried: Vec<&Point> = self
+            .labelled_points
+            .iter()
+            .filter(|point| {
+                self.selection.iter().any(|&i| {
+                    self.constructions
+                        .get(i)
+                        .is_some_and(|construction| construction.shape.distance(point.pos) < 1e-4)
+                })
+            })
+            .filter(|point| transform.maps(point.pos))
+            .collect();
+
+        let mut points: Vec<Point> = Vec::new();
+
+        for point in carried {
+            let mut label = format!("{}'", point.label);
+
+            while self
+                .labelled_points
+                .iter()
+                .chain(&points)
+                .any(|p| p.label == label)
+            {
+                label.push('\'');
+            }
+
+            points.push(Point {
+                label,
+                pos: transform.apply_point(point.pos),
+            });
+        }
+
+        if selected.is_empty() && points.is_empty() {
+            return false;
+        }
+
+        let before = self.snapshot();
+        self.selection.clear();
+
+        for point in points {
+            if let (Some(context), Ok(pos)) = (&mut self.exact, ExactPos::from_pos(point.pos)) {
+                context.add_point(pos);
+            }
+
+            self.labelled_points.push(point);
+        }
+
+        for construction in selected {
+            self.selection.push(self.constructions.len());
+            self.push_construction(construction);
+        }
+
+        self.record(Command::Replace {
+            before,
+            after: self.snapshot(),
+        });
+
+        true
+    }
+
+    /// Selects every construction on a layer, so that a transform applies to the whole layer.
+    pub fn select_layer(&mut self, name: &str) {
+        for (i, construction) in self.constructions.iter().enumerate() {
+            if construction.layer == name && !self.selection.contains(&i) {
+                self.selection.push(i);
+            }
+        }
+    }
+
+    /// What the selection would become if `mouse` were the last point of the current transform.
+    pub fn transform_preview(&self, mouse: Pos) -> Vec<Shape> {
+        let Some(kind) = self.current_transform else {
+            return Vec::new();
+        };
+
+        if self.points.len() + 1 != kind.num_points() {
+            return Vec::new();
+        }
+
+        let mut points = self.points.clone();
+        points.push(mouse);
+
+        let Some(transform) = Transform::from_points(kind, &points) else {
+            return Vec::new();
+        };
+
+        self.selection
+            .iter()
+            .filter_map(|&i| self.constructions.get(i))
+            .filter_map(|construction| transform.apply(&construction.shape))
+            .collect()
+    }
+
+    /// Selects the visible construction closest to `point` within the snap radius, or
+    /// deselects it if it was already selected.
+    pub fn toggle_selection(&mut self, point: Pos) {
+        if let Some(i) = self.closest_construction(point) {
+            match self.selection.iter().position(|&selected| selected == i) {
+                Some(n) => {
+                    self.selection.remove(n);
+                }
+                None => self.selection.push(i),
+            }
+        }
+    }
+
+    /// The visible construction closest to `point`, if one is within the snap radius.
+    fn closest_construction(&self, point: Pos) -> Option<usize> {
+        self.constructions
+            .iter()
+            .enumerate()
+            .filter(|(_, construction)| self.is_visible(construction))
+            .map(|(i, construction)| (i, construction.shape.distance(point)))
+            .filter(|(_, distance)| *distance <= self.snap_distance())
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    /// Instructions for the next click with the current tool, measurement, transform or locus.
+    pub fn instruction(&self) -> &str {
+        let instructions = match (self.current_measure, self.current_transform) {
+            (Some(kind), _) => kind.instructions(),
+            (None, Some(kind)) => kind.instructions(),
+            (None, None) if self.current_locus => locus::INSTRUCTIONS.to_vec(),
+            (None, None) => self.current_tool.instructions(),
+        };
+
+        instructions
+            .get(self.points.len())
+            .or(instructions.last())
+            .unwrap_or(&"none")
+    }
+
     pub fn clear_points(&mut self) {
         self.points.clear();
+        self.inputs.clear();
     }
 
     pub fn clear(&mut self) {
-        self.redo_stack
-            .push(RedoFrame::Group(self.constructions.clone()));
+        if !self.constructions.is_empty() {
+            self.record(Command::Replace {
+                before: self.snapshot(),
+                after: Snapshot {
+                    constructions: Vec::new(),
+                    measurements: Vec::new(),
+                    labelled_points: Vec::new(),
+                },
+            });
+        }
+
         self.constructions.clear();
-        self.points.clear();
+        self.measurements.clear();
+        self.transforms.clear();
+        self.selection.clear();
+        self.clear_points();
+        self.labelled_points.clear();
+        self.puzzle = None;
+        self.drag = None;
+        self.update_analysis();
     }
 
     pub fn undo(&mut self) {
-        if let Some(construction) = self.constructions.pop() {
-            self.redo_stack.push(RedoFrame::Single(construction));
+        if !self.can_undo() {
+            return;
+        }
+
+        match self.undo_stack.pop() {
+            Some(Command::Add(_)) => {
+                if let Some(construction) = self.constructions.pop() {
+                    self.redo_stack.push(Command::Add(construction));
+                }
+
+                let len = self.constructions.len();
+                let map = |i: usize| (i < len).then_some(i);
+
+                self.measurements = std::mem::take(&mut self.measurements)
+                    .into_iter()
+                    .filter_map(|measurement| measurement.reindex(map))
+                    .collect();
+                self.selection.retain(|&i| i < len);
+
+                self.update_analysis();
+            }
+            Some(Command::Replace { before, after }) => {
+                self.restore(before.clone());
+                self.redo_stack.push(Command::Replace { before, after });
+            }
+            None => {}
         }
     }
 
     pub fn redo(&mut self) {
-        if let Some(redo_group) = self.redo_stack.pop() {
-            for construction in redo_group.constructions() {
-                self.constructions.push(construction);
+        match self.redo_stack.pop() {
+            Some(Command::Add(construction)) => {
+                self.constructions.push(construction.clone());
+                self.update_exact(self.constructions.len() - 1);
+                self.undo_stack.push(Command::Add(construction));
+
+                self.update_analysis();
             }
+            Some(Command::Replace { before, after }) => {
+                self.restore(after.clone());
+                self.undo_stack.push(Command::Replace { before, after });
+            }
+            None => {}
         }
     }
 
+    /// Whether there is a change to undo. The starting objects of a puzzle cannot be undone.
     pub fn can_undo(&self) -> bool {
-        !self.constructions.is_empty()
+        match self.undo_stack.last() {
+            Some(Command::Add(_)) => {
+                self.constructions.len() > self.puzzle.as_ref().map_or(0, |puzzle| puzzle.given)
+            }
+            Some(Command::Replace { .. }) => true,
+            None => false,
+        }
     }
 
     pub fn can_redo(&self) -> bool {
         !self.redo_stack.is_empty()
     }
 
+    /// Whether the construction at `index` can be deleted or moved. The starting objects of a
+    /// puzzle cannot.
+    pub fn can_edit(&self, index: usize) -> bool {
+        index >= self.puzzle.as_ref().map_or(0, |puzzle| puzzle.given)
+    }
+
+    /// Deletes the construction at `index`. Constructions made from it keep their shape but
+    /// no longer follow it when points are dragged.
+    pub fn delete_construction(&mut self, index: usize) {
+        if index >= self.constructions.len() || !self.can_edit(index) {
+            return;
+        }
+
+        let mut order: Vec<usize> = (0..self.constructions.len()).collect();
+        order.remove(index);
+
+        self.reorder(order);
+    }
+
+    /// Moves the construction at `from` to `to` in the list, which is the order they are
+    /// drawn in.
+    pub fn move_construction(&mut self, from: usize, to: usize) {
+        let len = self.constructions.len();
+
+        if from >= len || to >= len || from == to || !self.can_edit(from) || !self.can_edit(to) {
+            return;
+        }
+
+        let mut order: Vec<usize> = (0..len).collect();
+        let moved = order.remove(from);
+        order.insert(to, moved);
+
+        self.reorder(order);
+    }
+
+    /// Rebuilds the construction list from the old indices in `order`, leaving out any that
+    /// are missing, and updates everything that refers to constructions by index.
+    fn reorder(&mut self, order: Vec<usize>) {
+        let before = self.snapshot();
+        let map = |old: usize| order.iter().position(|&i| i == old);
+
+        self.constructions = order
+            .iter()
+            .enumerate()
+            .map(|(new, &old)| {
+                let mut construction = before.constructions[old].clone();
+
+                if let Some(definition) = &mut construction.definition {
+                    definition.reindex(new, map);
+                }
+
+                construction
+            })
+            .collect();
+
+        self.measurements = std::mem::take(&mut self.measurements)
+            .into_iter()
+            .filter_map(|measurement| measurement.reindex(map))
+            .collect();
+        self.selection = self.selection.iter().filter_map(|&i| map(i)).collect();
+
+        self.update_intersections();
+        self.record(Command::Replace {
+            before,
+            after: self.snapshot(),
+        });
+
+        self.set_exact(self.exact.is_some());
+        self.update_analysis();
+    }
+
+    /// The point a drag starting at `pos` would pick up: a point placed freely or on a curve
+    /// while making a visible construction, within the snap radius.
+    pub fn drag_target(&self, pos: Pos) -> Option<Pos> {
+        if self.playback.is_some() {
+            return None;
+        }
+
+        self.draggable_inputs()
+            .map(|input| input.pos())
+            .filter(|point| point.distance(pos) <= self.snap_distance())
+            .min_by(|a, b| a.distance_sq(pos).total_cmp(&b.distance_sq(pos)))
+    }
+
+    /// The inputs of the visible constructions, except loci whose driver and traced point
+    /// are only copies of points other constructions were made from.
+    fn draggable_inputs(&self) -> impl Iterator<Item = &Input> {
+        self.visible_constructions()
+            .filter_map(|construction| construction.definition.as_ref())
+            .filter(|definition| definition.tool != locus::TOOL)
+            .flat_map(|definition| definition.inputs.iter())
+            .filter(|input| matches!(input, Input::Free { .. } | Input::OnCurve { .. }))
+    }
+
+    /// Whether `pos` is a point placed freely while making a construction, which can drive a
+    /// locus.
+    fn is_driver(&self, pos: Pos) -> bool {
+        self.draggable_inputs()
+            .any(|input| matches!(input, Input::Free { pos: free } if *free == pos))
+    }
+
+    /// Picks up the point nearest `pos`, returning whether there was one.
+    pub fn start_drag(&mut self, pos: Pos) -> bool {
+        let Some(target) = self.drag_target(pos) else {
+            return false;
+        };
+
+        let on = self.draggable_inputs().find_map(|input| match input {
+            Input::OnCurve { on, pos } if *pos == target => Some(*on),
+            _ => None,
+        });
+
+        self.drag = Some(Drag {
+            before: self.snapshot(),
+            pos: target,
+            offset: target - pos,
+            on,
+        });
+
+        true
+    }
+
+    pub fn dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Moves the point being dragged towards `pos`, making everything that depends on it again.
+    /// It stays where it was if something could no longer be made there.
+    pub fn drag_to(&mut self, pos: Pos) {
+        let Some(drag) = &self.drag else {
+            return;
+        };
+
+        let pos = pos + drag.offset;
+        let target = match drag.on.and_then(|on| self.constructions.get(on)) {
+            Some(construction) => construction.shape.project(pos),
+            None => Some(pos),
+        };
+
+        let Some(target) = target.filter(|target| *target != drag.pos) else {
+            return;
+        };
+
+        if let Some(moved) = definition::move_point(&self.constructions, drag.pos, target) {
+            // Points that no longer exist are left where they were, as the readout is kept
+            self.measurements = std::mem::take(&mut self.measurements)
+                .into_iter()
+                .map(|measurement| {
+                    measurement
+                        .map_inputs(|input| input.follow(&moved, drag.pos, target).unwrap_or(input))
+                })
+                .collect();
+            self.constructions = moved;
+
+            for point in &mut self.labelled_points {
+                if point.pos == drag.pos {
+                    point.pos = target;
+                }
+            }
+
+            self.update_intersections();
+
+            if let Some(drag) = &mut self.drag {
+                drag.pos = target;
+            }
+        }
+    }
+
+    /// Puts down the point being dragged, adding the whole drag to the undo stack.
+    pub fn end_drag(&mut self) {
+        let Some(drag) = self.drag.take() else {
+            return;
+        };
+
+        let moved = drag
+            .before
+            .constructions
+            .iter()
+            .zip(&self.constructions)
+            .any(|(before, after)| before.definition != after.definition);
+
+        if moved {
+            self.record(Command::Replace {
+                before: drag.before,
+                after: self.snapshot(),
+            });
+
+            self.set_exact(self.exact.is_some());
+            self.update_analysis();
+        }
+    }
+
     pub fn stats(&self) -> EngineStats {
         EngineStats::from(self)
     }
 
-    pub fn load(&mut self, data: &str) -> Result<(), serde_yaml::Error> {
+    /// Collects the current state into a document that can be saved.
+    pub fn document(&self) -> Document {
+        Document {
+            metadata: self.metadata.clone(),
+            view: self.view.clone(),
+            layers: self.layers.clone(),
+            points: self.labelled_points.clone(),
+            constructions: self.constructions.clone(),
+            measurements: self.measurements.clone(),
+            transforms: self.transforms.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Replaces the current state with a document, recomputing intersections.
+    pub fn set_document(&mut self, document: Document) {
         self.clear();
-        self.constructions = serde_yaml::from_str::<Vec<Construction>>(data)?;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+
+        self.metadata = document.metadata;
+        self.view = document.view;
+        self.layers = document.layers;
+        self.labelled_points = document.points;
+        self.measurements = document.measurements;
+        self.transforms = document.transforms;
+
+        let exact = self.exact.take().is_some();
+        let analysis = self.analysis.take().is_some();
+
+        for construction in document.constructions {
+            self.add_construction(construction);
+        }
+
+        self.set_exact(exact);
+        self.set_analysis(analysis);
+
+        if self.view.camera.is_none() {
+            self.view.camera = self.bounds().map(|(min, max)| {
+                let margin = (max - min) * 0.1;
+
+                Camera {
+                    min: min - margin,
+                    max: max + margin,
+                }
+            });
+        }
+
+        self.camera_changed = true;
+    }
+
+    /// The smallest box containing every construction and labelled point.
+    pub fn bounds(&self) -> Option<(Pos, Pos)> {
+        let boxes = self
+            .constructions
+            .iter()
+            .map(|construction| construction.shape.bounds())
+            .chain(
+                self.labelled_points
+                    .iter()
+                    .map(|point| (point.pos, point.pos)),
+            );
+
+        boxes.reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+    }
+
+    /// Returns the camera to move the view to, if it was changed by loading a document.
+    pub fn take_camera(&mut self) -> Option<Camera> {
+        if !std::mem::take(&mut self.camera_changed) {
+            return None;
+        }
+
+        self.view.camera.clone()
+    }
+
+    pub fn update_camera(&mut self, camera: Camera) {
+        self.view.camera = Some(camera);
+    }
+
+    /// Checks that every shape is well formed and that every intersection lies on both of
+    /// the shapes it was computed from, returning a description of each problem found.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for (i, construction) in self.constructions.iter().enumerate() {
+            let shape = &construction.shape;
+            let (min, max) = shape.bounds();
+
+            if !(min.x.is_finite() && min.y.is_finite() && max.x.is_finite() && max.y.is_finite()) {
+                problems.push(format!("construction {} is not finite: {}", i, shape));
+                continue;
+            }
+
+            let degenerate = match shape {
+                shapes::Shape::Circle(circle) => circle.r <= 0.0,
+                shapes::Shape::Arc(arc) => arc.r <= 0.0,
+                shapes::Shape::Line(line) => line.p1 == line.p2,
+                shapes::Shape::Segment(segment) => segment.p1 == segment.p2,
+                shapes::Shape::Conic(conic) => conic.kind() == shapes::ConicKind::Degenerate,
+                shapes::Shape::Locus(locus) => locus.pieces.is_empty(),
+            };
+
+            if degenerate {
+                problems.push(format!("construction {} is degenerate: {}", i, shape));
+            }
+        }
+
+        for (i, a) in self.constructions.iter().enumerate() {
+            for (j, b) in self.constructions.iter().enumerate().skip(i + 1) {
+                for point in a.shape.intersections(&b.shape) {
+                    if !(point.x.is_finite() && point.y.is_finite()) {
+                        problems.push(format!(
+                            "intersection of constructions {} and {} is not finite",
+                            i, j
+                        ));
+                        continue;
+                    }
+
+                    let tolerance = 1e-3 * (1.0 + point.length());
+
+                    for (k, shape) in [(i, &a.shape), (j, &b.shape)] {
+                        let distance = shape.distance(point);
+
+                        if distance > tolerance {
+                            problems.push(format!(
+                                "intersection ({}, {}) of constructions {} and {} is {} away from construction {}",
+                                point.x, point.y, i, j, distance, k
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Turns the shapes of a script into constructions using the current style.
+    pub fn script_constructions(&self, script: &script::Script) -> Vec<Construction> {
+        script
+            .shapes
+            .iter()
+            .map(|(label, shape)| Construction {
+                shape: shape.clone(),
+                layer: self.current_layer.clone(),
+                color: self.current_color,
+                width: self.current_width,
+                label: label.clone(),
+                definition: None,
+                hidden: false,
+                caption: None,
+                cost: 0,
+                intersections: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Adds a script's points and shapes to the current document.
+    pub fn run_script(&mut self, script: &script::Script) {
+        for point in &script.points {
+            self.labelled_points.retain(|p| p.label != point.label);
+            self.labelled_points.push(point.clone());
+
+            if let (Some(context), Ok(pos)) = (&mut self.exact, ExactPos::from_pos(point.pos)) {
+                context.add_point(pos);
+            }
+        }
+
+        for construction in self.script_constructions(script) {
+            self.add_construction(construction);
+        }
+    }
+
+    /// Replaces the current state with the starting objects of a puzzle.
+    pub fn start_puzzle(&mut self, puzzle: Puzzle) {
+        self.set_document(puzzle.document(self.config.text_color));
+
+        self.current_layer = self
+            .layers
+            .iter()
+            .find(|layer| layer.name != puzzle::GIVEN_LAYER)
+            .map_or(self.current_layer.clone(), |layer| layer.name.clone());
+
+        self.puzzle = Some(ActivePuzzle {
+            given: self.constructions.len(),
+            puzzle,
+        });
+    }
+
+    pub fn restart_puzzle(&mut self) {
+        if let Some(active) = self.puzzle.take() {
+            self.start_puzzle(active.puzzle);
+        }
+    }
+
+    /// Moves made and stars earned in the current puzzle, checking it against the targets.
+    pub fn puzzle_status(&self) -> Option<PuzzleStatus> {
+        self.puzzle
+            .as_ref()
+            .map(|active| active.puzzle.status(&self.constructions[active.given..]))
+    }
+
+    pub fn load(&mut self, data: &str, format: Format) -> Result<(), DocumentError> {
+        self.set_document(Document::parse(data, format)?);
 
         Ok(())
     }
 
-    pub fn save(&self) -> Result<String, serde_yaml::Error> {
-        serde_yaml::to_string(&self.constructions)
+    pub fn save(&self, format: Format) -> Result<String, DocumentError> {
+        self.document().write(format)
+    }
+
+    pub fn import_ggb(&mut self, data: &[u8]) -> Result<ggb::ImportSummary, ggb::GgbError> {
+        let (document, summary) = ggb::import(data)?;
+
+        self.set_document(document);
+
+        Ok(summary)
+    }
+
+    pub fn export_ggb(&self) -> Result<Vec<u8>, ggb::GgbError> {
+        ggb::export(&self.document())
     }
 }
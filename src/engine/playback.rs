@@ -0,0 +1,235 @@
+use std::f32::consts::TAU;
+
+use super::{
+    definition::Input,
+    export::arc_span,
+    locus::LocusData,
+    measure::Measurement,
+    pos::Pos,
+    shapes::{ArcData, Construction, SegmentData, Shape},
+    utils, Engine,
+};
+
+/// Replays the constructions one at a time in the order they were made, drawing each as it
+/// would be drawn by hand.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Playback {
+    /// The construction being drawn. Those before it are drawn whole, and it is one past the
+    /// last when playback has finished.
+    pub step: usize,
+    /// How much of the current construction is drawn, from 0 to 1.
+    pub progress: f32,
+    pub playing: bool,
+    /// Constructions drawn per second while playing.
+    pub speed: f32,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            step: 0,
+            progress: 0.0,
+            playing: false,
+            speed: 1.0,
+        }
+    }
+}
+
+impl Playback {
+    /// Moves on by `seconds` of playing time, stopping after the last of `len` constructions.
+    pub fn advance(&mut self, seconds: f32, len: usize) {
+        if !self.playing {
+            return;
+        }
+
+        self.progress += seconds * self.speed;
+
+        while self.progress >= 1.0 && self.step < len {
+            self.progress -= 1.0;
+            self.step += 1;
+        }
+
+        if self.step >= len {
+            self.step = len;
+            self.progress = 0.0;
+            self.playing = false;
+        }
+    }
+
+    /// Finishes the current construction, or moves on to the next if it is not started.
+    pub fn next(&mut self, len: usize) {
+        self.step = (self.step + 1).min(len);
+        self.progress = 0.0;
+    }
+
+    /// Rubs out the current construction, or the one before it if it is not started.
+    pub fn previous(&mut self) {
+        if self.progress == 0.0 {
+            self.step = self.step.saturating_sub(1);
+        }
+
+        self.progress = 0.0;
+    }
+
+    pub fn finished(&self, len: usize) -> bool {
+        self.step >= len
+    }
+
+    /// Starts playing, from the beginning if playback has finished.
+    pub fn play(&mut self, len: usize) {
+        if self.finished(len) {
+            self.step = 0;
+            self.progress = 0.0;
+        }
+
+        self.playing = true;
+    }
+
+    /// The caption of the construction being drawn, or of the last one once all are drawn.
+    pub fn caption<'a>(&self, constructions: &'a [Construction]) -> Option<&'a str> {
+        constructions
+            .get(self.step.min(constructions.len().saturating_sub(1)))?
+            .caption
+            .as_deref()
+    }
+
+    /// The visible constructions as they should be drawn now, with the current one partly
+    /// drawn. Lines grow until they cross the box from `min` to `max`. Only whole
+    /// constructions keep their intersections.
+    pub fn constructions(&self, engine: &Engine, min: Pos, max: Pos) -> Vec<Construction> {
+        engine
+            .constructions
+            .iter()
+            .take(self.step + 1)
+            .enumerate()
+            .filter(|(_, construction)| engine.is_visible(construction))
+            .filter_map(|(i, construction)| {
+                if i < self.step {
+                    return Some(construction.clone());
+                }
+
+                Some(Construction {
+                    shape: partial(construction, self.progress, min, max)?,
+                    intersections: Vec::new(),
+                    ..construction.clone()
+                })
+            })
+            .collect()
+    }
+
+    /// The measurements of constructions that have been drawn whole, and of points on them.
+    /// Every measurement is shown once playback has finished.
+    pub fn measurements<'a>(
+        &self,
+        engine: &'a Engine,
+    ) -> impl Iterator<Item = &'a Measurement> + 'a {
+        let finished = self.finished(engine.constructions.len());
+        let drawn = &engine.constructions[..self.step.min(engine.constructions.len())];
+
+        engine.measurements.iter().filter(move |measurement| {
+            if finished {
+                return true;
+            }
+
+            let step = drawn.len();
+            let circle_drawn = (*measurement)
+                .clone()
+                .reindex(|i| (i < step).then_some(i))
+                .is_some();
+
+            circle_drawn
+                && measurement
+                    .inputs()
+                    .into_iter()
+                    .all(|input| is_drawn(drawn, input))
+        })
+    }
+}
+
+/// Whether the point an input gives is part of the constructions drawn so far: an intersection
+/// of two of them, or a point on one or that one was made from.
+fn is_drawn(drawn: &[Construction], input: &Input) -> bool {
+    match input {
+        Input::Intersection { a, b, .. } => *a < drawn.len() && *b < drawn.len(),
+        Input::OnCurve { on, .. } => *on < drawn.len(),
+        Input::Free { pos } => drawn.iter().any(|construction| {
+            let made_from = construction
+                .definition
+                .as_ref()
+                .is_some_and(|definition| definition.inputs.iter().any(|i| i.pos() == *pos));
+
+            made_from
+                || construction.shape.defining_points().contains(pos)
+                || construction.shape.distance(*pos) <= 1e-5 * (1.0 + pos.length())
+        }),
+    }
+}
+
+/// The part of a construction drawn by `t`, from 0 to 1. Circles are swept round from the
+/// point the compass was opened to, arcs from their start and segments from their first
+/// point. Lines grow both ways from their first point until they leave the box from `min` to
+/// `max`. Conics appear whole at the end, and loci are traced in order.
+pub fn partial(construction: &Construction, t: f32, min: Pos, max: Pos) -> Option<Shape> {
+    if t <= 0.0 {
+        return None;
+    }
+
+    if t >= 1.0 {
+        return Some(construction.shape.clone());
+    }
+
+    Some(match &construction.shape {
+        Shape::Circle(circle) => {
+            let start = construction
+                .definition
+                .as_ref()
+                .and_then(|definition| definition.inputs.get(1))
+                .filter(|input| input.pos() != circle.pos)
+                .map_or(0.0, |input| utils::arc_angle(input.pos(), circle.pos));
+
+            Shape::Arc(ArcData {
+                pos: circle.pos,
+                r: circle.r,
+                start,
+                stop: (start + TAU * t).rem_euclid(TAU),
+            })
+        }
+        Shape::Arc(arc) => Shape::Arc(ArcData {
+            stop: (arc.start + arc_span(arc.start, arc.stop) * t).rem_euclid(TAU),
+            ..arc.clone()
+        }),
+        Shape::Segment(segment) => Shape::Segment(SegmentData {
+            p1: segment.p1,
+            p2: segment.p1 + (segment.p2 - segment.p1) * t,
+        }),
+        Shape::Line(line) => {
+            let reach = [min, Pos::new(min.x, max.y), Pos::new(max.x, min.y), max]
+                .into_iter()
+                .map(|corner| corner.distance(line.p1))
+                .fold(0.0, f32::max);
+            let direction = (line.p2 - line.p1).normalized() * (reach * t);
+
+            Shape::Segment(SegmentData {
+                p1: line.p1 - direction,
+                p2: line.p1 + direction,
+            })
+        }
+        Shape::Conic(_) => return None,
+        Shape::Locus(locus) => {
+            let total: usize = locus.pieces.iter().map(Vec::len).sum();
+            let mut remaining = (total as f32 * t).ceil() as usize;
+
+            Shape::Locus(LocusData {
+                pieces: locus
+                    .pieces
+                    .iter()
+                    .map_while(|piece| {
+                        let take = piece.len().min(remaining);
+                        remaining -= take;
+                        (take > 0).then(|| piece[..take].to_vec())
+                    })
+                    .collect(),
+            })
+        }
+    })
+}
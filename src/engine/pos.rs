@@ -0,0 +1,128 @@
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A point, or the offset between two points, in construction coordinates.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pos {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Pos {
+    pub const ZERO: Pos = Pos { x: 0.0, y: 0.0 };
+
+    pub const fn new(x: f32, y: f32) -> Self {
+        Pos { x, y }
+    }
+
+    /// The unit vector at `angle` radians counter-clockwise from the x axis.
+    pub fn from_angle(angle: f32) -> Self {
+        Pos::new(angle.cos(), angle.sin())
+    }
+
+    pub fn dot(self, other: Pos) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length(self) -> f32 {
+        self.length_sq().sqrt()
+    }
+
+    pub fn length_sq(self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn distance(self, other: Pos) -> f32 {
+        (self - other).length()
+    }
+
+    pub fn distance_sq(self, other: Pos) -> f32 {
+        (self - other).length_sq()
+    }
+
+    pub fn normalized(self) -> Pos {
+        self / self.length()
+    }
+
+    pub fn min(self, other: Pos) -> Pos {
+        Pos::new(self.x.min(other.x), self.y.min(other.y))
+    }
+
+    pub fn max(self, other: Pos) -> Pos {
+        Pos::new(self.x.max(other.x), self.y.max(other.y))
+    }
+
+    pub fn is_finite(self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+}
+
+impl fmt::Debug for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:?}, {:?})", self.x, self.y)
+    }
+}
+
+impl Add for Pos {
+    type Output = Pos;
+
+    fn add(self, other: Pos) -> Pos {
+        Pos::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Pos {
+    fn add_assign(&mut self, other: Pos) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Pos {
+    type Output = Pos;
+
+    fn sub(self, other: Pos) -> Pos {
+        Pos::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl SubAssign for Pos {
+    fn sub_assign(&mut self, other: Pos) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<f32> for Pos {
+    type Output = Pos;
+
+    fn mul(self, factor: f32) -> Pos {
+        Pos::new(self.x * factor, self.y * factor)
+    }
+}
+
+impl Mul<Pos> for f32 {
+    type Output = Pos;
+
+    fn mul(self, pos: Pos) -> Pos {
+        pos * self
+    }
+}
+
+impl Div<f32> for Pos {
+    type Output = Pos;
+
+    fn div(self, divisor: f32) -> Pos {
+        Pos::new(self.x / divisor, self.y / divisor)
+    }
+}
+
+impl Neg for Pos {
+    type Output = Pos;
+
+    fn neg(self) -> Pos {
+        Pos::new(-self.x, -self.y)
+    }
+}
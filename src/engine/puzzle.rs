@@ -0,0 +1,140 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    color::Color,
+    document::{Document, Layer, Metadata, Point},
+    shapes::{Construction, Shape},
+};
+
+/// Layer holding the objects a puzzle starts with.
+pub const GIVEN_LAYER: &str = "Given";
+
+/// A challenge: construct every target shape from the given objects in as few moves as possible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Puzzle {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub points: Vec<Point>,
+    #[serde(default)]
+    pub given: Vec<Shape>,
+    pub targets: Vec<Shape>,
+    pub par: Moves,
+    /// How far a construction may be from a target and still count.
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f32,
+}
+
+fn default_tolerance() -> f32 {
+    1e-3
+}
+
+/// Euclidea style move counts. Every line or circle is one L-move, and each tool costs
+/// some number of E-moves: one for the compass and straight edge, more for macro tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Moves {
+    #[serde(rename = "l")]
+    pub lines: u32,
+    #[serde(rename = "e")]
+    pub elementary: u32,
+}
+
+impl Moves {
+    pub fn of(constructions: &[Construction]) -> Self {
+        Moves {
+            lines: constructions.len() as u32,
+            elementary: constructions
+                .iter()
+                .map(|construction| construction.cost.max(1))
+                .sum(),
+        }
+    }
+}
+
+impl fmt::Display for Moves {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}L {}E", self.lines, self.elementary)
+    }
+}
+
+/// Progress through a puzzle that has been loaded into an engine.
+#[derive(Debug, Clone)]
+pub struct ActivePuzzle {
+    pub puzzle: Puzzle,
+    /// Number of constructions the puzzle started with, which are not counted as moves.
+    pub given: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PuzzleStatus {
+    pub moves: Moves,
+    pub solved: bool,
+    /// Out of three: one for solving, one for each move count within par.
+    pub stars: u8,
+}
+
+impl Puzzle {
+    pub fn parse(data: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(data)
+    }
+
+    /// The starting state, with the given shapes drawn in `color` on their own layer.
+    pub fn document(&self, color: Color) -> Document {
+        Document {
+            metadata: Metadata {
+                title: self.title.clone(),
+                description: self.description.clone(),
+                ..Default::default()
+            },
+            layers: vec![Layer::new(GIVEN_LAYER), Layer::new("Layer 1")],
+            points: self.points.clone(),
+            constructions: self
+                .given
+                .iter()
+                .map(|shape| Construction {
+                    shape: shape.clone(),
+                    layer: GIVEN_LAYER.to_owned(),
+                    color,
+                    width: 1.0,
+                    label: None,
+                    definition: None,
+                    hidden: false,
+                    caption: None,
+                    cost: 0,
+                    intersections: Vec::new(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether every target has been constructed.
+    pub fn solved(&self, constructions: &[Construction]) -> bool {
+        self.targets.iter().all(|target| {
+            constructions
+                .iter()
+                .any(|construction| target.matches(&construction.shape, self.tolerance))
+        })
+    }
+
+    pub fn status(&self, constructions: &[Construction]) -> PuzzleStatus {
+        let moves = Moves::of(constructions);
+        let solved = self.solved(constructions);
+
+        let stars = if solved {
+            1 + (moves.lines <= self.par.lines) as u8
+                + (moves.elementary <= self.par.elementary) as u8
+        } else {
+            0
+        };
+
+        PuzzleStatus {
+            moves,
+            solved,
+            stars,
+        }
+    }
+}
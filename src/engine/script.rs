@@ -0,0 +1,673 @@
+//! A small text language for writing constructions by hand, for example:
+//!
+//! ```text
+//! A = point(0, 0); B = point(1, 0)
+//! c1 = circle(A, B); c2 = circle(B, A)
+//! C = intersect(c1, c2)[0]
+//! seg(A, C)
+//! ```
+//!
+//! Statements are separated by `;` or new lines and `#` starts a comment. Assigning a point
+//! to a name creates a labelled point, and every line, segment, circle, arc or conic becomes
+//! a construction, labelled if it was assigned to a name.
+
+use std::{collections::HashMap, fmt};
+
+use super::{
+    document::{Document, Point},
+    pos::Pos,
+    shapes::{ArcData, CircleData, ConicData, LineData, SegmentData, Shape},
+    utils,
+};
+
+#[derive(Debug, Clone)]
+pub struct ScriptError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// The points and shapes produced by running a script, in order.
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    pub points: Vec<Point>,
+    pub shapes: Vec<(Option<String>, Shape)>,
+}
+
+pub fn compile(source: &str) -> Result<Script, ScriptError> {
+    let tokens = tokenize(source)?;
+
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        names: HashMap::new(),
+        script: Script::default(),
+    };
+
+    parser.program()?;
+
+    Ok(parser.script)
+}
+
+/// Writes a document's labelled points and constructions as a script.
+pub fn to_script(document: &Document) -> String {
+    let mut points: Vec<(Pos, String)> = document
+        .points
+        .iter()
+        .map(|point| (point.pos, point.label.clone()))
+        .collect();
+
+    let mut lines: Vec<String> = points
+        .iter()
+        .map(|(pos, label)| format!("{} = point({}, {})", label, pos.x, pos.y))
+        .collect();
+
+    let mut point_name = |pos: Pos, lines: &mut Vec<String>| -> String {
+        if let Some((_, label)) = points.iter().find(|(p, _)| *p == pos) {
+            return label.clone();
+        }
+
+        let label = (1..)
+            .map(|i| format!("P{}", i))
+            .find(|name| !points.iter().any(|(_, label)| label == name))
+            .unwrap();
+
+        lines.push(format!("{} = point({}, {})", label, pos.x, pos.y));
+        points.push((pos, label.clone()));
+
+        label
+    };
+
+    for construction in &document.constructions {
+        let expression = match &construction.shape {
+            Shape::Circle(circle) => format!(
+                "circle({}, {})",
+                point_name(circle.pos, &mut lines),
+                circle.r
+            ),
+            Shape::Line(line) => format!(
+                "line({}, {})",
+                point_name(line.p1, &mut lines),
+                point_name(line.p2, &mut lines)
+            ),
+            Shape::Segment(segment) => format!(
+                "seg({}, {})",
+                point_name(segment.p1, &mut lines),
+                point_name(segment.p2, &mut lines)
+            ),
+            Shape::Arc(arc) => {
+                let pos = arc.pos;
+                let (start, stop) = arc.endpoints();
+
+                format!(
+                    "arc({}, {}, {})",
+                    point_name(pos, &mut lines),
+                    point_name(start, &mut lines),
+                    point_name(stop, &mut lines)
+                )
+            }
+            Shape::Conic(conic) => format!(
+                "conic({}, {}, {}, {}, {}, {})",
+                conic.a, conic.b, conic.c, conic.d, conic.e, conic.f
+            ),
+            Shape::Locus(_) => {
+                lines.push("# loci cannot be written as scripts".to_owned());
+                continue;
+            }
+        };
+
+        lines.push(match &construction.label {
+            Some(label) => format!("{} = {}", label, expression),
+            None => expression,
+        });
+    }
+
+    lines.join("\n") + "\n"
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(f32),
+    Symbol(char),
+    Separator,
+    End,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    column: usize,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ScriptError> {
+    let mut tokens = Vec::new();
+    let mut depth = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            let error = |message: String| ScriptError {
+                line: i + 1,
+                column: start + 1,
+                message,
+            };
+
+            let kind = match c {
+                '#' => break,
+                c if c.is_whitespace() => continue,
+                c if c.is_alphabetic() || c == '_' => {
+                    let mut end = start + c.len_utf8();
+
+                    while let Some(&(j, c)) = chars.peek() {
+                        if !(c.is_alphanumeric() || c == '_') {
+                            break;
+                        }
+                        end = j + c.len_utf8();
+                        chars.next();
+                    }
+
+                    TokenKind::Ident(line[start..end].to_owned())
+                }
+                c if c.is_ascii_digit() || c == '.' => {
+                    let mut end = start + 1;
+
+                    while let Some(&(j, c)) = chars.peek() {
+                        if !(c.is_ascii_digit() || c == '.') {
+                            break;
+                        }
+                        end = j + 1;
+                        chars.next();
+                    }
+
+                    let text = &line[start..end];
+                    TokenKind::Number(
+                        text.parse()
+                            .map_err(|_| error(format!("invalid number {}", text)))?,
+                    )
+                }
+                ';' => TokenKind::Separator,
+                '(' | '[' => {
+                    depth += 1;
+                    TokenKind::Symbol(c)
+                }
+                ')' | ']' => {
+                    depth -= 1;
+                    TokenKind::Symbol(c)
+                }
+                ',' | '=' | '+' | '-' | '*' | '/' => TokenKind::Symbol(c),
+                c => return Err(error(format!("unexpected character '{}'", c))),
+            };
+
+            tokens.push(Token {
+                kind,
+                line: i + 1,
+                column: start + 1,
+            });
+        }
+
+        // New lines only end statements outside of brackets
+        if depth <= 0 {
+            tokens.push(Token {
+                kind: TokenKind::Separator,
+                line: i + 1,
+                column: line.len() + 1,
+            });
+        }
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        line: source.lines().count().max(1),
+        column: source.lines().last().map_or(1, |line| line.len() + 1),
+    });
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(f32),
+    Point(Pos),
+    Points(Vec<Pos>),
+    Shape(Shape),
+}
+
+impl Value {
+    fn type_name(&self) -> &str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Point(_) => "point",
+            Value::Points(_) => "list of points",
+            Value::Shape(_) => "shape",
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    names: HashMap<String, Value>,
+    script: Script,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    fn error(token: &Token, message: String) -> ScriptError {
+        ScriptError {
+            line: token.line,
+            column: token.column,
+            message,
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<Token, ScriptError> {
+        let token = self.next();
+
+        if token.kind == TokenKind::Symbol(symbol) {
+            Ok(token)
+        } else {
+            Err(Parser::error(
+                &token,
+                format!("expected '{}' but found {}", symbol, describe(&token.kind)),
+            ))
+        }
+    }
+
+    fn program(&mut self) -> Result<(), ScriptError> {
+        loop {
+            match self.peek().kind {
+                TokenKind::End => return Ok(()),
+                TokenKind::Separator => {
+                    self.next();
+                }
+                _ => self.statement()?,
+            }
+        }
+    }
+
+    fn statement(&mut self) -> Result<(), ScriptError> {
+        let is_assignment = matches!(self.peek().kind, TokenKind::Ident(_))
+            && self.tokens[self.position + 1].kind == TokenKind::Symbol('=');
+
+        let name = if is_assignment {
+            let TokenKind::Ident(name) = self.next().kind else {
+                unreachable!()
+            };
+            self.next();
+            Some(name)
+        } else {
+            None
+        };
+
+        let start = self.peek().clone();
+        let value = self.expression()?;
+
+        let end = self.next();
+        if end.kind != TokenKind::Separator && end.kind != TokenKind::End {
+            return Err(Parser::error(
+                &end,
+                format!(
+                    "expected end of statement but found {}",
+                    describe(&end.kind)
+                ),
+            ));
+        }
+
+        match &value {
+            Value::Shape(shape) => self.script.shapes.push((name.clone(), shape.clone())),
+            Value::Point(pos) => {
+                if let Some(name) = &name {
+                    self.script.points.retain(|point| &point.label != name);
+                    self.script.points.push(Point {
+                        label: name.clone(),
+                        pos: *pos,
+                    });
+                }
+            }
+            Value::Number(_) | Value::Points(_) if name.is_none() => {
+                return Err(Parser::error(
+                    &start,
+                    format!("a {} on its own does nothing", value.type_name()),
+                ))
+            }
+            _ => (),
+        }
+
+        if let Some(name) = name {
+            self.names.insert(name, value);
+        }
+
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<Value, ScriptError> {
+        let mut value = self.term()?;
+
+        while let TokenKind::Symbol(op @ ('+' | '-')) = self.peek().kind {
+            let token = self.next();
+            let rhs = self.term()?;
+
+            value = match (value, rhs) {
+                (Value::Number(a), Value::Number(b)) => {
+                    Value::Number(if op == '+' { a + b } else { a - b })
+                }
+                (Value::Point(a), Value::Point(b)) => {
+                    Value::Point(if op == '+' { a + b } else { a - b })
+                }
+                (a, b) => {
+                    return Err(Parser::error(
+                        &token,
+                        format!(
+                            "cannot apply '{}' to {} and {}",
+                            op,
+                            a.type_name(),
+                            b.type_name()
+                        ),
+                    ))
+                }
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<Value, ScriptError> {
+        let mut value = self.unary()?;
+
+        while let TokenKind::Symbol(op @ ('*' | '/')) = self.peek().kind {
+            let token = self.next();
+            let rhs = self.unary()?;
+
+            value = match (value, rhs) {
+                (Value::Number(a), Value::Number(b)) => {
+                    Value::Number(if op == '*' { a * b } else { a / b })
+                }
+                (Value::Point(p), Value::Number(n)) => {
+                    Value::Point(p * if op == '*' { n } else { 1.0 / n })
+                }
+                (a, b) => {
+                    return Err(Parser::error(
+                        &token,
+                        format!(
+                            "cannot apply '{}' to {} and {}",
+                            op,
+                            a.type_name(),
+                            b.type_name()
+                        ),
+                    ))
+                }
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<Value, ScriptError> {
+        if self.peek().kind == TokenKind::Symbol('-') {
+            let token = self.next();
+
+            return match self.unary()? {
+                Value::Number(n) => Ok(Value::Number(-n)),
+                Value::Point(p) => Ok(Value::Point(-p)),
+                value => Err(Parser::error(
+                    &token,
+                    format!("cannot negate a {}", value.type_name()),
+                )),
+            };
+        }
+
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Value, ScriptError> {
+        let mut value = self.primary()?;
+
+        while self.peek().kind == TokenKind::Symbol('[') {
+            let token = self.next();
+            let index = self.number()?;
+            self.expect(']')?;
+
+            let Value::Points(points) = &value else {
+                return Err(Parser::error(
+                    &token,
+                    format!("cannot index a {}", value.type_name()),
+                ));
+            };
+
+            if index < 0.0 || index.fract() != 0.0 || index as usize >= points.len() {
+                return Err(Parser::error(
+                    &token,
+                    format!(
+                        "index {} is out of range, there are {} points",
+                        index,
+                        points.len()
+                    ),
+                ));
+            }
+
+            value = Value::Point(points[index as usize]);
+        }
+
+        Ok(value)
+    }
+
+    fn primary(&mut self) -> Result<Value, ScriptError> {
+        let token = self.next();
+
+        match &token.kind {
+            TokenKind::Number(n) => Ok(Value::Number(*n)),
+            TokenKind::Symbol('(') => {
+                let value = self.expression()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            TokenKind::Ident(name) if self.peek().kind == TokenKind::Symbol('(') => {
+                self.next();
+
+                let mut arguments = Vec::new();
+
+                if self.peek().kind != TokenKind::Symbol(')') {
+                    loop {
+                        let argument_token = self.peek().clone();
+                        arguments.push((argument_token, self.expression()?));
+
+                        if self.peek().kind != TokenKind::Symbol(',') {
+                            break;
+                        }
+                        self.next();
+                    }
+                }
+
+                self.expect(')')?;
+                call(&token, name, arguments)
+            }
+            TokenKind::Ident(name) => match name.as_str() {
+                "pi" => Ok(Value::Number(std::f32::consts::PI)),
+                _ => self
+                    .names
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| Parser::error(&token, format!("{} is not defined", name))),
+            },
+            kind => Err(Parser::error(
+                &token,
+                format!("expected a value but found {}", describe(kind)),
+            )),
+        }
+    }
+
+    fn number(&mut self) -> Result<f32, ScriptError> {
+        let token = self.peek().clone();
+
+        match self.expression()? {
+            Value::Number(n) => Ok(n),
+            value => Err(Parser::error(
+                &token,
+                format!("expected a number but found a {}", value.type_name()),
+            )),
+        }
+    }
+}
+
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Ident(name) => format!("'{}'", name),
+        TokenKind::Number(n) => format!("'{}'", n),
+        TokenKind::Symbol(c) => format!("'{}'", c),
+        TokenKind::Separator => "end of statement".to_owned(),
+        TokenKind::End => "end of script".to_owned(),
+    }
+}
+
+fn call(token: &Token, name: &str, arguments: Vec<(Token, Value)>) -> Result<Value, ScriptError> {
+    let signature = |expected: &str| {
+        Parser::error(
+            token,
+            format!(
+                "{} expects ({}) but was given ({})",
+                name,
+                expected,
+                arguments
+                    .iter()
+                    .map(|(_, value)| value.type_name())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ),
+        )
+    };
+
+    let values: Vec<&Value> = arguments.iter().map(|(_, value)| value).collect();
+
+    match name {
+        "point" => match values[..] {
+            [Value::Number(x), Value::Number(y)] => Ok(Value::Point(Pos::new(*x, *y))),
+            _ => Err(signature("number, number")),
+        },
+        "circle" => match values[..] {
+            [Value::Point(pos), Value::Point(through)] => {
+                Ok(Value::Shape(Shape::Circle(CircleData {
+                    pos: *pos,
+                    r: pos.distance(*through),
+                })))
+            }
+            [Value::Point(pos), Value::Number(r)] => {
+                Ok(Value::Shape(Shape::Circle(CircleData { pos: *pos, r: *r })))
+            }
+            _ => Err(signature("point, point or point, number")),
+        },
+        "line" => match values[..] {
+            [Value::Point(p1), Value::Point(p2)] => {
+                Ok(Value::Shape(Shape::Line(LineData { p1: *p1, p2: *p2 })))
+            }
+            _ => Err(signature("point, point")),
+        },
+        "seg" | "segment" => match values[..] {
+            [Value::Point(p1), Value::Point(p2)] => Ok(Value::Shape(Shape::Segment(SegmentData {
+                p1: *p1,
+                p2: *p2,
+            }))),
+            _ => Err(signature("point, point")),
+        },
+        "arc" => match values[..] {
+            [Value::Point(pos), Value::Point(start), Value::Point(stop)] => {
+                Ok(Value::Shape(Shape::Arc(ArcData {
+                    pos: *pos,
+                    r: pos.distance(*start),
+                    start: utils::arc_angle(*start, *pos),
+                    stop: utils::arc_angle(*stop, *pos),
+                })))
+            }
+            _ => Err(signature("point, point, point")),
+        },
+        "ellipse" | "hyperbola" => match values[..] {
+            [Value::Point(f1), Value::Point(f2), Value::Point(through)] => {
+                Ok(Value::Shape(Shape::Conic(match name {
+                    "ellipse" => ConicData::ellipse(*f1, *f2, *through),
+                    _ => ConicData::hyperbola(*f1, *f2, *through),
+                })))
+            }
+            _ => Err(signature("point, point, point")),
+        },
+        "parabola" => match values[..] {
+            [Value::Point(focus), Value::Point(vertex)] => Ok(Value::Shape(Shape::Conic(
+                ConicData::parabola(*focus, *vertex),
+            ))),
+            _ => Err(signature("point, point")),
+        },
+        "conic" => match values[..] {
+            [Value::Number(a), Value::Number(b), Value::Number(c), Value::Number(d), Value::Number(e), Value::Number(f)] => {
+                Ok(Value::Shape(Shape::Conic(ConicData {
+                    a: *a,
+                    b: *b,
+                    c: *c,
+                    d: *d,
+                    e: *e,
+                    f: *f,
+                })))
+            }
+            [Value::Point(_), Value::Point(_), Value::Point(_), Value::Point(_), Value::Point(_)] =>
+            {
+                let points: Vec<Pos> = values
+                    .iter()
+                    .filter_map(|value| match value {
+                        Value::Point(point) => Some(*point),
+                        _ => None,
+                    })
+                    .collect();
+
+                ConicData::through(&points)
+                    .map(|conic| Value::Shape(Shape::Conic(conic)))
+                    .ok_or_else(|| {
+                        Parser::error(token, "the points do not fix a single conic".to_owned())
+                    })
+            }
+            _ => Err(signature("five points or six numbers")),
+        },
+        "intersect" => match values[..] {
+            [Value::Shape(a), Value::Shape(b)] => Ok(Value::Points(
+                a.intersections(b)
+                    .into_iter()
+                    .filter(|point| point.is_finite())
+                    .collect(),
+            )),
+            _ => Err(signature("shape, shape")),
+        },
+        "sqrt" => match values[..] {
+            [Value::Number(n)] if *n >= 0.0 => Ok(Value::Number(n.sqrt())),
+            [Value::Number(_)] => Err(Parser::error(
+                &arguments[0].0,
+                "cannot take the square root of a negative number".to_owned(),
+            )),
+            _ => Err(signature("number")),
+        },
+        _ => Err(Parser::error(token, format!("{} is not a function", name))),
+    }
+}
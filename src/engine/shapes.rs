@@ -1,54 +1,58 @@
 use std::{f32::consts::PI, fmt};
 
-use egui::{
-    plot::{self, PlotUi},
-    Pos2,
-};
 use serde::{Deserialize, Serialize};
 
-use super::utils;
-use crate::de::{color32::DeColor32, pos2::DePos2};
+use super::{color::Color, definition::Definition, pos::Pos, utils};
+
+pub use super::{
+    conic::{ConicData, ConicKind},
+    locus::LocusData,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Construction {
     pub shape: Shape,
     pub layer: String,
-    pub color: DeColor32,
+    pub color: Color,
     pub width: f32,
-    pub intersections: Vec<DePos2>,
-}
-
-impl Construction {
-    pub fn get_line(&self, ui: &PlotUi) -> plot::Line {
-        match &self.shape {
-            Shape::Circle(circle) => utils::circle(circle.pos.to_pos2(), circle.r),
-            Shape::Line(line) => utils::line(
-                line.p1.to_pos2(),
-                line.p2.to_pos2(),
-                ui.plot_bounds().max()[1],
-                ui.plot_bounds().min()[1],
-            ),
-            Shape::Segment(segment) => utils::segment(segment.p1.to_pos2(), segment.p2.to_pos2()),
-            Shape::Arc(arc) => utils::arc(arc.pos.to_pos2(), arc.r, arc.start, arc.stop),
-        }
-        .color(self.color.to_color32())
-        .width(self.width)
-        .name(&self.layer)
-    }
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The tool and points that made this, if it was made with a tool.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub definition: Option<Definition>,
+    /// Hidden constructions are kept but not drawn, snapped to or exported.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+    /// Shown while this construction is drawn during playback.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    /// Elementary moves taken by the tool that made this, or 0 if it was not made with a tool.
+    #[serde(skip)]
+    pub cost: u32,
+    #[serde(skip)]
+    pub intersections: Vec<Pos>,
 }
 
 impl fmt::Display for Construction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.shape)
+        match &self.label {
+            Some(label) => write!(f, "{} = {}", label, self.shape),
+            None => write!(f, "{}", self.shape),
+        }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Shape {
     Circle(CircleData),
     Line(LineData),
     Segment(SegmentData),
     Arc(ArcData),
+    /// An ellipse, parabola or hyperbola, which compass and straight edge cannot draw.
+    Conic(ConicData),
+    /// The sampled path of a point as another point moves along a curve.
+    Locus(LocusData),
 }
 
 impl fmt::Display for Shape {
@@ -74,31 +78,37 @@ impl fmt::Display for Shape {
                 "Arc: p=({:.2}, {:.2}), r={:.3}, start={:.2}, stop={:.2}",
                 data.pos.x, data.pos.y, data.r, data.start, data.stop
             ),
+            Shape::Conic(data) => write!(f, "{}", data),
+            Shape::Locus(data) => write!(
+                f,
+                "Locus: {} points",
+                data.pieces.iter().map(Vec::len).sum::<usize>()
+            ),
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CircleData {
-    pub pos: DePos2,
+    pub pos: Pos,
     pub r: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LineData {
-    pub p1: DePos2,
-    pub p2: DePos2,
+    pub p1: Pos,
+    pub p2: Pos,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SegmentData {
-    pub p1: DePos2,
-    pub p2: DePos2,
+    pub p1: Pos,
+    pub p2: Pos,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArcData {
-    pub pos: DePos2,
+    pub pos: Pos,
     pub r: f32,
     pub start: f32,
     pub stop: f32,
@@ -107,39 +117,47 @@ pub struct ArcData {
 impl SegmentData {
     pub fn line(&self) -> LineData {
         LineData {
-            p1: self.p1.clone(),
-            p2: self.p2.clone(),
+            p1: self.p1,
+            p2: self.p2,
         }
     }
 
-    pub fn valid_points(&self, points: Vec<DePos2>) -> Vec<DePos2> {
-        let mut valid = Vec::new();
-
-        for point in points {
-            if utils::within(point.x, self.p1.x, self.p2.x)
-                && utils::within(point.y, self.p1.y, self.p2.y)
-            {
-                valid.push(point);
-            }
-        }
-
-        valid
+    /// The points that lie between the ends, measured along the segment so that points on
+    /// nearly horizontal or vertical segments are not lost to rounding.
+    pub fn valid_points(&self, points: Vec<Pos>) -> Vec<Pos> {
+        let direction = self.p2 - self.p1;
+
+        points
+            .into_iter()
+            .filter(|point| {
+                let t = (*point - self.p1).dot(direction) / direction.length_sq();
+                (-1e-5..=1.0 + 1e-5).contains(&t)
+            })
+            .collect()
     }
 }
 
 impl ArcData {
     pub fn circle(&self) -> CircleData {
         CircleData {
-            pos: self.pos.clone(),
+            pos: self.pos,
             r: self.r,
         }
     }
 
-    pub fn valid_points(&self, points: Vec<DePos2>) -> Vec<DePos2> {
+    /// The start and stop points.
+    pub fn endpoints(&self) -> (Pos, Pos) {
+        (
+            self.pos + self.r * Pos::from_angle(self.start),
+            self.pos + self.r * Pos::from_angle(self.stop),
+        )
+    }
+
+    pub fn valid_points(&self, points: Vec<Pos>) -> Vec<Pos> {
         let mut valid = Vec::new();
 
         for point in points {
-            let angle = utils::arc_angle(point.to_pos2(), self.pos.to_pos2());
+            let angle = utils::arc_angle(point, self.pos);
 
             if self.stop < self.start {
                 if (self.start <= angle && angle <= 2.0 * PI)
@@ -157,13 +175,158 @@ impl ArcData {
 }
 
 impl Shape {
-    pub fn intersections(&self, other: &Shape) -> Vec<DePos2> {
+    /// A box containing the shape. Lines are bounded by their defining points.
+    pub fn bounds(&self) -> (Pos, Pos) {
+        match self {
+            Shape::Circle(circle) => {
+                let r = Pos::new(circle.r, circle.r);
+                (circle.pos - r, circle.pos + r)
+            }
+            Shape::Line(line) => (line.p1.min(line.p2), line.p1.max(line.p2)),
+            Shape::Segment(segment) => (segment.p1.min(segment.p2), segment.p1.max(segment.p2)),
+            Shape::Arc(arc) => {
+                let r = Pos::new(arc.r, arc.r);
+                (arc.pos - r, arc.pos + r)
+            }
+            Shape::Conic(conic) => conic.bounds(),
+            Shape::Locus(locus) => locus.bounds(),
+        }
+    }
+
+    /// The shortest distance from a point to the shape.
+    pub fn distance(&self, point: Pos) -> f32 {
+        match self {
+            Shape::Circle(circle) => (point.distance(circle.pos) - circle.r).abs(),
+            Shape::Line(line) => {
+                let (p1, p2) = (line.p1, line.p2);
+                let direction = (p2 - p1).normalized();
+
+                (point - p1 - direction * (point - p1).dot(direction)).length()
+            }
+            Shape::Segment(segment) => {
+                let (p1, p2) = (segment.p1, segment.p2);
+                let t = ((point - p1).dot(p2 - p1) / (p2 - p1).length_sq()).clamp(0.0, 1.0);
+
+                point.distance(p1 + (p2 - p1) * t)
+            }
+            Shape::Arc(arc) => {
+                if arc.valid_points(vec![point]).is_empty() {
+                    let (start, stop) = arc.endpoints();
+
+                    f32::min(point.distance(start), point.distance(stop))
+                } else {
+                    (point.distance(arc.pos) - arc.r).abs()
+                }
+            }
+            Shape::Conic(conic) => conic.distance(point),
+            Shape::Locus(locus) => locus.distance(point),
+        }
+    }
+
+    /// The points the shape was made from that can be snapped to: centres, the points lines
+    /// pass through, and the ends of segments and arcs. Conics and loci have none.
+    pub fn defining_points(&self) -> Vec<Pos> {
+        match self {
+            Shape::Circle(circle) => vec![circle.pos],
+            Shape::Line(line) => vec![line.p1, line.p2],
+            Shape::Segment(segment) => vec![segment.p1, segment.p2],
+            Shape::Arc(arc) => {
+                let (start, stop) = arc.endpoints();
+
+                vec![arc.pos, start, stop]
+            }
+            Shape::Conic(_) | Shape::Locus(_) => Vec::new(),
+        }
+    }
+
+    /// The point on the shape closest to `point`. `None` if it cannot be found, as when a
+    /// conic's projection does not settle or a locus has no samples.
+    pub fn project(&self, point: Pos) -> Option<Pos> {
+        match self {
+            Shape::Circle(circle) => {
+                let offset = point - circle.pos;
+
+                Some(match offset.length() {
+                    0.0 => circle.pos + Pos::new(circle.r, 0.0),
+                    length => circle.pos + offset * (circle.r / length),
+                })
+            }
+            Shape::Line(line) => {
+                let (p1, p2) = (line.p1, line.p2);
+                let direction = (p2 - p1).normalized();
+
+                Some(p1 + direction * (point - p1).dot(direction))
+            }
+            Shape::Segment(segment) => {
+                let (p1, p2) = (segment.p1, segment.p2);
+                let t = ((point - p1).dot(p2 - p1) / (p2 - p1).length_sq()).clamp(0.0, 1.0);
+
+                Some(p1 + (p2 - p1) * t)
+            }
+            Shape::Arc(arc) => {
+                let (start, stop) = arc.endpoints();
+
+                match Shape::Circle(arc.circle()).project(point) {
+                    Some(foot) if !arc.valid_points(vec![foot]).is_empty() => Some(foot),
+                    _ if point.distance_sq(start) <= point.distance_sq(stop) => Some(start),
+                    _ => Some(stop),
+                }
+            }
+            Shape::Conic(conic) => conic.project(point),
+            Shape::Locus(locus) => locus.project(point),
+        }
+        .filter(|foot| foot.is_finite())
+    }
+
+    /// Whether `other` is the same object to within `tolerance`. Lines match if they pass
+    /// through both defining points of this one, and a circle and any of its arcs match
+    /// either way round.
+    pub fn matches(&self, other: &Shape, tolerance: f32) -> bool {
+        let close = |a: Pos, b: Pos| a.distance(b) <= tolerance;
+
+        match (self, other) {
+            (Shape::Circle(a), Shape::Circle(b)) => {
+                close(a.pos, b.pos) && (a.r - b.r).abs() <= tolerance
+            }
+            (Shape::Line(a), Shape::Line(_)) => {
+                other.distance(a.p1) <= tolerance && other.distance(a.p2) <= tolerance
+            }
+            (Shape::Segment(a), Shape::Segment(b)) => {
+                (close(a.p1, b.p1) && close(a.p2, b.p2)) || (close(a.p1, b.p2) && close(a.p2, b.p1))
+            }
+            (Shape::Arc(a), Shape::Circle(_)) => {
+                Shape::Circle(a.circle()).matches(other, tolerance)
+            }
+            (Shape::Circle(_), Shape::Arc(b)) => {
+                self.matches(&Shape::Circle(b.circle()), tolerance)
+            }
+            (Shape::Arc(a), Shape::Arc(b)) => {
+                let ((a1, a2), (b1, b2)) = (a.endpoints(), b.endpoints());
+
+                Shape::Circle(a.circle()).matches(&Shape::Circle(b.circle()), tolerance)
+                    && close(a1, b1)
+                    && close(a2, b2)
+            }
+            (Shape::Conic(a), Shape::Conic(b)) => a.matches(b, tolerance),
+            (Shape::Locus(a), Shape::Locus(b)) => {
+                a.pieces.len() == b.pieces.len()
+                    && a.pieces.iter().zip(&b.pieces).all(|(a, b)| {
+                        a.len() == b.len() && a.iter().zip(b).all(|(p, q)| close(*p, *q))
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    pub fn intersections(&self, other: &Shape) -> Vec<Pos> {
         match self {
             Shape::Circle(a) => match other {
                 Shape::Circle(b) => Shape::circle_circle(a, b),
                 Shape::Line(b) => Shape::circle_line(a, b),
                 Shape::Segment(b) => Shape::circle_segment(a, b),
                 Shape::Arc(b) => Shape::circle_arc(a, b),
+                Shape::Conic(b) => b.circle_intersections(a.pos, a.r),
+                Shape::Locus(b) => b.intersections(self),
             },
 
             Shape::Line(a) => match other {
@@ -171,6 +334,8 @@ impl Shape {
                 Shape::Line(b) => Shape::line_line(a, b),
                 Shape::Segment(b) => Shape::line_segment(a, b),
                 Shape::Arc(b) => Shape::line_arc(a, b),
+                Shape::Conic(b) => b.line_intersections(a.p1, a.p2),
+                Shape::Locus(b) => b.intersections(self),
             },
 
             Shape::Segment(a) => match other {
@@ -178,6 +343,8 @@ impl Shape {
                 Shape::Line(b) => Shape::line_segment(b, a),
                 Shape::Segment(b) => Shape::segment_segment(a, b),
                 Shape::Arc(b) => Shape::segment_arc(a, b),
+                Shape::Conic(b) => a.valid_points(b.line_intersections(a.p1, a.p2)),
+                Shape::Locus(b) => b.intersections(self),
             },
 
             Shape::Arc(a) => match other {
@@ -185,12 +352,27 @@ impl Shape {
                 Shape::Line(b) => Shape::line_arc(b, a),
                 Shape::Segment(b) => Shape::segment_arc(b, a),
                 Shape::Arc(b) => Shape::arc_arc(a, b),
+                Shape::Conic(b) => a.valid_points(b.circle_intersections(a.pos, a.r)),
+                Shape::Locus(b) => b.intersections(self),
+            },
+
+            Shape::Conic(a) => match other {
+                Shape::Conic(b) => a.conic_intersections(b),
+                Shape::Locus(b) => b.intersections(self),
+                _ => other.intersections(self),
             },
+
+            Shape::Locus(a) => a.intersections(other),
         }
     }
 
-    fn circle_circle(a: &CircleData, b: &CircleData) -> Vec<DePos2> {
-        if Pos2::distance_sq(a.pos.to_pos2(), b.pos.to_pos2()) > f32::powi(a.r + b.r, 2) {
+    fn circle_circle(a: &CircleData, b: &CircleData) -> Vec<Pos> {
+        let distance_sq = Pos::distance_sq(a.pos, b.pos);
+
+        if distance_sq > f32::powi(a.r + b.r, 2)
+            || distance_sq < f32::powi(a.r - b.r, 2)
+            || distance_sq == 0.0
+        {
             Vec::new()
         } else {
             let m = (a.pos.x - b.pos.x) / (b.pos.y - a.pos.y);
@@ -202,12 +384,12 @@ impl Shape {
                 let pb = -2.0 * a.pos.y;
                 let pc = (a.pos.y * a.pos.y) - (a.r * a.r) + f32::powi(x - a.pos.x, 2);
 
-                let d = f32::sqrt((pb * pb) - 4.0 * pc);
+                let d = f32::sqrt(f32::max((pb * pb) - 4.0 * pc, 0.0));
 
                 let y1 = (-pb + d) / 2.0;
                 let y2 = (-pb - d) / 2.0;
 
-                return vec![DePos2::new(x, y1), DePos2::new(x, y2)];
+                return vec![Pos::new(x, y1), Pos::new(x, y2)];
             }
 
             let c = ((a.pos.x * a.pos.x) + (a.pos.y * a.pos.y)
@@ -220,14 +402,14 @@ impl Shape {
             Shape::circle_line(
                 a,
                 &LineData {
-                    p1: DePos2::new(0.0, c),
-                    p2: DePos2::new(1.0, m + c),
+                    p1: Pos::new(0.0, c),
+                    p2: Pos::new(1.0, m + c),
                 },
             )
         }
     }
 
-    fn circle_line(a: &CircleData, b: &LineData) -> Vec<DePos2> {
+    fn circle_line(a: &CircleData, b: &LineData) -> Vec<Pos> {
         let m = (b.p1.y - b.p2.y) / (b.p1.x - b.p2.x);
         let c = -m * b.p1.x + b.p1.y;
 
@@ -237,12 +419,17 @@ impl Shape {
             let pb = 2.0 * a.pos.y;
             let pc = f32::powi(x - a.pos.x, 2) + (a.pos.y * a.pos.y) - (a.r * a.r);
 
-            let d = f32::sqrt((pb * pb) - (4.0 * pc));
+            let discriminant = (pb * pb) - (4.0 * pc);
+            if discriminant < 0.0 {
+                return Vec::new();
+            }
+
+            let d = f32::sqrt(discriminant);
 
             let y1 = (pb + d) / 2.0;
             let y2 = (pb - d) / 2.0;
 
-            return vec![DePos2::new(x, y1), DePos2::new(x, y2)];
+            return vec![Pos::new(x, y1), Pos::new(x, y2)];
         }
 
         let d = ((m * m + 1.0) * (a.r * a.r)) - f32::powi(a.pos.x * m - a.pos.y + c, 2);
@@ -257,23 +444,23 @@ impl Shape {
             let y1 = m * x1 + c;
             let y2 = m * x2 + c;
 
-            vec![DePos2::new(x1, y1), DePos2::new(x2, y2)]
+            vec![Pos::new(x1, y1), Pos::new(x2, y2)]
         } else {
             Vec::new()
         }
     }
 
-    fn circle_segment(a: &CircleData, b: &SegmentData) -> Vec<DePos2> {
+    fn circle_segment(a: &CircleData, b: &SegmentData) -> Vec<Pos> {
         let possible = Shape::circle_line(a, &b.line());
         b.valid_points(possible)
     }
 
-    fn circle_arc(a: &CircleData, b: &ArcData) -> Vec<DePos2> {
+    fn circle_arc(a: &CircleData, b: &ArcData) -> Vec<Pos> {
         let possible = Shape::circle_circle(a, &b.circle());
         b.valid_points(possible)
     }
 
-    fn line_line(a: &LineData, b: &LineData) -> Vec<DePos2> {
+    fn line_line(a: &LineData, b: &LineData) -> Vec<Pos> {
         let m1 = (a.p1.y - a.p2.y) / (a.p1.x - a.p2.x);
         let m2 = (b.p1.y - b.p2.y) / (b.p1.x - b.p2.x);
 
@@ -285,7 +472,7 @@ impl Shape {
             let x = a.p1.x;
             let y = (m2 * x) - (m2 * b.p1.x) + b.p1.y;
 
-            return vec![DePos2::new(x, y)];
+            return vec![Pos::new(x, y)];
         }
 
         if m2.is_infinite() {
@@ -295,30 +482,30 @@ impl Shape {
         let x = (b.p1.y - a.p1.y - (m2 * b.p1.x) + (m1 * a.p1.x)) / (m1 - m2);
         let y = m1 * (x - a.p1.x) + a.p1.y;
 
-        vec![DePos2::new(x, y)]
+        vec![Pos::new(x, y)]
     }
 
-    fn line_segment(a: &LineData, b: &SegmentData) -> Vec<DePos2> {
+    fn line_segment(a: &LineData, b: &SegmentData) -> Vec<Pos> {
         let possible = Shape::line_line(a, &b.line());
         b.valid_points(possible)
     }
 
-    fn line_arc(a: &LineData, b: &ArcData) -> Vec<DePos2> {
+    fn line_arc(a: &LineData, b: &ArcData) -> Vec<Pos> {
         let possible = Shape::circle_line(&b.circle(), a);
         b.valid_points(possible)
     }
 
-    fn segment_segment(a: &SegmentData, b: &SegmentData) -> Vec<DePos2> {
+    fn segment_segment(a: &SegmentData, b: &SegmentData) -> Vec<Pos> {
         let possible = Shape::line_segment(&a.line(), b);
         a.valid_points(possible)
     }
 
-    fn segment_arc(a: &SegmentData, b: &ArcData) -> Vec<DePos2> {
+    fn segment_arc(a: &SegmentData, b: &ArcData) -> Vec<Pos> {
         let possible = Shape::line_arc(&a.line(), b);
         a.valid_points(possible)
     }
 
-    fn arc_arc(a: &ArcData, b: &ArcData) -> Vec<DePos2> {
+    fn arc_arc(a: &ArcData, b: &ArcData) -> Vec<Pos> {
         let possible = Shape::circle_arc(&a.circle(), b);
         a.valid_points(possible)
     }
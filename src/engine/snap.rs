@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+
+use super::{definition::Input, pos::Pos, shapes::Construction};
+
+/// The kinds of snap target, which can each be turned off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapClass {
+    /// Intersections and labelled points.
+    Intersections,
+    /// Centres, the points lines pass through and the ends of segments and arcs.
+    Points,
+    /// The closest point on a construction.
+    Curves,
+    /// The closest point of the background grid, when it is shown.
+    Grid,
+}
+
+impl SnapClass {
+    /// Every class, from the highest priority to the lowest by default.
+    pub const ALL: [SnapClass; 4] = [
+        SnapClass::Intersections,
+        SnapClass::Points,
+        SnapClass::Curves,
+        SnapClass::Grid,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SnapClass::Intersections => "intersections",
+            SnapClass::Points => "points",
+            SnapClass::Curves => "curves",
+            SnapClass::Grid => "grid",
+        }
+    }
+}
+
+/// Which classes of target are snapped to and in what order. When targets of several classes
+/// are in range, the one whose class comes first in `order` is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapSettings {
+    pub intersections: bool,
+    pub points: bool,
+    pub curves: bool,
+    pub grid: bool,
+    /// Every class, from the highest priority to the lowest.
+    pub order: Vec<SnapClass>,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        SnapSettings {
+            intersections: true,
+            points: true,
+            curves: true,
+            grid: true,
+            order: SnapClass::ALL.to_vec(),
+        }
+    }
+}
+
+impl SnapSettings {
+    pub fn enabled(&self, class: SnapClass) -> bool {
+        match class {
+            SnapClass::Intersections => self.intersections,
+            SnapClass::Points => self.points,
+            SnapClass::Curves => self.curves,
+            SnapClass::Grid => self.grid,
+        }
+    }
+
+    /// Settings with classes in `order`. Repeated classes are dropped and missing ones are
+    /// added at the end, so every class is in the order once.
+    pub fn with_order(order: &[SnapClass]) -> Self {
+        let mut settings = SnapSettings {
+            order: Vec::new(),
+            ..Default::default()
+        };
+
+        for &class in order.iter().chain(SnapClass::ALL.iter()) {
+            if !settings.order.contains(&class) {
+                settings.order.push(class);
+            }
+        }
+
+        settings
+    }
+
+    /// Moves a class one place earlier in the order.
+    pub fn raise(&mut self, class: SnapClass) {
+        if let Some(i) = self.order.iter().position(|&c| c == class) {
+            if i > 0 {
+                self.order.swap(i - 1, i);
+            }
+        }
+    }
+
+    pub fn enabled_mut(&mut self, class: SnapClass) -> &mut bool {
+        match class {
+            SnapClass::Intersections => &mut self.intersections,
+            SnapClass::Points => &mut self.points,
+            SnapClass::Curves => &mut self.curves,
+            SnapClass::Grid => &mut self.grid,
+        }
+    }
+}
+
+/// What a click was snapped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapKind {
+    /// An intersection or labelled point.
+    Intersection,
+    /// A defining point of the construction at this index.
+    Point(usize),
+    /// The closest point on the construction at this index.
+    Curve(usize),
+    /// A point of the background grid.
+    Grid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Snap {
+    pub pos: Pos,
+    pub kind: SnapKind,
+}
+
+impl Snap {
+    /// How a construction made from this point should remember it.
+    pub fn input(&self, constructions: &[Construction]) -> Input {
+        match self.kind {
+            SnapKind::Intersection | SnapKind::Point(_) | SnapKind::Grid => {
+                Input::find(constructions, self.pos)
+            }
+            SnapKind::Curve(on) => Input::OnCurve { on, pos: self.pos },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridKind {
+    #[default]
+    Square,
+    /// Equilateral triangles, with rows of points offset by half the spacing.
+    Triangular,
+}
+
+impl GridKind {
+    pub const ALL: [GridKind; 2] = [GridKind::Square, GridKind::Triangular];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GridKind::Square => "square",
+            GridKind::Triangular => "triangular",
+        }
+    }
+}
+
+/// A lattice of points drawn behind the constructions, for sketches that do not need to be
+/// constructed exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Grid {
+    pub kind: GridKind,
+    /// Distance between neighbouring points.
+    pub spacing: f32,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid {
+            kind: GridKind::Square,
+            spacing: 1.0,
+        }
+    }
+}
+
+/// Grid lines are not drawn when there would be more than this many in a direction.
+const MAX_LINES: f32 = 200.0;
+
+impl Grid {
+    /// The lattice vectors from a point to its neighbours along a row and to the next row.
+    fn basis(&self) -> (Pos, Pos) {
+        match self.kind {
+            GridKind::Square => (Pos::new(self.spacing, 0.0), Pos::new(0.0, self.spacing)),
+            GridKind::Triangular => (
+                Pos::new(self.spacing, 0.0),
+                Pos::new(self.spacing / 2.0, self.spacing * 3.0_f32.sqrt() / 2.0),
+            ),
+        }
+    }
+
+    /// The grid point closest to `pos`, or `None` if the spacing is not a positive number.
+    pub fn nearest(&self, pos: Pos) -> Option<Pos> {
+        if !self.spacing.is_finite() || self.spacing <= 0.0 {
+            return None;
+        }
+
+        let (u, v) = self.basis();
+
+        // Lattice coordinates, then the closest of the four points around them
+        let b = pos.y / v.y;
+        let a = (pos.x - b * v.x) / u.x;
+
+        [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+            .into_iter()
+            .map(|(i, j)| u * (a.floor() + i) + v * (b.floor() + j))
+            .min_by(|p, q| p.distance_sq(pos).total_cmp(&q.distance_sq(pos)))
+    }
+
+    /// The grid lines crossing the box from `min` to `max`, as pairs of points beyond its
+    /// edges. Empty if they would be too close together to see.
+    pub fn lines(&self, min: Pos, max: Pos) -> Vec<(Pos, Pos)> {
+        if !self.spacing.is_finite() || self.spacing <= 0.0 {
+            return Vec::new();
+        }
+
+        let (u, v) = self.basis();
+
+        // Each family of lines is given by its direction, with lines `step` apart
+        let families = match self.kind {
+            GridKind::Square => vec![(u, self.spacing), (v, self.spacing)],
+            GridKind::Triangular => vec![(u, v.y), (v, v.y), (v - u, v.y)],
+        };
+
+        let corners = [min, Pos::new(min.x, max.y), Pos::new(max.x, min.y), max];
+        let mut lines = Vec::new();
+
+        for (direction, step) in families {
+            let direction = direction.normalized();
+            let normal = Pos::new(-direction.y, direction.x);
+
+            let range = |axis: Pos| {
+                corners
+                    .iter()
+                    .map(|corner| corner.dot(axis))
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), d| {
+                        (lo.min(d), hi.max(d))
+                    })
+            };
+            let (lo, hi) = range(normal);
+            let (start, end) = range(direction);
+
+            if (hi - lo) / step > MAX_LINES {
+                return Vec::new();
+            }
+
+            let mut k = (lo / step).floor();
+
+            while k * step <= hi {
+                let offset = normal * (k * step);
+
+                lines.push((offset + direction * start, offset + direction * end));
+                k += 1.0;
+            }
+        }
+
+        lines
+    }
+}
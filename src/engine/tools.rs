@@ -1,29 +1,55 @@
-use egui::{
-    plot::{self, PlotUi},
-    Pos2,
-};
 use std::vec;
 
-use super::{shapes, utils};
+use super::{pos::Pos, shapes, utils};
 
 pub trait Tool {
     fn name(&self) -> &str;
     fn instructions(&self) -> Vec<&str>;
     fn num_points(&self) -> u8;
-    fn get_guides(&self, points: &[Pos2], mouse: Pos2, ui: &PlotUi) -> Vec<plot::Line>;
-    fn get_shape(&self, points: &[Pos2]) -> shapes::Shape;
+    /// Shapes previewing the construction that would be made if `mouse` were clicked next.
+    fn get_guides(&self, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape>;
+    fn get_shape(&self, points: &[Pos]) -> shapes::Shape;
+
+    /// Elementary moves the tool counts as in puzzles. Macro tools that stand in for several
+    /// compass and straight edge steps cost more.
+    fn cost(&self) -> u32 {
+        1
+    }
 }
 
-impl PartialEq for (dyn Tool + 'static) {
+impl PartialEq for dyn Tool + 'static {
     fn eq(&self, other: &Self) -> bool {
         self.name() == other.name()
     }
 }
 
+/// Every tool, in the order they are offered.
+pub const ALL: [&dyn Tool; 9] = [
+    &Compass,
+    &StraightEdge,
+    &LineSegment,
+    &Arc,
+    &PerpendicularBisector,
+    &Ellipse,
+    &Hyperbola,
+    &Parabola,
+    &ConicThroughPoints,
+];
+
+/// The tool called `name`, for making a construction again from its definition.
+pub fn by_name(name: &str) -> Option<&'static dyn Tool> {
+    ALL.iter().find(|tool| tool.name() == name).copied()
+}
+
 pub struct Compass;
 pub struct StraightEdge;
 pub struct LineSegment;
 pub struct Arc;
+pub struct PerpendicularBisector;
+pub struct Ellipse;
+pub struct Hyperbola;
+pub struct Parabola;
+pub struct ConicThroughPoints;
 
 impl Tool for Compass {
     fn name(&self) -> &str {
@@ -38,13 +64,13 @@ impl Tool for Compass {
         2
     }
 
-    fn get_guides(&self, points: &[Pos2], mouse: Pos2, _ui: &PlotUi) -> Vec<plot::Line> {
-        vec![utils::circle(points[0], points[0].distance(mouse))]
+    fn get_guides(&self, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape> {
+        vec![self.get_shape(&[points[0], mouse])]
     }
 
-    fn get_shape(&self, points: &[Pos2]) -> shapes::Shape {
+    fn get_shape(&self, points: &[Pos]) -> shapes::Shape {
         shapes::Shape::Circle(shapes::CircleData {
-            pos: points[0].into(),
+            pos: points[0],
             r: points[0].distance(points[1]),
         })
     }
@@ -63,19 +89,14 @@ impl Tool for StraightEdge {
         2
     }
 
-    fn get_guides(&self, points: &[Pos2], mouse: Pos2, ui: &PlotUi) -> Vec<plot::Line> {
-        vec![utils::line(
-            points[0],
-            mouse,
-            ui.plot_bounds().max()[1],
-            ui.plot_bounds().min()[1],
-        )]
+    fn get_guides(&self, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape> {
+        vec![self.get_shape(&[points[0], mouse])]
     }
 
-    fn get_shape(&self, points: &[Pos2]) -> shapes::Shape {
+    fn get_shape(&self, points: &[Pos]) -> shapes::Shape {
         shapes::Shape::Line(shapes::LineData {
-            p1: points[0].into(),
-            p2: points[1].into(),
+            p1: points[0],
+            p2: points[1],
         })
     }
 }
@@ -93,14 +114,14 @@ impl Tool for LineSegment {
         2
     }
 
-    fn get_guides(&self, points: &[Pos2], mouse: Pos2, _ui: &PlotUi) -> Vec<plot::Line> {
-        vec![utils::segment(points[0], mouse)]
+    fn get_guides(&self, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape> {
+        vec![self.get_shape(&[points[0], mouse])]
     }
 
-    fn get_shape(&self, points: &[Pos2]) -> shapes::Shape {
+    fn get_shape(&self, points: &[Pos]) -> shapes::Shape {
         shapes::Shape::Segment(shapes::SegmentData {
-            p1: points[0].into(),
-            p2: points[1].into(),
+            p1: points[0],
+            p2: points[1],
         })
     }
 }
@@ -123,40 +144,42 @@ impl Tool for Arc {
         4
     }
 
-    fn get_guides(&self, points: &[Pos2], mouse: Pos2, _ui: &PlotUi) -> Vec<plot::Line> {
+    fn get_guides(&self, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape> {
         if points.len() == 1 {
             return vec![
-                utils::circle(points[0], points[0].distance(mouse)),
-                utils::segment(points[0], mouse),
+                circle(points[0], points[0].distance(mouse)),
+                segment(points[0], mouse),
             ];
         } else if points.len() == 2 {
+            let r = points[0].distance(points[1]);
+
             return vec![
-                utils::arc(
+                arc(
                     points[0],
-                    points[0].distance(points[1]),
+                    r,
                     utils::arc_angle(mouse, points[0]),
                     utils::arc_angle(mouse, points[0]) + 1.0,
                 ),
-                utils::segment(
+                segment(
                     points[0],
-                    points[0] + ((mouse - points[0]).normalized() * points[0].distance(points[1])),
+                    points[0] + ((mouse - points[0]).normalized() * r),
                 ),
             ];
         } else if points.len() == 3 {
             let r = points[0].distance(points[1]);
 
             return vec![
-                utils::arc(
+                arc(
                     points[0],
                     r,
                     utils::arc_angle(points[2], points[0]),
                     utils::arc_angle(mouse, points[0]),
                 ),
-                utils::segment(
+                segment(
                     points[0],
                     points[0] + ((points[2] - points[0]).normalized() * r),
                 ),
-                utils::segment(
+                segment(
                     points[0],
                     points[0] + ((mouse - points[0]).normalized() * r),
                 ),
@@ -166,12 +189,191 @@ impl Tool for Arc {
         Vec::new()
     }
 
-    fn get_shape(&self, points: &[Pos2]) -> shapes::Shape {
+    fn get_shape(&self, points: &[Pos]) -> shapes::Shape {
         shapes::Shape::Arc(shapes::ArcData {
-            pos: points[0].into(),
+            pos: points[0],
             r: points[0].distance(points[1]),
             start: utils::arc_angle(points[2], points[0]),
             stop: utils::arc_angle(points[3], points[0]),
         })
     }
 }
+
+impl Tool for PerpendicularBisector {
+    fn name(&self) -> &str {
+        "perpendicular bisector"
+    }
+
+    fn instructions(&self) -> Vec<&str> {
+        vec!["select first point", "select second point"]
+    }
+
+    fn num_points(&self) -> u8 {
+        2
+    }
+
+    fn get_guides(&self, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape> {
+        vec![
+            segment(points[0], mouse),
+            self.get_shape(&[points[0], mouse]),
+        ]
+    }
+
+    fn get_shape(&self, points: &[Pos]) -> shapes::Shape {
+        let midpoint = (points[0] + points[1]) / 2.0;
+        let offset = points[1] - points[0];
+
+        shapes::Shape::Line(shapes::LineData {
+            p1: midpoint,
+            p2: midpoint + Pos::new(-offset.y, offset.x),
+        })
+    }
+
+    fn cost(&self) -> u32 {
+        // Two circles and the line through their intersections
+        3
+    }
+}
+
+impl Tool for Ellipse {
+    fn name(&self) -> &str {
+        "ellipse"
+    }
+
+    fn instructions(&self) -> Vec<&str> {
+        vec![
+            "select first focus",
+            "select second focus",
+            "select point on ellipse",
+        ]
+    }
+
+    fn num_points(&self) -> u8 {
+        3
+    }
+
+    fn get_guides(&self, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape> {
+        foci_guides(self, points, mouse)
+    }
+
+    fn get_shape(&self, points: &[Pos]) -> shapes::Shape {
+        shapes::Shape::Conic(shapes::ConicData::ellipse(points[0], points[1], points[2]))
+    }
+}
+
+impl Tool for Hyperbola {
+    fn name(&self) -> &str {
+        "hyperbola"
+    }
+
+    fn instructions(&self) -> Vec<&str> {
+        vec![
+            "select first focus",
+            "select second focus",
+            "select point on hyperbola",
+        ]
+    }
+
+    fn num_points(&self) -> u8 {
+        3
+    }
+
+    fn get_guides(&self, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape> {
+        foci_guides(self, points, mouse)
+    }
+
+    fn get_shape(&self, points: &[Pos]) -> shapes::Shape {
+        shapes::Shape::Conic(shapes::ConicData::hyperbola(
+            points[0], points[1], points[2],
+        ))
+    }
+}
+
+impl Tool for Parabola {
+    fn name(&self) -> &str {
+        "parabola"
+    }
+
+    fn instructions(&self) -> Vec<&str> {
+        vec!["select focus", "select vertex"]
+    }
+
+    fn num_points(&self) -> u8 {
+        2
+    }
+
+    fn get_guides(&self, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape> {
+        vec![self.get_shape(&[points[0], mouse])]
+    }
+
+    fn get_shape(&self, points: &[Pos]) -> shapes::Shape {
+        shapes::Shape::Conic(shapes::ConicData::parabola(points[0], points[1]))
+    }
+}
+
+impl Tool for ConicThroughPoints {
+    fn name(&self) -> &str {
+        "conic through five points"
+    }
+
+    fn instructions(&self) -> Vec<&str> {
+        vec![
+            "select first point",
+            "select second point",
+            "select third point",
+            "select fourth point",
+            "select fifth point",
+        ]
+    }
+
+    fn num_points(&self) -> u8 {
+        5
+    }
+
+    fn get_guides(&self, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape> {
+        if points.len() < 4 {
+            return points
+                .iter()
+                .zip(points.iter().skip(1).chain([&mouse]))
+                .map(|(p1, p2)| segment(*p1, *p2))
+                .collect();
+        }
+
+        let mut points = points.to_vec();
+        points.push(mouse);
+
+        vec![self.get_shape(&points)]
+    }
+
+    /// Points that do not fix a single conic give a conic with no points, which is not drawn
+    /// and is not added to the figure.
+    fn get_shape(&self, points: &[Pos]) -> shapes::Shape {
+        shapes::Shape::Conic(shapes::ConicData::through(points).unwrap_or_default())
+    }
+}
+
+/// The segment between the foci until both are chosen, then the conic through the mouse.
+fn foci_guides(tool: &dyn Tool, points: &[Pos], mouse: Pos) -> Vec<shapes::Shape> {
+    match points {
+        [focus] => vec![segment(*focus, mouse)],
+        [f1, f2] => vec![tool.get_shape(&[*f1, *f2, mouse])],
+        _ => Vec::new(),
+    }
+}
+
+fn circle(pos: Pos, r: f32) -> shapes::Shape {
+    shapes::Shape::Circle(shapes::CircleData { pos, r })
+}
+
+fn segment(p1: Pos, p2: Pos) -> shapes::Shape {
+    shapes::Shape::Segment(shapes::SegmentData { p1, p2 })
+}
+
+fn arc(pos: Pos, r: f32, start: f32, stop: f32) -> shapes::Shape {
+    shapes::Shape::Arc(shapes::ArcData {
+        pos,
+        r,
+        start,
+        stop,
+    })
+}
@@ -0,0 +1,408 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    pos::Pos,
+    shapes::{ArcData, CircleData, LineData, LocusData, SegmentData, Shape},
+    utils,
+};
+
+/// The kinds of transform that can be defined with clicks, like a tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformKind {
+    Reflect,
+    Rotate,
+    Translate,
+    Dilate,
+    Invert,
+}
+
+impl TransformKind {
+    pub const ALL: [TransformKind; 5] = [
+        TransformKind::Reflect,
+        TransformKind::Rotate,
+        TransformKind::Translate,
+        TransformKind::Dilate,
+        TransformKind::Invert,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransformKind::Reflect => "reflect",
+            TransformKind::Rotate => "rotate",
+            TransformKind::Translate => "translate",
+            TransformKind::Dilate => "dilate",
+            TransformKind::Invert => "invert",
+        }
+    }
+
+    pub fn instructions(&self) -> Vec<&'static str> {
+        match self {
+            TransformKind::Reflect => vec![
+                "select first point of mirror",
+                "select second point of mirror",
+            ],
+            TransformKind::Rotate => vec![
+                "select start of angle",
+                "select centre",
+                "select end of angle",
+            ],
+            TransformKind::Translate => vec!["select start of vector", "select end of vector"],
+            TransformKind::Dilate => vec![
+                "select centre",
+                "select start of unit length",
+                "select end of unit length",
+                "select start of scaled length",
+                "select end of scaled length",
+            ],
+            TransformKind::Invert => vec!["select centre", "select radius"],
+        }
+    }
+
+    pub fn num_points(&self) -> usize {
+        self.instructions().len()
+    }
+}
+
+/// A transform that can be applied to shapes, kept so it can be applied again. Every transform
+/// maps lines and circles to lines and circles, though inversion may swap one for the other.
+/// Conics keep their kind under every transform but inversion, which has no conic image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Transform {
+    /// Reflection in the line through `p1` and `p2`.
+    Reflect {
+        p1: Pos,
+        p2: Pos,
+    },
+    /// Anticlockwise rotation by `angle` radians.
+    Rotate {
+        centre: Pos,
+        angle: f32,
+    },
+    Translate {
+        by: Pos,
+    },
+    Dilate {
+        centre: Pos,
+        ratio: f32,
+    },
+    /// Inversion in the circle about `centre` with radius `r`.
+    Invert {
+        centre: Pos,
+        r: f32,
+    },
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transform::Reflect { p1, p2 } => write!(f, "reflect in {:?}-{:?}", p1, p2),
+            Transform::Rotate { centre, angle } => {
+                write!(f, "rotate {:.2}° about {:?}", angle.to_degrees(), centre)
+            }
+            Transform::Translate { by } => write!(f, "translate by {:?}", by),
+            Transform::Dilate { centre, ratio } => {
+                write!(f, "dilate ×{:.3} from {:?}", ratio, centre)
+            }
+            Transform::Invert { centre, r } => {
+                write!(f, "invert in circle {:?} r={:.3}", centre, r)
+            }
+        }
+    }
+}
+
+impl Transform {
+    /// The transform defined by clicked points, or `None` if they are degenerate.
+    pub fn from_points(kind: TransformKind, points: &[Pos]) -> Option<Self> {
+        let transform = match kind {
+            TransformKind::Reflect if points[0] != points[1] => Transform::Reflect {
+                p1: points[0],
+                p2: points[1],
+            },
+            TransformKind::Rotate => Transform::Rotate {
+                centre: points[1],
+                angle: utils::arc_angle(points[2], points[1])
+                    - utils::arc_angle(points[0], points[1]),
+            },
+            TransformKind::Translate => Transform::Translate {
+                by: points[1] - points[0],
+            },
+            TransformKind::Dilate => Transform::Dilate {
+                centre: points[0],
+                ratio: points[3].distance(points[4]) / points[1].distance(points[2]),
+            },
+            TransformKind::Invert => Transform::Invert {
+                centre: points[0],
+                r: points[0].distance(points[1]),
+            },
+            _ => return None,
+        };
+
+        transform.is_valid().then_some(transform)
+    }
+
+    fn is_valid(&self) -> bool {
+        match self {
+            Transform::Reflect { p1, p2 } => p1.is_finite() && p2.is_finite(),
+            Transform::Rotate { centre, angle } => centre.is_finite() && angle.is_finite(),
+            Transform::Translate { by } => by.is_finite(),
+            Transform::Dilate { centre, ratio } => {
+                centre.is_finite() && ratio.is_finite() && *ratio > 0.0
+            }
+            Transform::Invert { centre, r } => centre.is_finite() && r.is_finite() && *r > 0.0,
+        }
+    }
+
+    pub fn apply_point(&self, point: Pos) -> Pos {
+        match self {
+            Transform::Reflect { p1, p2 } => {
+                let direction = (*p2 - *p1).normalized();
+                let foot = *p1 + direction * (point - *p1).dot(direction);
+
+                foot * 2.0 - point
+            }
+            Transform::Rotate { centre, angle } => {
+                let (sin, cos) = angle.sin_cos();
+                let rel = point - *centre;
+
+                *centre + Pos::new(rel.x * cos - rel.y * sin, rel.x * sin + rel.y * cos)
+            }
+            Transform::Translate { by } => point + *by,
+            Transform::Dilate { centre, ratio } => *centre + (point - *centre) * *ratio,
+            Transform::Invert { centre, r } => {
+                let rel = point - *centre;
+
+                *centre + rel * (r * r / rel.length_sq())
+            }
+        }
+    }
+
+    /// Whether the point has an image. Only the centre of an inversion does not.
+    pub fn maps(&self, point: Pos) -> bool {
+        self.apply_point(point).is_finite()
+    }
+
+    /// The transform that undoes this one.
+    fn inverse(&self) -> Transform {
+        match self {
+            Transform::Rotate { centre, angle } => Transform::Rotate {
+                centre: *centre,
+                angle: -angle,
+            },
+            Transform::Translate { by } => Transform::Translate { by: -*by },
+            Transform::Dilate { centre, ratio } => Transform::Dilate {
+                centre: *centre,
+                ratio: 1.0 / ratio,
+            },
+            Transform::Reflect { .. } | Transform::Invert { .. } => self.clone(),
+        }
+    }
+
+    /// Whether the transform turns anticlockwise arcs clockwise.
+    fn reverses(&self) -> bool {
+        matches!(self, Transform::Reflect { .. } | Transform::Invert { .. })
+    }
+
+    /// The image of `shape`, or `None` for a conic under inversion.
+    pub fn apply(&self, shape: &Shape) -> Option<Shape> {
+        if let Transform::Invert { centre, r } = self {
+            return invert(shape, *centre, *r);
+        }
+
+        Some(match shape {
+            Shape::Circle(circle) => {
+                let pos = self.apply_point(circle.pos);
+                let r = pos.distance(self.apply_point(circle.pos + Pos::new(circle.r, 0.0)));
+
+                Shape::Circle(CircleData { pos, r })
+            }
+            Shape::Line(line) => Shape::Line(LineData {
+                p1: self.apply_point(line.p1),
+                p2: self.apply_point(line.p2),
+            }),
+            Shape::Segment(segment) => Shape::Segment(SegmentData {
+                p1: self.apply_point(segment.p1),
+                p2: self.apply_point(segment.p2),
+            }),
+            Shape::Arc(arc) => {
+                let pos = self.apply_point(arc.pos);
+                let (start, stop) = arc.endpoints();
+                let (start, stop) = (self.apply_point(start), self.apply_point(stop));
+                let (start, stop) = if self.reverses() {
+                    (stop, start)
+                } else {
+                    (start, stop)
+                };
+
+                Shape::Arc(ArcData {
+                    pos,
+                    r: pos.distance(start),
+                    start: utils::arc_angle(start, pos),
+                    stop: utils::arc_angle(stop, pos),
+                })
+            }
+            Shape::Conic(conic) => {
+                let inverse = self.inverse();
+
+                Shape::Conic(conic.transformed(|p| inverse.apply_point(p)))
+            }
+            Shape::Locus(locus) => Shape::Locus(self.apply_locus(locus)),
+        })
+    }
+
+    /// Moves each sample of a locus, which under inversion only follows the true image
+    /// closely where the samples are close together.
+    fn apply_locus(&self, locus: &LocusData) -> LocusData {
+        LocusData {
+            pieces: locus
+                .pieces
+                .iter()
+                .map(|piece| {
+                    piece
+                        .iter()
+                        .map(|&p| self.apply_point(p))
+                        .filter(|p| p.is_finite())
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Whether `distance` is zero, to within rounding of values around `scale`.
+fn negligible(distance: f32, scale: f32) -> bool {
+    distance.abs() <= 1e-5 * (1.0 + scale)
+}
+
+/// The image of a shape under inversion in the circle about `centre` with radius `r`.
+///
+/// Lines through the centre are their own images and other lines become circles through the
+/// centre, while circles through the centre become lines. A segment or arc that passes
+/// through the centre would become two rays, which are drawn as the whole line.
+fn invert(shape: &Shape, centre: Pos, r: f32) -> Option<Shape> {
+    let transform = Transform::Invert { centre, r };
+    let image = |p: Pos| transform.apply_point(p);
+
+    Some(match shape {
+        Shape::Line(line) => {
+            if negligible(Shape::Line(line.clone()).distance(centre), line.p1.length()) {
+                return Some(shape.clone());
+            }
+
+            let (pos, radius) = line_image(line, centre, r);
+
+            Shape::Circle(CircleData { pos, r: radius })
+        }
+        Shape::Circle(circle) => match circle_image(circle, centre, r) {
+            Ok(circle) => Shape::Circle(circle),
+            Err(line) => Shape::Line(line),
+        },
+        Shape::Segment(segment) => {
+            let line = segment.line();
+            let (p1, p2) = (image(segment.p1), image(segment.p2));
+
+            if negligible(Shape::Line(line.clone()).distance(centre), line.p1.length()) {
+                // Collinear with the centre, so the image is part of the same line
+                return Some(match negligible(shape.distance(centre), line.p1.length()) {
+                    true => Shape::Line(line),
+                    false => Shape::Segment(SegmentData { p1, p2 }),
+                });
+            }
+
+            let (pos, radius) = line_image(&line, centre, r);
+
+            arc_through(pos, radius, p1, image((segment.p1 + segment.p2) / 2.0), p2)
+        }
+        Shape::Arc(arc) => {
+            let (start, stop) = arc.endpoints();
+            let (p1, p2) = (image(start), image(stop));
+
+            match circle_image(&arc.circle(), centre, r) {
+                Ok(circle) => {
+                    let middle = arc_middle(arc);
+
+                    arc_through(circle.pos, circle.r, p1, image(middle), p2)
+                }
+                Err(_) if arc.valid_points(vec![centre]).is_empty() => {
+                    Shape::Segment(SegmentData { p1, p2 })
+                }
+                Err(line) => Shape::Line(line),
+            }
+        }
+        Shape::Conic(_) => return None,
+        Shape::Locus(locus) => Shape::Locus(transform.apply_locus(locus)),
+    })
+}
+
+/// The centre and radius of the circle through `centre` that a line not through it maps to.
+fn line_image(line: &LineData, centre: Pos, r: f32) -> (Pos, f32) {
+    let direction = (line.p2 - line.p1).normalized();
+    let foot = line.p1 + direction * (centre - line.p1).dot(direction);
+    let d = foot.distance(centre);
+    let radius = r * r / (2.0 * d);
+
+    (centre + (foot - centre).normalized() * radius, radius)
+}
+
+/// The image of a circle: another circle, or a line if it passes through the centre.
+fn circle_image(circle: &CircleData, centre: Pos, r: f32) -> Result<CircleData, LineData> {
+    let offset = circle.pos - centre;
+
+    // The points of the circle nearest and furthest from the centre stay on a diameter
+    let axis = if offset.length_sq() > 0.0 {
+        offset.normalized()
+    } else {
+        Pos::new(1.0, 0.0)
+    };
+
+    let far_image = centre + axis * (r * r / (offset.length() + circle.r));
+
+    if negligible(circle.pos.distance(centre) - circle.r, circle.r) {
+        let normal = Pos::new(-axis.y, axis.x);
+
+        return Err(LineData {
+            p1: far_image,
+            p2: far_image + normal,
+        });
+    }
+
+    let near = circle.pos - axis * circle.r;
+    let near_image = centre + (near - centre) * (r * r / (near - centre).length_sq());
+
+    Ok(CircleData {
+        pos: (near_image + far_image) / 2.0,
+        r: near_image.distance(far_image) / 2.0,
+    })
+}
+
+fn arc_middle(arc: &ArcData) -> Pos {
+    let mut stop = arc.stop;
+
+    if stop < arc.start {
+        stop += 2.0 * std::f32::consts::PI;
+    }
+
+    arc.pos + Pos::from_angle((arc.start + stop) / 2.0) * arc.r
+}
+
+/// The arc of the circle about `pos` from `p1` to `p2` that passes through `middle`.
+fn arc_through(pos: Pos, r: f32, p1: Pos, middle: Pos, p2: Pos) -> Shape {
+    let arc = ArcData {
+        pos,
+        r,
+        start: utils::arc_angle(p1, pos),
+        stop: utils::arc_angle(p2, pos),
+    };
+
+    if arc.valid_points(vec![middle]).is_empty() {
+        Shape::Arc(ArcData {
+            start: arc.stop,
+            stop: arc.start,
+            ..arc
+        })
+    } else {
+        Shape::Arc(arc)
+    }
+}
@@ -1,86 +1,114 @@
-use std::f32::consts::{PI, TAU};
-
-use egui::{
-    plot::{self, Line, PlotPoints},
-    remap, Pos2,
-};
-
-pub fn circle(pos: Pos2, r: f32) -> plot::Line {
-    let n = 512;
-    let circle_points: PlotPoints = (0..=n)
-        .map(|i| {
-            let t = remap(i as f32, 0.0..=(n as f32), 0.0..=TAU);
-            [(r * t.cos() + pos.x) as f64, (r * t.sin() + pos.y) as f64]
-        })
-        .collect();
+use std::f32::consts::PI;
 
-    Line::new(circle_points)
-}
+use super::pos::Pos;
 
-pub fn line(p1: Pos2, p2: Pos2, top: f64, bottom: f64) -> plot::Line {
-    let m = (p2.y - p1.y) as f64 / (p2.x - p1.x) as f64;
-    let c = p1.y as f64 - (m * p1.x as f64);
+pub fn arc_angle(point: Pos, centre: Pos) -> f32 {
+    let rel = (point - centre).normalized();
 
-    if m.is_infinite() {
-        return Line::new(PlotPoints::new(vec![
-            [p1.x as f64, top * 1.5],
-            [p1.x as f64, bottom * 1.5],
-        ]));
+    let mut angle = libm::acos(Pos::new(-1.0, 0.0).dot(rel) as f64) as f32;
+
+    if angle.is_nan() {
+        angle = 0.0;
     }
 
-    Line::new(PlotPoints::from_explicit_callback(
-        move |x| (x * m) + c,
-        ..,
-        512,
-    ))
+    let sign = if rel.y == 0.0 {
+        1.0
+    } else {
+        rel.y / rel.y.abs()
+    };
+
+    (angle * sign - PI).abs()
 }
 
-pub fn segment(p1: Pos2, p2: Pos2) -> plot::Line {
-    let n = 512;
+/// Real roots of the polynomial with coefficients `coefficients`, lowest power first. Each
+/// root lies between two turning points, so the derivative's roots split the line into
+/// pieces that are searched by bisection. Repeated roots, as at tangents, are found once.
+pub fn polynomial_roots(coefficients: &[f64]) -> Vec<f64> {
+    let scale = coefficients.iter().fold(0.0_f64, |max, c| max.max(c.abs()));
 
-    let line_points: PlotPoints = (0..=n)
-        .map(|i| {
-            let x = remap(i as f32, 0.0..=(n as f32), p1.x..=p2.x);
-            let y = remap(i as f32, 0.0..=(n as f32), p1.y..=p2.y);
-            [x as f64, y as f64]
-        })
-        .collect();
+    let Some(degree) = coefficients.iter().rposition(|c| c.abs() > 1e-12 * scale) else {
+        return Vec::new();
+    };
 
-    Line::new(line_points)
-}
+    let coefficients = &coefficients[..=degree];
 
-pub fn arc(pos: Pos2, r: f32, a1: f32, a2: f32) -> plot::Line {
-    let stop = if a1 > a2 { a2 + TAU } else { a2 };
+    match degree {
+        0 => return Vec::new(),
+        1 => return vec![-coefficients[0] / coefficients[1]],
+        _ => {}
+    }
 
-    let n = 512;
-    let arc_points: PlotPoints = (0..=n)
-        .map(|i| {
-            let t = remap(i as f32, 0.0..=(n as f32), a1..=stop);
-            [(r * t.cos() + pos.x) as f64, (r * t.sin() + pos.y) as f64]
-        })
+    let value = |x: f64| coefficients.iter().rev().fold(0.0, |sum, c| sum * x + c);
+
+    let derivative: Vec<f64> = coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| i as f64 * c)
         .collect();
 
-    Line::new(arc_points)
-}
+    // Every root is within this bound (Cauchy's bound)
+    let bound = 1.0
+        + coefficients[..degree]
+            .iter()
+            .fold(0.0_f64, |max, c| max.max((c / coefficients[degree]).abs()));
+
+    let mut stops = vec![-bound];
+    stops.extend(
+        polynomial_roots(&derivative)
+            .into_iter()
+            .filter(|x| x.abs() < bound),
+    );
+    stops.push(bound);
+    stops.sort_by(f64::total_cmp);
+
+    let mut roots = Vec::new();
+
+    for pair in stops.windows(2) {
+        let (mut low, mut high) = (pair[0], pair[1]);
+        let low_sign = value(low).signum();
+
+        if low_sign == value(high).signum() {
+            continue;
+        }
+
+        for _ in 0..200 {
+            let middle = (low + high) / 2.0;
+
+            if middle == low || middle == high {
+                break;
+            }
+
+            if value(middle).signum() == low_sign {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        roots.push((low + high) / 2.0);
+    }
 
-pub fn arc_angle(point: Pos2, centre: Pos2) -> f32 {
-    let rel = (point - centre).normalized();
+    // Size of the terms near x, for judging when a value is zero to within rounding
+    let size = |x: f64| {
+        let x = x.abs().max(1e-6 * bound);
 
-    let mut angle = libm::acos(Pos2::new(-1.0, 0.0).to_vec2().dot(rel) as f64) as f32;
+        coefficients
+            .iter()
+            .rev()
+            .fold(0.0, |sum, c| sum * x + c.abs())
+    };
 
-    if angle.is_nan() {
-        angle = 0.0;
-    }
+    let close = |a: f64, b: f64| (a - b).abs() <= 1e-4 * (1.0 + a.abs());
 
-    let sign = if rel.y == 0.0 {
-        1.0
-    } else {
-        rel.y / rel.y.abs()
-    };
+    for &stop in &stops[1..stops.len() - 1] {
+        if value(stop).abs() <= 1e-7 * size(stop) && !roots.iter().any(|&root| close(root, stop)) {
+            roots.push(stop);
+        }
+    }
 
-    (angle * sign - PI).abs()
-}
+    roots.sort_by(f64::total_cmp);
+    roots.dedup_by(|a, b| close(*a, *b));
 
-pub fn within(v: f32, a: f32, b: f32) -> bool {
-    f32::min(a, b) <= v && v <= f32::max(a, b)
+    roots
 }
@@ -0,0 +1,8 @@
+//! Straightedge and compass constructions.
+//!
+//! [`engine`] holds the geometry, documents and file formats and does not depend on a GUI.
+//! With the `gui` feature, [`adapter`] draws an engine into an egui plot.
+
+#[cfg(feature = "gui")]
+pub mod adapter;
+pub mod engine;
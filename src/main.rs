@@ -1,9 +1,19 @@
+#[cfg(feature = "gui")]
 mod app;
-mod engine;
+mod cli;
+#[cfg(feature = "gui")]
+mod recovery;
+#[cfg(feature = "gui")]
 mod ui;
-mod de;
 
+#[cfg(feature = "gui")]
 fn main() -> eframe::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     let native_options = eframe::NativeOptions {
         fullscreen: true,
         ..Default::default()
@@ -15,3 +25,14 @@ fn main() -> eframe::Result<()> {
         Box::new(|_cc| Box::new(app::Euclid::new())),
     )
 }
+
+#[cfg(not(feature = "gui"))]
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.is_empty() {
+        args.push("help".to_owned());
+    }
+
+    std::process::exit(cli::run(&args));
+}
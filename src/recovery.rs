@@ -0,0 +1,88 @@
+//! Keeps a copy of the document being worked on, so that it is not lost if the app crashes.
+
+use std::time::{Duration, Instant};
+
+use euclid::engine::{document::Format, Engine};
+
+/// Where the document is written while the app is open and when it quits.
+pub const RECOVERY_FILE: &str = "saves/.recovery.yml";
+
+/// Exists while the app is open. If it is there when the app starts, the last session did
+/// not exit cleanly.
+const SESSION_FILE: &str = "saves/.session";
+
+pub struct Recovery {
+    interval: Duration,
+    last_save: Instant,
+    /// What was last written, so an unchanged document is not written again.
+    saved: Option<String>,
+}
+
+impl Recovery {
+    /// Starts a session that saves every `interval`, or only on quit if it is zero. Returns the
+    /// document left by the last session if that session did not exit cleanly, and whether
+    /// this session could be marked as started. The document is returned even if it could not.
+    pub fn start(interval: Duration) -> (Self, Result<Option<String>, String>, Result<(), String>) {
+        let unclean = std::path::Path::new(SESSION_FILE).exists();
+
+        let recovered = if unclean {
+            match std::fs::read_to_string(RECOVERY_FILE) {
+                Ok(contents) => Ok(Some(contents)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(format!("could not read {}: {}", RECOVERY_FILE, e)),
+            }
+        } else {
+            Ok(None)
+        };
+
+        let started = std::fs::create_dir_all("saves")
+            .and_then(|()| std::fs::write(SESSION_FILE, ""))
+            .map_err(|e| format!("could not start autosave: {}", e));
+
+        let recovery = Recovery {
+            interval,
+            last_save: Instant::now(),
+            saved: None,
+        };
+
+        (recovery, recovered, started)
+    }
+
+    /// Saves the document if the interval has passed since the last save.
+    pub fn tick(&mut self, engine: &Engine) -> Result<(), String> {
+        if self.interval.is_zero() || self.last_save.elapsed() < self.interval {
+            return Ok(());
+        }
+
+        self.save(engine)
+    }
+
+    /// Saves the document if it has changed since the last save.
+    pub fn save(&mut self, engine: &Engine) -> Result<(), String> {
+        self.last_save = Instant::now();
+
+        let contents = engine.save(Format::Yaml).map_err(|e| e.to_string())?;
+
+        if self.saved.as_ref() == Some(&contents) {
+            return Ok(());
+        }
+
+        // Write next to the file and rename it over, so a crash mid-write leaves the old copy
+        let temporary = format!("{}.tmp", RECOVERY_FILE);
+
+        std::fs::write(&temporary, &contents)
+            .and_then(|()| std::fs::rename(&temporary, RECOVERY_FILE))
+            .map_err(|e| format!("could not autosave: {}", e))?;
+
+        self.saved = Some(contents);
+
+        Ok(())
+    }
+
+    /// Saves the document and marks the session as having exited cleanly.
+    pub fn finish(&mut self, engine: &Engine) -> Result<(), String> {
+        self.save(engine)?;
+
+        std::fs::remove_file(SESSION_FILE).map_err(|e| format!("could not end session: {}", e))
+    }
+}
@@ -0,0 +1,127 @@
+use egui::{Color32, RichText};
+use euclid::engine::Engine;
+
+enum Edit {
+    Delete(usize),
+    Move(usize, usize),
+    Select(usize),
+}
+
+/// Lists every construction in drawing order. Clicking one selects it, and each can be hidden,
+/// recoloured, deleted or moved. Right clicking gives its width, playback caption and layer.
+pub fn show(ui: &mut egui::Ui, engine: &mut Engine) {
+    let layers: Vec<String> = engine
+        .layers
+        .iter()
+        .map(|layer| layer.name.clone())
+        .collect();
+    let len = engine.constructions.len();
+    let mut edit = None;
+
+    egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            for i in 0..len {
+                let description = engine.describe(i);
+                let selected = engine.selection.contains(&i);
+                let (editable, can_raise) = (engine.can_edit(i), i > 0 && engine.can_edit(i - 1));
+                let text_color = Color32::from(engine.config.text_color);
+                let construction = &mut engine.constructions[i];
+
+                ui.horizontal(|ui| {
+                    let mut color = Color32::from(construction.color);
+
+                    if egui::color_picker::color_edit_button_srgba(
+                        ui,
+                        &mut color,
+                        egui::color_picker::Alpha::OnlyBlend,
+                    )
+                    .changed()
+                    {
+                        construction.color = color.into();
+                    }
+
+                    let mut shown = !construction.hidden;
+
+                    if ui.checkbox(&mut shown, "").on_hover_text("shown").changed() {
+                        construction.hidden = !shown;
+                    }
+
+                    let text = RichText::new(description).monospace().color(if shown {
+                        text_color
+                    } else {
+                        text_color.gamma_multiply(0.4)
+                    });
+
+                    let response = ui
+                        .selectable_label(selected, text)
+                        .on_hover_text("right click for width, caption and layer");
+
+                    if response.clicked() {
+                        edit = Some(Edit::Select(i));
+                    }
+
+                    response.context_menu(|ui| {
+                        ui.add(egui::Slider::new(&mut construction.width, 0.5..=5.0).text("width"));
+
+                        let mut caption = construction.caption.clone().unwrap_or_default();
+
+                        ui.horizontal(|ui| {
+                            ui.label("caption");
+
+                            if ui.text_edit_singleline(&mut caption).changed() {
+                                construction.caption = (!caption.is_empty()).then_some(caption);
+                            }
+                        });
+
+                        egui::ComboBox::from_id_source(("history-layer", i))
+                            .selected_text(&construction.layer)
+                            .show_ui(ui, |ui| {
+                                for layer in &layers {
+                                    ui.selectable_value(
+                                        &mut construction.layer,
+                                        layer.clone(),
+                                        layer,
+                                    );
+                                }
+                            });
+                    });
+
+                    ui.weak(&construction.layer);
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.add_enabled_ui(editable, |ui| {
+                            if ui.small_button("x").on_hover_text("delete").clicked() {
+                                edit = Some(Edit::Delete(i));
+                            }
+
+                            if i + 1 < len
+                                && ui.small_button("⬇").on_hover_text("draw later").clicked()
+                            {
+                                edit = Some(Edit::Move(i, i + 1));
+                            }
+
+                            if can_raise
+                                && ui.small_button("⬆").on_hover_text("draw earlier").clicked()
+                            {
+                                edit = Some(Edit::Move(i, i - 1));
+                            }
+                        });
+                    });
+                });
+            }
+        });
+
+    match edit {
+        Some(Edit::Delete(i)) => engine.delete_construction(i),
+        Some(Edit::Move(from, to)) => engine.move_construction(from, to),
+        Some(Edit::Select(i)) => match engine.selection.iter().position(|&j| j == i) {
+            Some(n) => {
+                engine.selection.remove(n);
+            }
+            None => engine.selection.push(i),
+        },
+        None => {}
+    }
+}
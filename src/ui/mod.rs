@@ -1,2 +1,4 @@
 pub mod grid;
+pub mod history;
+pub mod playback;
 pub mod window;
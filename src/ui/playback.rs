@@ -0,0 +1,42 @@
+use euclid::engine::{playback::Playback, Engine};
+
+/// Turns step by step playback on and off, and steps through or plays the constructions.
+pub fn show(ui: &mut egui::Ui, engine: &mut Engine) {
+    let len = engine.constructions.len();
+    let mut on = engine.playback.is_some();
+
+    if ui.checkbox(&mut on, "replay the construction").changed() {
+        engine.playback = on.then(Playback::default);
+        engine.clear_points();
+    }
+
+    let Some(playback) = &mut engine.playback else {
+        return;
+    };
+
+    ui.horizontal(|ui| {
+        if ui.button("⏮").on_hover_text("previous").clicked() {
+            playback.previous();
+        }
+
+        if playback.playing {
+            if ui.button("⏸").on_hover_text("pause").clicked() {
+                playback.playing = false;
+            }
+        } else if ui.button("▶").on_hover_text("play").clicked() {
+            playback.play(len);
+        }
+
+        if ui.button("⏭").on_hover_text("next").clicked() {
+            playback.next(len);
+        }
+
+        ui.label(format!("{} / {}", playback.step.min(len), len));
+    });
+
+    ui.add(
+        egui::Slider::new(&mut playback.speed, 0.1..=5.0)
+            .logarithmic(true)
+            .text("steps / s"),
+    );
+}
@@ -1,13 +1,101 @@
 use std::ffi::OsString;
 
+use euclid::engine::{
+    analysis::Analysis, ggb::ImportSummary, script::ScriptError, shapes::Construction,
+};
+
+pub enum ScriptAction {
+    None,
+    Apply,
+    LoadDocument,
+}
+
+pub struct ScriptWindow<'a> {
+    open: &'a mut bool,
+    source: &'a mut String,
+    error: Option<&'a ScriptError>,
+}
+
+impl<'a> ScriptWindow<'a> {
+    pub fn new(open: &'a mut bool, source: &'a mut String, error: Option<&'a ScriptError>) -> Self {
+        ScriptWindow {
+            open,
+            source,
+            error,
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> ScriptAction {
+        if !*self.open {
+            return ScriptAction::None;
+        }
+
+        let mut action = ScriptAction::None;
+
+        egui::Window::new("script")
+            .open(self.open)
+            .title_bar(true)
+            .collapsible(true)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(self.source)
+                                .code_editor()
+                                .desired_rows(12)
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+
+                match self.error {
+                    Some(error) => ui.colored_label(ui.visuals().error_fg_color, error.to_string()),
+                    None => ui.weak("ok"),
+                };
+
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(self.error.is_none(), |ui| {
+                        if ui.button("add to document").clicked() {
+                            action = ScriptAction::Apply;
+                        }
+                    });
+
+                    if ui.button("load from document").clicked() {
+                        action = ScriptAction::LoadDocument;
+                    }
+                });
+            });
+
+        action
+    }
+}
+
 pub struct SaveWindow<'a> {
     open: &'a mut bool,
     file_name: &'a mut Option<String>,
+    title: &'a str,
+    hint: Option<&'a str>,
 }
 
 impl<'a> SaveWindow<'a> {
     pub fn new(open: &'a mut bool, file_name: &'a mut Option<String>) -> Self {
-        SaveWindow { open, file_name }
+        SaveWindow {
+            open,
+            file_name,
+            title: "save file",
+            hint: None,
+        }
+    }
+
+    pub fn title(mut self, title: &'a str) -> Self {
+        self.title = title;
+        self
+    }
+
+    pub fn hint(mut self, hint: &'a str) -> Self {
+        self.hint = Some(hint);
+        self
     }
 
     pub fn show(&mut self, ctx: &egui::Context) -> bool {
@@ -19,8 +107,8 @@ impl<'a> SaveWindow<'a> {
 
         let mut file_name = self.file_name.clone().unwrap_or("unnamed".to_string());
 
-        egui::Window::new("save file")
-            .open(&mut self.open)
+        egui::Window::new(self.title)
+            .open(self.open)
             .title_bar(true)
             .collapsible(false)
             .resizable(false)
@@ -31,6 +119,10 @@ impl<'a> SaveWindow<'a> {
                     ui.text_edit_singleline(&mut file_name);
                 });
 
+                if let Some(hint) = self.hint {
+                    ui.weak(hint);
+                }
+
                 closed = ui.button("save").clicked();
             });
 
@@ -43,11 +135,29 @@ impl<'a> SaveWindow<'a> {
 pub struct OpenWindow<'a> {
     open: &'a mut bool,
     file_name: &'a mut Option<String>,
+    title: &'a str,
+    extensions: &'a [&'a str],
 }
 
 impl<'a> OpenWindow<'a> {
     pub fn new(open: &'a mut bool, file_name: &'a mut Option<String>) -> Self {
-        OpenWindow { open, file_name }
+        OpenWindow {
+            open,
+            file_name,
+            title: "open file",
+            extensions: &["yml", "yaml", "json"],
+        }
+    }
+
+    pub fn title(mut self, title: &'a str) -> Self {
+        self.title = title;
+        self
+    }
+
+    /// Only lists files with one of these extensions.
+    pub fn extensions(mut self, extensions: &'a [&'a str]) -> Self {
+        self.extensions = extensions;
+        self
     }
 
     pub fn show(&mut self, ctx: &egui::Context) -> bool {
@@ -57,29 +167,28 @@ impl<'a> OpenWindow<'a> {
 
         let mut closed = false;
 
+        // A missing saves folder just means there is nothing to open yet
         let file_names = std::fs::read_dir("saves")
-            .expect("could not read saves folder")
-            .map(|res| res.map(|e| e.file_name()))
-            .collect::<Result<Vec<_>, std::io::Error>>()
-            .expect("could not read saves folder");
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| get_file_name(&entry.ok()?.file_name(), self.extensions))
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
 
         let mut file_name = if self.file_name.is_none()
-            || !file_names
-                .iter()
-                .map(|f| get_file_name(f))
-                .collect::<Vec<String>>()
-                .contains(&self.file_name.clone().unwrap_or("".to_string()))
+            || !file_names.contains(&self.file_name.clone().unwrap_or("".to_string()))
         {
             file_names
                 .first()
-                .map(|f| get_file_name(f))
+                .cloned()
                 .unwrap_or("no saves".to_string())
         } else {
             self.file_name.clone().unwrap()
         };
 
-        egui::Window::new("open file")
-            .open(&mut self.open)
+        egui::Window::new(self.title)
+            .open(self.open)
             .title_bar(true)
             .collapsible(false)
             .resizable(false)
@@ -92,11 +201,7 @@ impl<'a> OpenWindow<'a> {
                         .selected_text(file_name.clone())
                         .show_ui(ui, |ui| {
                             for file in file_names.iter() {
-                                ui.selectable_value(
-                                    &mut file_name,
-                                    get_file_name(file),
-                                    get_file_name(file),
-                                );
+                                ui.selectable_value(&mut file_name, file.clone(), file);
                             }
                         });
                 });
@@ -110,9 +215,202 @@ impl<'a> OpenWindow<'a> {
     }
 }
 
-fn get_file_name(file: &OsString) -> String {
-    file.to_string_lossy()
-        .strip_suffix(".yml")
-        .unwrap()
-        .to_string()
+pub struct SummaryWindow<'a> {
+    summary: &'a mut Option<ImportSummary>,
+}
+
+impl<'a> SummaryWindow<'a> {
+    pub fn new(summary: &'a mut Option<ImportSummary>) -> Self {
+        SummaryWindow { summary }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let Some(summary) = self.summary.as_ref() else {
+            return;
+        };
+
+        let mut closed = false;
+
+        egui::Window::new("import summary")
+            .title_bar(true)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("points: {}", summary.points));
+                ui.label(format!("constructions: {}", summary.constructions));
+
+                if !summary.unsupported.is_empty() {
+                    ui.separator();
+                    ui.label("not imported:");
+
+                    for object in &summary.unsupported {
+                        ui.monospace(format!(
+                            "{} ({}): {}",
+                            object.label, object.kind, object.reason
+                        ));
+                    }
+                }
+
+                closed = ui.button("ok").clicked();
+            });
+
+        if closed {
+            *self.summary = None;
+        }
+    }
+}
+
+/// Shows an error until it is dismissed.
+pub struct ErrorWindow<'a> {
+    error: &'a mut Option<String>,
+}
+
+impl<'a> ErrorWindow<'a> {
+    pub fn new(error: &'a mut Option<String>) -> Self {
+        ErrorWindow { error }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let Some(error) = self.error.as_ref() else {
+            return;
+        };
+
+        let mut closed = false;
+
+        egui::Window::new("error")
+            .title_bar(true)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+
+                closed = ui.button("ok").clicked();
+            });
+
+        if closed {
+            *self.error = None;
+        }
+    }
+}
+
+pub enum RecoveryAction {
+    None,
+    Restore,
+    Discard,
+}
+
+/// Offers to restore the document left by a session that did not exit cleanly.
+pub struct RecoveryWindow<'a> {
+    recovered: &'a Option<String>,
+}
+
+impl<'a> RecoveryWindow<'a> {
+    pub fn new(recovered: &'a Option<String>) -> Self {
+        RecoveryWindow { recovered }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> RecoveryAction {
+        if self.recovered.is_none() {
+            return RecoveryAction::None;
+        }
+
+        let mut action = RecoveryAction::None;
+
+        egui::Window::new("restore session")
+            .title_bar(true)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("euclid did not close properly last time.");
+                ui.label("restore the construction you were working on?");
+
+                ui.horizontal(|ui| {
+                    if ui.button("restore").clicked() {
+                        action = RecoveryAction::Restore;
+                    }
+
+                    if ui.button("discard").clicked() {
+                        action = RecoveryAction::Discard;
+                    }
+                });
+            });
+
+        action
+    }
+}
+
+pub struct PropertiesWindow<'a> {
+    analysis: &'a mut Option<Analysis>,
+    constructions: &'a [Construction],
+}
+
+impl<'a> PropertiesWindow<'a> {
+    pub fn new(analysis: &'a mut Option<Analysis>, constructions: &'a [Construction]) -> Self {
+        PropertiesWindow {
+            analysis,
+            constructions,
+        }
+    }
+
+    /// Lists the properties found, highlighting the one under the pointer. Closing the
+    /// window turns the analyser off.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let Some(analysis) = self.analysis.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut highlighted = None;
+
+        egui::Window::new("properties")
+            .open(&mut open)
+            .title_bar(true)
+            .collapsible(true)
+            .resizable(true)
+            .show(ctx, |ui| {
+                if analysis.properties.is_empty() {
+                    ui.weak("nothing found");
+                }
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (i, property) in analysis.properties.iter().enumerate() {
+                            let text = property.describe(self.constructions);
+
+                            if ui.monospace(text).hovered() {
+                                highlighted = Some(i);
+                            }
+                        }
+                    });
+            });
+
+        analysis.highlighted = highlighted;
+
+        if !open {
+            *self.analysis = None;
+        }
+    }
+}
+
+fn get_file_name(file: &OsString, extensions: &[&str]) -> Option<String> {
+    let name = file.to_string_lossy();
+
+    extensions
+        .iter()
+        .any(|extension| name.ends_with(&format!(".{}", extension)))
+        .then(|| name.to_string())
+}
+
+/// Appends `extension` to a file name that does not already end with it or one of `alternatives`.
+pub fn with_extension(file_name: &str, extension: &str, alternatives: &[&str]) -> String {
+    let has_extension = std::iter::once(&extension)
+        .chain(alternatives)
+        .any(|ext| file_name.ends_with(&format!(".{}", ext)));
+
+    if has_extension {
+        file_name.to_owned()
+    } else {
+        format!("{}.{}", file_name, extension)
+    }
 }